@@ -0,0 +1,64 @@
+use crate::mjai::Event;
+#[cfg(feature = "anonymize-hash")]
+use std::fmt::Write;
+
+#[cfg(feature = "anonymize-hash")]
+use sha2::{Digest, Sha256};
+
+/// Replaces player names so that a log can be shared without revealing who
+/// played it. Empty names, such as the absent seat in sanma, are kept empty.
+#[derive(Debug, Clone, Default)]
+pub enum Anonymizer {
+    /// "Aさん" to "Dさん" by seat, the same as tenhou.net does for guests.
+    #[default]
+    Seat,
+    /// "Player-" followed by the first 8 hex digits of the SHA-256 of the
+    /// salt and the name, so that the same player gets the same alias in
+    /// different logs as long as the salt is the same.
+    ///
+    /// Requires the `anonymize-hash` feature.
+    #[cfg(feature = "anonymize-hash")]
+    Hash { salt: String },
+}
+
+impl Anonymizer {
+    pub fn alias(&self, seat: usize, name: &str) -> String {
+        if name.is_empty() {
+            return String::new();
+        }
+
+        match self {
+            Anonymizer::Seat => format!("{}さん", (b'A' + (seat % 4) as u8) as char),
+            #[cfg(feature = "anonymize-hash")]
+            Anonymizer::Hash { salt } => {
+                let digest = Sha256::new()
+                    .chain_update(salt.as_bytes())
+                    .chain_update([0])
+                    .chain_update(name.as_bytes())
+                    .finalize();
+                digest
+                    .iter()
+                    .take(4)
+                    .fold("Player-".to_owned(), |mut s, b| {
+                        let _ = write!(s, "{:02x}", b);
+                        s
+                    })
+            }
+        }
+    }
+
+    pub fn anonymize_names(&self, names: &mut [String; 4]) {
+        for (seat, name) in names.iter_mut().enumerate() {
+            *name = self.alias(seat, name);
+        }
+    }
+
+    /// Replaces the names in `StartGame`.
+    pub fn anonymize_events(&self, events: &mut [Event]) {
+        for event in events {
+            if let Event::StartGame { names, .. } = event {
+                self.anonymize_names(names);
+            }
+        }
+    }
+}
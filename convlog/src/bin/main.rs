@@ -1,17 +1,382 @@
-use std::io;
+use std::fs::{self, File};
+use std::io::{self, prelude::*, BufReader, BufWriter};
+use std::path::Path;
+use std::process;
 
-use convlog::tenhou;
+use anyhow::{bail, Context, Result};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use convlog::{majsoul, mjai, mjlog, tenhou, Anonymizer, KyokuFilter};
 use serde_json as json;
 
 fn main() {
-    let stdin = io::stdin();
+    if let Err(err) = run() {
+        eprintln!("error: {:#}", err);
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
+    let input_arg = || {
+        Arg::with_name("INPUT")
+            .index(1)
+            .help("Input file. Reads from stdin if omitted or \"-\"")
+    };
+    let in_format_arg = || {
+        Arg::with_name("in-format")
+            .short("f")
+            .long("in-format")
+            .takes_value(true)
+            .value_name("FORMAT")
+            .possible_values(&["tenhou", "mjlog", "majsoul"])
+            .default_value("tenhou")
+            .help("Format of the input log")
+    };
+    let out_file_arg = || {
+        Arg::with_name("out-file")
+            .short("o")
+            .long("out-file")
+            .takes_value(true)
+            .value_name("FILE")
+            .help("Output file. Writes to stdout if omitted or \"-\"")
+    };
+
+    let matches = App::new("convlog")
+        .about("Converts, inspects and validates mahjong logs")
+        .setting(AppSettings::VersionlessSubcommands)
+        .after_help(
+            "If no subcommand is given, acts as to-mjai reading a tenhou.net/6 log from stdin.",
+        )
+        .subcommand(
+            SubCommand::with_name("to-mjai")
+                .about("Converts a log into mjai events, one per line")
+                .arg(input_arg())
+                .arg(in_format_arg())
+                .arg(out_file_arg())
+                .arg(
+                    Arg::with_name("lenient")
+                        .long("lenient")
+                        .help("Skip or patch broken kyokus instead of failing, with warnings"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("to-tenhou")
+                .about("Converts mjai events into a tenhou.net/6 log")
+                .arg(input_arg())
+                .arg(out_file_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("split")
+                .about("Splits a log into one tenhou.net/6 log per kyoku")
+                .arg(input_arg())
+                .arg(in_format_arg())
+                .arg(
+                    Arg::with_name("out-dir")
+                        .short("d")
+                        .long("out-dir")
+                        .takes_value(true)
+                        .value_name("DIR")
+                        .required(true)
+                        .help("Directory to write the logs to, named like \"E1.0.json\""),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("filter")
+                .about("Keeps only the specified kyokus of a log")
+                .arg(input_arg())
+                .arg(in_format_arg())
+                .arg(out_file_arg())
+                .arg(
+                    Arg::with_name("kyokus")
+                        .short("k")
+                        .long("kyokus")
+                        .takes_value(true)
+                        .value_name("ARRAY")
+                        .required(true)
+                        .help(
+                            "Kyokus to keep, e.g. \"E1,E4,S3.1\", \"E3-S2\", \"!S4\" or \"last:3\"",
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("anonymize")
+                .about("Replaces player names and removes their dan, rate and sex")
+                .arg(input_arg())
+                .arg(
+                    Arg::with_name("in-format")
+                        .short("f")
+                        .long("in-format")
+                        .takes_value(true)
+                        .value_name("FORMAT")
+                        .possible_values(&["tenhou", "mjlog", "majsoul", "mjai"])
+                        .default_value("tenhou")
+                        .help(
+                            "Format of the input log. \
+                            Writes mjai events for \"mjai\" and a tenhou.net/6 log otherwise",
+                        ),
+                )
+                .arg(out_file_arg())
+                .arg(
+                    Arg::with_name("salt")
+                        .long("salt")
+                        .takes_value(true)
+                        .value_name("SALT")
+                        .help(
+                            "Replace names with a salted hash, which stays the same \
+                            across logs, instead of \"Aさん\" to \"Dさん\" by seat",
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("validate")
+                .about("Checks whether a log describes a legal game")
+                .arg(input_arg())
+                .arg(
+                    Arg::with_name("in-format")
+                        .short("f")
+                        .long("in-format")
+                        .takes_value(true)
+                        .value_name("FORMAT")
+                        .possible_values(&["mjai", "tenhou", "mjlog", "majsoul"])
+                        .default_value("mjai")
+                        .help("Format of the input log"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("info")
+                .about("Shows the players, rule and kyokus of a log")
+                .arg(input_arg())
+                .arg(in_format_arg()),
+        )
+        .get_matches();
+
+    match matches.subcommand() {
+        ("to-mjai", Some(m)) => to_mjai(m),
+        ("to-tenhou", Some(m)) => to_tenhou(m),
+        ("split", Some(m)) => split(m),
+        ("filter", Some(m)) => filter(m),
+        ("anonymize", Some(m)) => anonymize(m),
+        ("validate", Some(m)) => validate(m),
+        ("info", Some(m)) => info(m),
+        _ => to_mjai(&ArgMatches::default()),
+    }
+}
+
+fn to_mjai(m: &ArgMatches) -> Result<()> {
+    let log = tenhou::Log::from(read_raw_log(m)?);
+    let events = if m.is_present("lenient") {
+        let (events, diagnostics) = convlog::tenhou_to_mjai_lenient(&log);
+        for diagnostic in &diagnostics {
+            eprintln!("warning: {}", diagnostic);
+        }
+        events
+    } else {
+        convlog::tenhou_to_mjai(&log).context("failed to convert into mjai format")?
+    };
+
+    let mut out = open_output(m)?;
+    for event in &events {
+        json::to_writer(&mut out, event).context("failed to write mjai event")?;
+        writeln!(out).context("failed to write mjai event")?;
+    }
+    out.flush().context("failed to write mjai event")?;
+
+    Ok(())
+}
+
+fn to_tenhou(m: &ArgMatches) -> Result<()> {
+    let events = read_mjai_events(open_input(m)?)?;
+    let raw_log =
+        convlog::mjai_to_tenhou(&events).context("failed to convert into tenhou.net/6 format")?;
+
+    write_json(open_output(m)?, &raw_log)
+}
+
+fn split(m: &ArgMatches) -> Result<()> {
+    let raw_log = read_raw_log(m)?;
+    let log = tenhou::Log::from(raw_log.clone());
+
+    let out_dir = Path::new(m.value_of("out-dir").unwrap_or("."));
+    fs::create_dir_all(out_dir)
+        .with_context(|| format!("failed to create out dir {:?}", out_dir))?;
 
-    let tenhou_log_raw: tenhou::RawLog =
-        json::from_reader(stdin).expect("failed to parse tenhou log");
-    let tenhou_log = tenhou::Log::from(tenhou_log_raw);
+    for (kyoku, partial_log) in log.kyokus.iter().zip(raw_log.split_by_kyoku()) {
+        let path = out_dir.join(format!("{}.json", kyoku_name(&kyoku.meta)));
+        let file =
+            File::create(&path).with_context(|| format!("failed to create file {:?}", path))?;
+        write_json(BufWriter::new(file), &partial_log)?;
+        println!("{}", path.display());
+    }
+
+    Ok(())
+}
+
+fn filter(m: &ArgMatches) -> Result<()> {
+    let mut raw_log = read_raw_log(m)?;
+
+    let kyoku_filter: KyokuFilter = m
+        .value_of("kyokus")
+        .unwrap_or_default()
+        .parse()
+        .context("failed to parse kyoku filter")?;
+    raw_log.filter_kyokus(&kyoku_filter);
+
+    write_json(open_output(m)?, &raw_log)
+}
+
+fn anonymize(m: &ArgMatches) -> Result<()> {
+    let anonymizer = match m.value_of("salt") {
+        Some(salt) => Anonymizer::Hash {
+            salt: salt.to_owned(),
+        },
+        None => Anonymizer::Seat,
+    };
+
+    if m.value_of("in-format") == Some("mjai") {
+        let mut events = read_mjai_events(open_input(m)?)?;
+        anonymizer.anonymize_events(&mut events);
+
+        let mut out = open_output(m)?;
+        for event in &events {
+            json::to_writer(&mut out, event).context("failed to write mjai event")?;
+            writeln!(out).context("failed to write mjai event")?;
+        }
+        out.flush().context("failed to write mjai event")?;
+
+        return Ok(());
+    }
+
+    let mut raw_log = read_raw_log(m)?;
+    raw_log.anonymize(&anonymizer);
+
+    write_json(open_output(m)?, &raw_log)
+}
 
-    convlog::tenhou_to_mjai(&tenhou_log)
-        .expect("failed to transform tenhou log")
+fn validate(m: &ArgMatches) -> Result<()> {
+    let events = match m.value_of("in-format") {
+        Some("mjai") | None => read_mjai_events(open_input(m)?)?,
+        _ => {
+            let log = tenhou::Log::from(read_raw_log(m)?);
+            convlog::tenhou_to_mjai(&log).context("failed to convert into mjai format")?
+        }
+    };
+
+    convlog::validate(&events)?;
+    println!("ok: {} events", events.len());
+
+    Ok(())
+}
+
+fn info(m: &ArgMatches) -> Result<()> {
+    let log = tenhou::Log::from(read_raw_log(m)?);
+
+    println!("rule: {}", log.rule);
+    println!("players:");
+    for (i, name) in log
+        .names
         .iter()
-        .for_each(|event| println!("{}", json::to_string(event).unwrap()));
+        .take(log.num_players() as usize)
+        .enumerate()
+    {
+        println!("  {}: {}", i, name);
+    }
+
+    println!("kyokus:");
+    for kyoku in &log.kyokus {
+        let result = match &kyoku.end_status {
+            tenhou::kyoku::EndStatus::Hora { details } => details
+                .iter()
+                .map(|d| {
+                    let how = if d.who == d.target {
+                        "ツモ"
+                    } else {
+                        "ロン"
+                    };
+                    format!("{} {} {}", how, d.who, d.score_deltas[d.who as usize])
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+            tenhou::kyoku::EndStatus::Ryukyoku { kind, .. } => kind.to_string(),
+        };
+        println!(
+            "  {} {:?}: {}",
+            kyoku_name(&kyoku.meta),
+            kyoku.scoreboard,
+            result,
+        );
+    }
+
+    Ok(())
+}
+
+/// Formats like "E1.0".
+fn kyoku_name(meta: &tenhou::kyoku::Meta) -> String {
+    let bakaze = ["E", "S", "W", "N"][(meta.kyoku_num / 4 % 4) as usize];
+    format!("{}{}.{}", bakaze, meta.kyoku_num % 4 + 1, meta.honba)
+}
+
+fn open_input(m: &ArgMatches) -> Result<Box<dyn BufRead>> {
+    match m.value_of("INPUT") {
+        Some(path) if path != "-" => {
+            let file =
+                File::open(path).with_context(|| format!("failed to open input {:?}", path))?;
+            Ok(Box::new(BufReader::new(file)))
+        }
+        _ => Ok(Box::new(BufReader::new(io::stdin()))),
+    }
+}
+
+fn open_output(m: &ArgMatches) -> Result<Box<dyn Write>> {
+    match m.value_of("out-file") {
+        Some(path) if path != "-" => {
+            let file =
+                File::create(path).with_context(|| format!("failed to create {:?}", path))?;
+            Ok(Box::new(BufWriter::new(file)))
+        }
+        _ => Ok(Box::new(BufWriter::new(io::stdout()))),
+    }
+}
+
+fn read_raw_log(m: &ArgMatches) -> Result<tenhou::RawLog> {
+    let reader = open_input(m)?;
+
+    let raw_log = match m.value_of("in-format") {
+        Some("mjlog") => {
+            tenhou::RawLog::from(mjlog::parse_reader(reader).context("failed to parse mjlog")?)
+        }
+        Some("majsoul") => tenhou::RawLog::from(
+            majsoul::parse_reader(reader).context("failed to parse Mahjong Soul record")?,
+        ),
+        _ => json::from_reader(reader).context("failed to parse tenhou log")?,
+    };
+
+    Ok(raw_log)
+}
+
+fn read_mjai_events(reader: impl BufRead) -> Result<Vec<mjai::Event>> {
+    let mut events = vec![];
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.context("failed to read input")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let event = json::from_str(&line)
+            .with_context(|| format!("failed to parse mjai event at line {}", i + 1))?;
+        events.push(event);
+    }
+
+    if events.is_empty() {
+        bail!("no mjai event found");
+    }
+
+    Ok(events)
+}
+
+fn write_json(mut writer: impl Write, value: &impl serde::Serialize) -> Result<()> {
+    json::to_writer(&mut writer, value).context("failed to write output")?;
+    writeln!(writer).context("failed to write output")?;
+    writer.flush().context("failed to write output")?;
+
+    Ok(())
 }
@@ -0,0 +1,628 @@
+//! A simulator that replays mjai events and keeps track of the full position
+//! of every seat.
+
+use crate::mjai::{Consumed2, Consumed3, Consumed4, Event};
+use crate::notation::Pais;
+use crate::score::HoraContext;
+use crate::shanten;
+use crate::{Pai, TileCounts};
+
+use std::mem;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum BoardError {
+    #[error("invalid actor {0}")]
+    InvalidActor(u8),
+
+    #[error("actor {actor} does not have {pai} in the tehai {tehai}")]
+    PaiNotInTehai { actor: u8, pai: Pai, tehai: Pais },
+
+    #[error("kakan of {pai} by actor {actor} without a previous pon")]
+    PonNotFound { actor: u8, pai: Pai },
+
+    #[error("actor {actor} calls from the empty kawa of {target}")]
+    EmptyKawa { actor: u8, target: u8 },
+}
+
+pub type Result<T> = std::result::Result<T, BoardError>;
+
+/// The full position of a game at some point, built by feeding mjai events to
+/// [`Board::update`] one by one.
+#[derive(Debug, Clone, Serialize)]
+pub struct Board {
+    pub num_players: u8,
+
+    pub bakaze: Pai,
+    /// Counts from 1.
+    pub kyoku: u8,
+    pub honba: u8,
+    pub kyotaku: u8,
+    pub oya: u8,
+    pub scores: [i32; 4],
+    pub dora_markers: Vec<Pai>,
+    /// Number of pais that can still be drawn from the live wall.
+    pub tiles_left: u8,
+
+    pub players: [PlayerBoard; 4],
+
+    /// Whether there has been any call in the kyoku, including ankan.
+    #[serde(skip)]
+    has_calls: bool,
+    #[serde(skip)]
+    next_tsumo_rinshan: bool,
+    #[serde(skip)]
+    last_tsumo_rinshan: bool,
+    /// The kakan that can still be robbed, as `(actor, pai)`.
+    #[serde(skip)]
+    last_kakan: Option<(u8, Pai)>,
+    #[serde(skip)]
+    num_horas: u8,
+}
+
+/// The position of a single seat.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PlayerBoard {
+    /// Sorted, except that the last drawn pai stays at the end until the next
+    /// discard.
+    pub tehai: Vec<Pai>,
+    pub fuuros: Vec<Fuuro>,
+    /// Number of nukidora (kita) declared, only in sanma.
+    pub nukidora: u8,
+    pub kawa: Vec<KawaItem>,
+
+    /// Index into `kawa` of the pai discarded sideways for reach.
+    pub reach_turn: Option<usize>,
+    /// Whether the reach has been accepted, i.e. the stick has been paid.
+    pub reach_accepted: bool,
+
+    #[serde(skip)]
+    reach_declared: bool,
+    /// Waits of the current tehai as a bitset of [`Pai::to_id34`]. Only meaningful
+    /// when the tehai is not holding a drawn pai.
+    #[serde(skip)]
+    waits: u64,
+    #[serde(skip)]
+    temporary_furiten: bool,
+    #[serde(skip)]
+    reach_furiten: bool,
+    #[serde(skip)]
+    double_reach: bool,
+    #[serde(skip)]
+    ippatsu: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct KawaItem {
+    pub pai: Pai,
+    pub tsumogiri: bool,
+    /// Whether it has been called by others with chi, pon or daiminkan.
+    pub called: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+pub enum Fuuro {
+    Chi {
+        target: u8,
+        #[serde(deserialize_with = "Pai::deserialize_mjai_str")]
+        pai: Pai,
+        consumed: Consumed2,
+    },
+    Pon {
+        target: u8,
+        #[serde(deserialize_with = "Pai::deserialize_mjai_str")]
+        pai: Pai,
+        consumed: Consumed2,
+    },
+    Daiminkan {
+        target: u8,
+        #[serde(deserialize_with = "Pai::deserialize_mjai_str")]
+        pai: Pai,
+        consumed: Consumed3,
+    },
+    Kakan {
+        #[serde(deserialize_with = "Pai::deserialize_mjai_str")]
+        pai: Pai,
+        previous_pon_target: u8,
+        #[serde(deserialize_with = "Pai::deserialize_mjai_str")]
+        previous_pon_pai: Pai,
+        consumed: Consumed2,
+    },
+    Ankan {
+        consumed: Consumed4,
+    },
+}
+
+impl Fuuro {
+    /// All the pais in the fuuro, including the called one.
+    pub fn pais(&self) -> Vec<Pai> {
+        match *self {
+            Fuuro::Chi { pai, consumed, .. } | Fuuro::Pon { pai, consumed, .. } => {
+                vec![pai, consumed.0[0], consumed.0[1]]
+            }
+            Fuuro::Daiminkan { pai, consumed, .. } => {
+                let mut pais = vec![pai];
+                pais.extend_from_slice(&consumed.0);
+                pais
+            }
+            Fuuro::Kakan {
+                pai,
+                previous_pon_pai,
+                consumed,
+                ..
+            } => vec![previous_pon_pai, consumed.0[0], consumed.0[1], pai],
+            Fuuro::Ankan { consumed } => consumed.0.to_vec(),
+        }
+    }
+}
+
+impl Default for Board {
+    #[inline]
+    fn default() -> Self {
+        Board {
+            num_players: 4,
+            bakaze: Pai::East,
+            kyoku: 1,
+            honba: 0,
+            kyotaku: 0,
+            oya: 0,
+            scores: [0; 4],
+            dora_markers: vec![],
+            tiles_left: 0,
+            players: Default::default(),
+            has_calls: false,
+            next_tsumo_rinshan: false,
+            last_tsumo_rinshan: false,
+            last_kakan: None,
+            num_horas: 0,
+        }
+    }
+}
+
+impl Board {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replays `events[..=index]` and returns the position right after the
+    /// event at `index`.
+    pub fn at(events: &[Event], index: usize) -> Result<Self> {
+        let mut board = Self::new();
+        for event in events.iter().take(index + 1) {
+            board.update(event)?;
+        }
+        Ok(board)
+    }
+
+    /// Applies an event to the board. Events that do not change the position,
+    /// such as `EndKyoku`, are no-ops.
+    pub fn update(&mut self, event: &Event) -> Result<()> {
+        if let Some(actor) = event.actor() {
+            if actor >= self.num_players {
+                return Err(BoardError::InvalidActor(actor));
+            }
+        }
+
+        match *event {
+            Event::StartGame { sanma, .. } => {
+                *self = Board {
+                    num_players: if sanma { 3 } else { 4 },
+                    ..Self::default()
+                };
+            }
+
+            Event::StartKyoku {
+                bakaze,
+                dora_marker,
+                kyoku,
+                honba,
+                kyotaku,
+                oya,
+                scores,
+                ref tehais,
+            } => {
+                self.bakaze = bakaze;
+                self.kyoku = kyoku;
+                self.honba = honba;
+                self.kyotaku = kyotaku;
+                self.oya = oya;
+                self.scores = scores;
+                self.dora_markers = vec![dora_marker];
+                self.has_calls = false;
+                self.next_tsumo_rinshan = false;
+                self.last_tsumo_rinshan = false;
+                self.last_kakan = None;
+                self.num_horas = 0;
+                self.tiles_left = if self.num_players == 3 {
+                    108 - 14 - 13 * 3
+                } else {
+                    136 - 14 - 13 * 4
+                };
+
+                for (player, tehai) in self.players.iter_mut().zip(tehais) {
+                    *player = PlayerBoard {
+                        tehai: tehai.to_vec(),
+                        ..PlayerBoard::default()
+                    };
+                    player.tehai.sort_unstable_by_key(|&p| p.sort_key());
+                    player.update_waits();
+                }
+            }
+
+            Event::Tsumo { actor, pai } => {
+                self.tiles_left = self.tiles_left.saturating_sub(1);
+                self.last_tsumo_rinshan = mem::take(&mut self.next_tsumo_rinshan);
+                // the kakan has not been robbed.
+                if self.last_kakan.take().is_some() {
+                    self.interrupt();
+                }
+                self.players[actor as usize].tehai.push(pai);
+            }
+
+            Event::Dahai {
+                actor,
+                pai,
+                tsumogiri,
+            } => {
+                let player = &mut self.players[actor as usize];
+                player.remove(actor, pai)?;
+                player.tehai.sort_unstable_by_key(|&p| p.sort_key());
+                player.kawa.push(KawaItem {
+                    pai,
+                    tsumogiri,
+                    called: false,
+                });
+                if player.reach_declared && player.reach_turn.is_none() {
+                    player.reach_turn = Some(player.kawa.len() - 1);
+                }
+                player.temporary_furiten = false;
+                player.ippatsu = false;
+                player.update_waits();
+
+                self.pass(actor, pai);
+            }
+
+            Event::Chi {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => {
+                self.mark_called(actor, target)?;
+                self.interrupt();
+                let player = &mut self.players[actor as usize];
+                player.remove_multiple(actor, &consumed.0)?;
+                player.fuuros.push(Fuuro::Chi {
+                    target,
+                    pai,
+                    consumed,
+                });
+            }
+
+            Event::Pon {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => {
+                self.mark_called(actor, target)?;
+                self.interrupt();
+                let player = &mut self.players[actor as usize];
+                player.remove_multiple(actor, &consumed.0)?;
+                player.fuuros.push(Fuuro::Pon {
+                    target,
+                    pai,
+                    consumed,
+                });
+            }
+
+            Event::Daiminkan {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => {
+                self.mark_called(actor, target)?;
+                self.interrupt();
+                let player = &mut self.players[actor as usize];
+                player.remove_multiple(actor, &consumed.0)?;
+                player.fuuros.push(Fuuro::Daiminkan {
+                    target,
+                    pai,
+                    consumed,
+                });
+                self.next_tsumo_rinshan = true;
+            }
+
+            Event::Kakan {
+                actor,
+                pai,
+                consumed,
+            } => {
+                let player = &mut self.players[actor as usize];
+                player.remove(actor, pai)?;
+
+                let (idx, previous_pon_target, previous_pon_pai, previous_pon_consumed) = player
+                    .fuuros
+                    .iter()
+                    .enumerate()
+                    .find_map(|(idx, f)| match *f {
+                        Fuuro::Pon {
+                            target: pon_target,
+                            pai: pon_pai,
+                            consumed: pon_consumed,
+                        } if Consumed3([pon_pai, pon_consumed.0[0], pon_consumed.0[1]])
+                            == consumed =>
+                        {
+                            Some((idx, pon_target, pon_pai, pon_consumed))
+                        }
+                        _ => None,
+                    })
+                    .ok_or(BoardError::PonNotFound { actor, pai })?;
+
+                player.fuuros[idx] = Fuuro::Kakan {
+                    pai,
+                    previous_pon_target,
+                    previous_pon_pai,
+                    consumed: previous_pon_consumed,
+                };
+
+                // chankan is possible.
+                self.pass(actor, pai);
+                self.last_kakan = Some((actor, pai));
+                self.next_tsumo_rinshan = true;
+            }
+
+            Event::Ankan { actor, consumed } => {
+                let player = &mut self.players[actor as usize];
+                player.remove_multiple(actor, &consumed.0)?;
+                player.fuuros.push(Fuuro::Ankan { consumed });
+                self.interrupt();
+                self.next_tsumo_rinshan = true;
+            }
+
+            Event::Nukidora { actor, pai } => {
+                let player = &mut self.players[actor as usize];
+                player.remove(actor, pai)?;
+                player.nukidora += 1;
+                self.next_tsumo_rinshan = true;
+            }
+
+            Event::Dora { dora_marker } => self.dora_markers.push(dora_marker),
+
+            Event::Reach { actor } => {
+                let player = &mut self.players[actor as usize];
+                player.reach_declared = true;
+                player.double_reach = !self.has_calls && player.kawa.is_empty();
+            }
+
+            Event::ReachAccepted { actor } => {
+                let player = &mut self.players[actor as usize];
+                player.reach_accepted = true;
+                player.ippatsu = true;
+                self.scores[actor as usize] -= 1000;
+                self.kyotaku += 1;
+            }
+
+            Event::Hora { deltas, .. } => {
+                if let Some(deltas) = deltas {
+                    self.apply_deltas(deltas);
+                    self.kyotaku = 0;
+                }
+                self.num_horas += 1;
+            }
+
+            Event::Ryukyoku {
+                deltas: Some(deltas),
+                ..
+            } => self.apply_deltas(deltas),
+
+            _ => (),
+        };
+
+        Ok(())
+    }
+
+    /// Iterates over the seats that are present in this game.
+    #[inline]
+    pub fn active_players(&self) -> impl Iterator<Item = &PlayerBoard> {
+        self.players.iter().take(self.num_players as usize)
+    }
+
+    /// Counts the pais visible to everyone, which are the dora markers, the
+    /// kawa except the called pais, the fuuros and the nukidoras.
+    pub fn visible_pais(&self) -> TileCounts {
+        let mut counts: TileCounts = self.dora_markers.iter().copied().collect();
+
+        for player in self.active_players() {
+            counts.extend(
+                player
+                    .kawa
+                    .iter()
+                    .filter(|item| !item.called)
+                    .map(|item| item.pai),
+            );
+            for fuuro in &player.fuuros {
+                counts.extend(fuuro.pais());
+            }
+            for _ in 0..player.nukidora {
+                counts.add(Pai::North);
+            }
+        }
+
+        counts
+    }
+
+    /// Gathers what is needed to value a `Hora` event, before the event is
+    /// applied. Returns `None` for other events.
+    pub fn hora_context(&self, event: &Event) -> Option<HoraContext> {
+        let (actor, target, pao, uradora_markers) = match *event {
+            Event::Hora {
+                actor,
+                target,
+                pao,
+                ref uradora_markers,
+                ..
+            } => (actor, target, pao, uradora_markers.clone()),
+            _ => return None,
+        };
+
+        let player = self.players.get(actor as usize)?;
+        let is_tsumo = actor == target;
+        let mut tehai = player.tehai.clone();
+        let (winning_pai, is_chankan) = if is_tsumo {
+            (tehai.pop()?, false)
+        } else {
+            match self.last_kakan {
+                Some((kakan_actor, pai)) if kakan_actor == target => (pai, true),
+                _ => (self.players.get(target as usize)?.kawa.last()?.pai, false),
+            }
+        };
+
+        Some(HoraContext {
+            num_players: self.num_players,
+            actor,
+            target,
+            pao,
+            oya: self.oya,
+            bakaze: self.bakaze,
+            tehai,
+            fuuros: player.fuuros.clone(),
+            winning_pai,
+            dora_markers: self.dora_markers.clone(),
+            uradora_markers: uradora_markers.unwrap_or_default(),
+            nukidora: player.nukidora,
+            is_reach: player.reach_accepted,
+            is_double_reach: player.reach_accepted && player.double_reach,
+            is_ippatsu: player.ippatsu,
+            is_rinshan: is_tsumo && self.last_tsumo_rinshan,
+            is_chankan,
+            is_last_pai: self.tiles_left == 0,
+            is_first_tsumo: is_tsumo && !self.has_calls && player.kawa.is_empty(),
+            // honba go to the first winner only, same as kyotaku.
+            honba: if self.num_horas == 0 { self.honba } else { 0 },
+            kyotaku: self.kyotaku,
+        })
+    }
+
+    /// A call breaks ippatsu and the first turn.
+    #[inline]
+    fn interrupt(&mut self) {
+        self.has_calls = true;
+        for player in &mut self.players {
+            player.ippatsu = false;
+        }
+    }
+
+    #[inline]
+    fn apply_deltas(&mut self, deltas: [i32; 4]) {
+        for (score, delta) in self.scores.iter_mut().zip(&deltas) {
+            *score += delta;
+        }
+    }
+
+    fn mark_called(&mut self, actor: u8, target: u8) -> Result<()> {
+        let item = self
+            .players
+            .get_mut(target as usize)
+            .ok_or(BoardError::InvalidActor(target))?
+            .kawa
+            .last_mut()
+            .ok_or(BoardError::EmptyKawa { actor, target })?;
+        item.called = true;
+        Ok(())
+    }
+
+    /// Others let the pai pass without a ron.
+    fn pass(&mut self, actor: u8, pai: Pai) {
+        let id = match pai.to_id34() {
+            Some(id) => id,
+            None => return,
+        };
+
+        for (i, player) in self.players.iter_mut().enumerate() {
+            if i as u8 != actor && player.waits & (1 << id) != 0 {
+                player.temporary_furiten = true;
+                if player.reach_accepted {
+                    player.reach_furiten = true;
+                }
+            }
+        }
+    }
+}
+
+impl PlayerBoard {
+    /// Whether the player is in reach, including the pending one whose
+    /// declaration pai has not been discarded yet.
+    #[inline]
+    pub fn is_reach(&self) -> bool {
+        self.reach_declared
+    }
+
+    /// Whether the player's current waits are furiten, including sutehai
+    /// furiten, temporary furiten and reach furiten.
+    pub fn is_furiten(&self) -> bool {
+        if self.temporary_furiten || self.reach_furiten {
+            return true;
+        }
+
+        self.kawa
+            .iter()
+            .filter_map(|item| item.pai.to_id34())
+            .any(|id| self.waits & (1 << id) != 0)
+    }
+
+    /// Returns the waits of the current tehai, or an empty `Vec` if it is not
+    /// tenpai.
+    pub fn waits(&self) -> Vec<Pai> {
+        (0..34)
+            .filter(|&id| self.waits & (1 << id) != 0)
+            .filter_map(Pai::from_id34)
+            .collect()
+    }
+
+    fn remove(&mut self, actor: u8, pai: Pai) -> Result<()> {
+        // tehais of the absent seat or of others in a partial log can be
+        // unknown.
+        let idx = self
+            .tehai
+            .iter()
+            .rposition(|&p| p == pai)
+            .or_else(|| self.tehai.iter().rposition(|&p| p == Pai::Unknown))
+            .ok_or_else(|| BoardError::PaiNotInTehai {
+                actor,
+                pai,
+                tehai: Pais::from(self.tehai.as_slice()),
+            })?;
+        self.tehai.remove(idx);
+        Ok(())
+    }
+
+    fn remove_multiple(&mut self, actor: u8, pais: &[Pai]) -> Result<()> {
+        for &pai in pais {
+            self.remove(actor, pai)?;
+        }
+        Ok(())
+    }
+
+    fn update_waits(&mut self) {
+        self.waits = 0;
+
+        // an unknown pai or a drawn pai in the tehai means no waits.
+        if self.tehai.len() % 3 != 1 || self.tehai.contains(&Pai::Unknown) {
+            return;
+        }
+
+        let counts = TileCounts::from(self.tehai.as_slice());
+        for pai in shanten::waits(&counts, self.fuuros.len()) {
+            if let Some(id) = pai.to_id34() {
+                self.waits |= 1 << id;
+            }
+        }
+    }
+}
@@ -3,6 +3,7 @@ use crate::tenhou;
 use crate::Pai;
 
 use std::convert::TryFrom;
+use std::fmt;
 
 use thiserror::Error;
 
@@ -35,39 +36,223 @@ pub enum ConvertError {
 
 pub type Result<T> = std::result::Result<T, ConvertError>;
 
-/// Transform a tenhou.net/6 format log into mjai format.
-pub fn tenhou_to_mjai(log: &tenhou::Log) -> Result<Vec<mjai::Event>> {
-    let mut events = vec![];
+/// A problem found in a kyoku by [`tenhou_to_mjai_lenient`].
+#[derive(Debug)]
+pub struct Diagnostic {
+    /// Index of the kyoku in `log.kyokus`.
+    pub kyoku_index: usize,
+    pub kyoku: u8,
+    pub honba: u8,
+    pub actor: Option<u8>,
+    /// Where the offending item is in the action table of `actor`, if known.
+    pub position: Option<ActionPosition>,
+    pub error: ConvertError,
+    pub resolution: Resolution,
+}
 
-    events.push(mjai::Event::StartGame {
-        kyoku_first: log.game_length as u8,
-        aka_flag: log.has_aka,
-        names: log.names.clone(),
-    });
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActionPosition {
+    pub sequence: ActionSequence,
+    pub index: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionSequence {
+    Takes,
+    Discards,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// The whole kyoku is left out of the output.
+    SkippedKyoku,
+    /// The kyoku is kept with the offending part dropped, e.g. a kan dora
+    /// whose indicator is missing is never revealed.
+    Patched,
+}
 
-    for kyoku in &log.kyokus {
-        tenhou_kyoku_to_mjai_events(&mut events, kyoku)?;
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "kyoku={} honba={}", self.kyoku, self.honba)?;
+        if let Some(actor) = self.actor {
+            write!(f, " actor={}", actor)?;
+        }
+        if let Some(ActionPosition { sequence, index }) = self.position {
+            let name = match sequence {
+                ActionSequence::Takes => "takes",
+                ActionSequence::Discards => "discards",
+            };
+            write!(f, " {}[{}]", name, index)?;
+        }
+        write!(f, ": {}", self.error)?;
+        match self.resolution {
+            Resolution::SkippedKyoku => write!(f, " (kyoku skipped)"),
+            Resolution::Patched => write!(f, " (patched)"),
+        }
     }
+}
+
+/// A `ConvertError` with where it happens in a kyoku.
+#[derive(Debug)]
+struct KyokuError {
+    actor: Option<u8>,
+    position: Option<ActionPosition>,
+    error: ConvertError,
+}
+
+impl From<ConvertError> for KyokuError {
+    #[inline]
+    fn from(error: ConvertError) -> Self {
+        let actor = match error {
+            ConvertError::InsufficientTakes { actor, .. }
+            | ConvertError::InsufficientDiscards { actor, .. } => Some(actor),
+            _ => None,
+        };
+
+        KyokuError {
+            actor,
+            position: None,
+            error,
+        }
+    }
+}
+
+type KyokuResult<T> = std::result::Result<T, KyokuError>;
 
+/// Transform a tenhou.net/6 format log into mjai format.
+pub fn tenhou_to_mjai(log: &tenhou::Log) -> Result<Vec<mjai::Event>> {
+    let kyokus = tenhou_to_mjai_iter(log);
+
+    let mut events = vec![kyokus.start_game()];
+    for kyoku_events in kyokus {
+        events.extend(kyoku_events?);
+    }
     events.push(mjai::Event::EndGame);
 
     Ok(events)
 }
 
-fn tenhou_kyoku_to_mjai_events(events: &mut Vec<mjai::Event>, kyoku: &tenhou::Kyoku) -> Result<()> {
+/// Transform a tenhou.net/6 format log into mjai format lazily, kyoku by
+/// kyoku.
+///
+/// Each item holds the events from `StartKyoku` to `EndKyoku` of one kyoku,
+/// in the same order as `log.kyokus`, so a broken kyoku can be skipped
+/// without losing the rest of the game. `StartGame` and `EndGame` are not
+/// yielded.
+#[inline]
+pub fn tenhou_to_mjai_iter(log: &tenhou::Log) -> KyokuEvents<'_> {
+    KyokuEvents {
+        log,
+        kyokus: log.kyokus.iter(),
+    }
+}
+
+/// Iterator returned by [`tenhou_to_mjai_iter`].
+#[derive(Debug, Clone)]
+pub struct KyokuEvents<'a> {
+    log: &'a tenhou::Log,
+    kyokus: std::slice::Iter<'a, tenhou::Kyoku>,
+}
+
+impl KyokuEvents<'_> {
+    /// Returns the `StartGame` event of the log.
+    pub fn start_game(&self) -> mjai::Event {
+        mjai::Event::StartGame {
+            kyoku_first: self.log.rule.game_length as u8,
+            aka_flag: self.log.rule.has_aka(),
+            names: self.log.names.clone(),
+            sanma: self.log.rule.is_sanma,
+        }
+    }
+}
+
+impl Iterator for KyokuEvents<'_> {
+    type Item = Result<Vec<mjai::Event>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let kyoku = self.kyokus.next()?;
+
+        let mut events = vec![];
+        let result = tenhou_kyoku_to_mjai_events(&mut events, kyoku, self.log.num_players(), None);
+        Some(result.map(|_| events).map_err(|err| err.error))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.kyokus.size_hint()
+    }
+}
+
+impl ExactSizeIterator for KyokuEvents<'_> {}
+
+/// Transform a tenhou.net/6 format log into mjai format, recording errors as
+/// diagnostics instead of failing.
+///
+/// A missing kan dora indicator is patched by not revealing the dora. For any
+/// other error the kyoku is skipped entirely.
+pub fn tenhou_to_mjai_lenient(log: &tenhou::Log) -> (Vec<mjai::Event>, Vec<Diagnostic>) {
+    let kyokus = tenhou_to_mjai_iter(log);
+    let mut events = vec![kyokus.start_game()];
+    let mut diagnostics = vec![];
+
+    for (kyoku_index, kyoku) in log.kyokus.iter().enumerate() {
+        let diagnostic = |err: KyokuError, resolution| Diagnostic {
+            kyoku_index,
+            kyoku: kyoku.meta.kyoku_num,
+            honba: kyoku.meta.honba,
+            actor: err.actor,
+            position: err.position,
+            error: err.error,
+            resolution,
+        };
+
+        let mut kyoku_events = vec![];
+        let mut patched = vec![];
+        let result = tenhou_kyoku_to_mjai_events(
+            &mut kyoku_events,
+            kyoku,
+            log.num_players(),
+            Some(&mut patched),
+        );
+
+        match result {
+            Ok(_) => {
+                events.extend(kyoku_events);
+                diagnostics.extend(
+                    patched
+                        .into_iter()
+                        .map(|err| diagnostic(err, Resolution::Patched)),
+                );
+            }
+            Err(err) => diagnostics.push(diagnostic(err, Resolution::SkippedKyoku)),
+        }
+    }
+
+    events.push(mjai::Event::EndGame);
+    (events, diagnostics)
+}
+
+/// If `patched` is given, recoverable errors are pushed into it instead of
+/// failing the kyoku.
+fn tenhou_kyoku_to_mjai_events(
+    events: &mut Vec<mjai::Event>,
+    kyoku: &tenhou::Kyoku,
+    num_players: u8,
+    mut patched: Option<&mut Vec<KyokuError>>,
+) -> KyokuResult<()> {
     // First of all, transform all takes and discards to events.
     let mut take_events: Vec<_> = (0..4)
         .map(|i| {
-            take_action_to_events(i, &kyoku.action_tables[i as usize].takes)
+            take_action_to_events(i, &kyoku.action_tables[i as usize].takes, num_players)
                 .map(|ev| ev.into_iter().peekable())
         })
-        .collect::<Result<Vec<_>>>()?;
+        .collect::<KyokuResult<Vec<_>>>()?;
     let mut discard_events: Vec<_> = (0..4)
         .map(|i| {
             discard_action_to_events(i, &kyoku.action_tables[i as usize].discards)
                 .map(|ev| ev.into_iter().peekable())
         })
-        .collect::<Result<Vec<_>>>()?;
+        .collect::<KyokuResult<Vec<_>>>()?;
 
     // Then emit the events in order.
     let oya = kyoku.meta.kyoku_num % 4;
@@ -162,16 +347,10 @@ fn tenhou_kyoku_to_mjai_events(events: &mut Vec<mjai::Event>, kyoku: &tenhou::Ky
         // Emit the discard event.
         events.push(discard.clone());
 
-        // Process previous minkan.
-        if need_new_dora {
-            events.push(mjai::Event::Dora {
-                dora_marker: dora_feed
-                    .next()
-                    .ok_or(ConvertError::InsufficientDoraIndicators {
-                        kyoku: kyoku.meta.kyoku_num,
-                        honba: kyoku.meta.honba,
-                    })?,
-            });
+        // Process previous minkan. The dora is revealed on the next actual
+        // discard, not on nukidora.
+        if need_new_dora && !matches!(discard, mjai::Event::Nukidora { .. }) {
+            push_kan_dora(events, dora_feed.next(), kyoku, &mut patched)?;
             need_new_dora = false;
         }
 
@@ -212,20 +391,15 @@ fn tenhou_kyoku_to_mjai_events(events: &mut Vec<mjai::Event>, kyoku: &tenhou::Ky
         match discard {
             mjai::Event::Ankan { .. } => {
                 // ankan triggers a dora event immediately.
-                events.push(mjai::Event::Dora {
-                    dora_marker: dora_feed.next().ok_or(
-                        ConvertError::InsufficientDoraIndicators {
-                            kyoku: kyoku.meta.kyoku_num,
-                            honba: kyoku.meta.honba,
-                        },
-                    )?,
-                });
+                push_kan_dora(events, dora_feed.next(), kyoku, &mut patched)?;
                 continue;
             }
             mjai::Event::Kakan { .. } => {
                 need_new_dora = true;
                 continue;
             }
+            // nukidora draws a rinshan pai but reveals no dora.
+            mjai::Event::Nukidora { .. } => continue,
             _ => (),
         }
 
@@ -265,290 +439,388 @@ fn tenhou_kyoku_to_mjai_events(events: &mut Vec<mjai::Event>, kyoku: &tenhou::Ky
             // it seems not possible to properly describe it on tenhou.net/6.
             .max_by_key(|&(_, naki_ord)| naki_ord)
             .map(|(i, _)| i)
-            .unwrap_or((actor + 1) % 4);
+            .unwrap_or((actor + 1) % num_players as usize);
     }
 
     Ok(())
 }
 
-fn take_action_to_events(actor: u8, takes: &[tenhou::ActionItem]) -> Result<Vec<mjai::Event>> {
+/// Emits the dora revealed by a kan. A missing indicator is recoverable, in
+/// which case the dora is simply not revealed.
+fn push_kan_dora(
+    events: &mut Vec<mjai::Event>,
+    dora_marker: Option<Pai>,
+    kyoku: &tenhou::Kyoku,
+    patched: &mut Option<&mut Vec<KyokuError>>,
+) -> KyokuResult<()> {
+    if let Some(dora_marker) = dora_marker {
+        events.push(mjai::Event::Dora { dora_marker });
+        return Ok(());
+    }
+
+    let err = KyokuError::from(ConvertError::InsufficientDoraIndicators {
+        kyoku: kyoku.meta.kyoku_num,
+        honba: kyoku.meta.honba,
+    });
+    match patched {
+        Some(patched) => {
+            patched.push(err);
+            Ok(())
+        }
+        None => Err(err),
+    }
+}
+
+fn take_action_to_events(
+    actor: u8,
+    takes: &[tenhou::ActionItem],
+    num_players: u8,
+) -> KyokuResult<Vec<mjai::Event>> {
     takes
         .iter()
-        .map(|take| match take {
-            tenhou::ActionItem::Tsumogiri(_) => Err(ConvertError::UnexpectedTsumogiri),
+        .enumerate()
+        .map(|(index, take)| {
+            take_to_event(actor, take, num_players).map_err(|error| KyokuError {
+                actor: Some(actor),
+                position: Some(ActionPosition {
+                    sequence: ActionSequence::Takes,
+                    index,
+                }),
+                error,
+            })
+        })
+        .collect()
+}
 
-            &tenhou::ActionItem::Pai(pai) => Ok(mjai::Event::Tsumo { actor, pai }),
+fn take_to_event(actor: u8, take: &tenhou::ActionItem, num_players: u8) -> Result<mjai::Event> {
+    let kamicha = (actor + num_players - 1) % num_players;
+    let shimocha = (actor + 1) % num_players;
 
-            tenhou::ActionItem::Naki(naki_string) => {
-                let naki = naki_string.as_bytes();
+    match take {
+        tenhou::ActionItem::Tsumogiri(_) => Err(ConvertError::UnexpectedTsumogiri),
 
-                if naki.contains(&b'c') {
-                    // chi
-                    // you can only chi from kamicha right...?
+        &tenhou::ActionItem::Pai(pai) => Ok(mjai::Event::Tsumo { actor, pai }),
 
-                    if naki_string.len() != 7 {
-                        return Err(ConvertError::InvalidNaki(naki_string.clone()));
-                    }
+        tenhou::ActionItem::Naki(naki_string) => {
+            let naki = naki_string.as_bytes();
+
+            if naki.contains(&b'c') {
+                // chi
+                // you can only chi from kamicha right...?
 
-                    // e.g. "c275226" => chi 7p with 06p from kamicha
-                    Ok(mjai::Event::Chi {
+                if naki_string.len() != 7 {
+                    return Err(ConvertError::InvalidNaki(naki_string.clone()));
+                }
+
+                // e.g. "c275226" => chi 7p with 06p from kamicha
+                Ok(mjai::Event::Chi {
+                    actor,
+                    target: kamicha,
+                    pai: pai_from_bytes(&naki[1..3])?,
+                    consumed: mjai::Consumed2([
+                        pai_from_bytes(&naki[3..5])?,
+                        pai_from_bytes(&naki[5..7])?,
+                    ]),
+                })
+            } else if let Some(idx) = naki_string.find('p') {
+                // pon
+
+                if naki_string.len() != 7 {
+                    return Err(ConvertError::InvalidNaki(naki_string.clone()));
+                }
+
+                match idx {
+                    // from kamicha
+                    // e.g. "p252525" => pon 5p from kamicha
+                    0 => Ok(mjai::Event::Pon {
                         actor,
-                        target: (actor + 3) % 4,
+                        target: kamicha,
                         pai: pai_from_bytes(&naki[1..3])?,
                         consumed: mjai::Consumed2([
                             pai_from_bytes(&naki[3..5])?,
                             pai_from_bytes(&naki[5..7])?,
                         ]),
-                    })
-                } else if let Some(idx) = naki_string.find('p') {
-                    // pon
+                    }),
 
-                    if naki_string.len() != 7 {
-                        return Err(ConvertError::InvalidNaki(naki_string.clone()));
-                    }
+                    // from toimen
+                    // e.g. "12p1212" => pon 2m from toimen
+                    2 if num_players == 4 => Ok(mjai::Event::Pon {
+                        actor,
+                        target: (actor + 2) % 4,
+                        pai: pai_from_bytes(&naki[3..5])?,
+                        consumed: mjai::Consumed2([
+                            pai_from_bytes(&naki[0..2])?,
+                            pai_from_bytes(&naki[5..7])?,
+                        ]),
+                    }),
+                    // from shimocha
+                    // e.g. "3737p37" => pon 7s from shimocha
+                    4 => Ok(mjai::Event::Pon {
+                        actor,
+                        target: shimocha,
+                        pai: pai_from_bytes(&naki[5..7])?,
+                        consumed: mjai::Consumed2([
+                            pai_from_bytes(&naki[0..2])?,
+                            pai_from_bytes(&naki[2..4])?,
+                        ]),
+                    }),
 
-                    match idx {
-                        // from kamicha
-                        // e.g. "p252525" => pon 5p from kamicha
-                        0 => Ok(mjai::Event::Pon {
-                            actor,
-                            target: (actor + 3) % 4,
-                            pai: pai_from_bytes(&naki[1..3])?,
-                            consumed: mjai::Consumed2([
-                                pai_from_bytes(&naki[3..5])?,
-                                pai_from_bytes(&naki[5..7])?,
-                            ]),
-                        }),
-
-                        // from toimen
-                        // e.g. "12p1212" => pon 2m from toimen
-                        2 => Ok(mjai::Event::Pon {
-                            actor,
-                            target: (actor + 2) % 4,
-                            pai: pai_from_bytes(&naki[3..5])?,
-                            consumed: mjai::Consumed2([
-                                pai_from_bytes(&naki[0..2])?,
-                                pai_from_bytes(&naki[5..7])?,
-                            ]),
-                        }),
-                        // from shimocha
-                        // e.g. "3737p37" => pon 7s from shimocha
-                        4 => Ok(mjai::Event::Pon {
-                            actor,
-                            target: (actor + 1) % 4,
-                            pai: pai_from_bytes(&naki[5..7])?,
-                            consumed: mjai::Consumed2([
-                                pai_from_bytes(&naki[0..2])?,
-                                pai_from_bytes(&naki[2..4])?,
-                            ]),
-                        }),
-
-                        // ???
-                        _ => Err(ConvertError::InvalidNaki(naki_string.clone())),
-                    }
-                } else if let Some(idx) = naki_string.find('m') {
-                    // daiminkan
+                    // ???
+                    _ => Err(ConvertError::InvalidNaki(naki_string.clone())),
+                }
+            } else if let Some(idx) = naki_string.find('m') {
+                // daiminkan
 
-                    if naki_string.len() != 9 {
-                        return Err(ConvertError::InvalidNaki(naki_string.clone()));
-                    }
+                if naki_string.len() != 9 {
+                    return Err(ConvertError::InvalidNaki(naki_string.clone()));
+                }
 
-                    match idx {
-                        // from kamicha
-                        // e.g. "m39393939" => kan 9s from kamicha
-                        0 => Ok(mjai::Event::Daiminkan {
-                            actor,
-                            target: (actor + 3) % 4,
-                            pai: pai_from_bytes(&naki[1..3])?,
-                            consumed: mjai::Consumed3([
-                                pai_from_bytes(&naki[3..5])?,
-                                pai_from_bytes(&naki[5..7])?,
-                                pai_from_bytes(&naki[7..9])?,
-                            ]),
-                        }),
-
-                        // from toimen
-                        // e.g. "26m262626" => kan 6p from toimen
-                        2 => Ok(mjai::Event::Daiminkan {
-                            actor,
-                            target: (actor + 2) % 4,
-                            pai: pai_from_bytes(&naki[3..5])?,
-                            consumed: mjai::Consumed3([
-                                pai_from_bytes(&naki[0..2])?,
-                                pai_from_bytes(&naki[5..7])?,
-                                pai_from_bytes(&naki[7..9])?,
-                            ]),
-                        }),
-
-                        // from shimocha
-                        // e.g. "131313m13" => kan 3m from shimocha
-                        6 => Ok(mjai::Event::Daiminkan {
-                            actor,
-                            target: (actor + 1) % 4,
-                            pai: pai_from_bytes(&naki[7..9])?,
-                            consumed: mjai::Consumed3([
-                                pai_from_bytes(&naki[0..2])?,
-                                pai_from_bytes(&naki[2..4])?,
-                                pai_from_bytes(&naki[4..6])?,
-                            ]),
-                        }),
-
-                        // ???
-                        _ => Err(ConvertError::InvalidNaki(naki_string.clone())),
-                    }
-                } else {
-                    Err(ConvertError::InvalidNaki(naki_string.clone()))
+                match idx {
+                    // from kamicha
+                    // e.g. "m39393939" => kan 9s from kamicha
+                    0 => Ok(mjai::Event::Daiminkan {
+                        actor,
+                        target: kamicha,
+                        pai: pai_from_bytes(&naki[1..3])?,
+                        consumed: mjai::Consumed3([
+                            pai_from_bytes(&naki[3..5])?,
+                            pai_from_bytes(&naki[5..7])?,
+                            pai_from_bytes(&naki[7..9])?,
+                        ]),
+                    }),
+
+                    // from toimen
+                    // e.g. "26m262626" => kan 6p from toimen
+                    2 if num_players == 4 => Ok(mjai::Event::Daiminkan {
+                        actor,
+                        target: (actor + 2) % 4,
+                        pai: pai_from_bytes(&naki[3..5])?,
+                        consumed: mjai::Consumed3([
+                            pai_from_bytes(&naki[0..2])?,
+                            pai_from_bytes(&naki[5..7])?,
+                            pai_from_bytes(&naki[7..9])?,
+                        ]),
+                    }),
+
+                    // from shimocha
+                    // e.g. "131313m13" => kan 3m from shimocha
+                    6 => Ok(mjai::Event::Daiminkan {
+                        actor,
+                        target: shimocha,
+                        pai: pai_from_bytes(&naki[7..9])?,
+                        consumed: mjai::Consumed3([
+                            pai_from_bytes(&naki[0..2])?,
+                            pai_from_bytes(&naki[2..4])?,
+                            pai_from_bytes(&naki[4..6])?,
+                        ]),
+                    }),
+
+                    // ???
+                    _ => Err(ConvertError::InvalidNaki(naki_string.clone())),
                 }
+            } else {
+                Err(ConvertError::InvalidNaki(naki_string.clone()))
             }
-        })
-        .collect()
+        }
+    }
 }
 
 fn discard_action_to_events(
     actor: u8,
     discards: &[tenhou::ActionItem],
-) -> Result<Vec<mjai::Event>> {
+) -> KyokuResult<Vec<mjai::Event>> {
     let mut ret = vec![];
 
-    for discard in discards {
-        match discard {
-            &tenhou::ActionItem::Pai(pai) => {
-                let ev = mjai::Event::Dahai {
-                    actor,
-                    pai,
-                    tsumogiri: false,
-                };
+    for (index, discard) in discards.iter().enumerate() {
+        discard_to_events(&mut ret, actor, discard).map_err(|error| KyokuError {
+            actor: Some(actor),
+            position: Some(ActionPosition {
+                sequence: ActionSequence::Discards,
+                index,
+            }),
+            error,
+        })?;
+    }
 
-                ret.push(ev);
-            }
+    Ok(ret)
+}
 
-            tenhou::ActionItem::Tsumogiri(_) => {
-                let ev = mjai::Event::Dahai {
-                    actor,
-                    pai: Pai::Unknown, // must be filled later
-                    tsumogiri: true,
-                };
+fn discard_to_events(
+    ret: &mut Vec<mjai::Event>,
+    actor: u8,
+    discard: &tenhou::ActionItem,
+) -> Result<()> {
+    match discard {
+        &tenhou::ActionItem::Pai(pai) => {
+            let ev = mjai::Event::Dahai {
+                actor,
+                pai,
+                tsumogiri: false,
+            };
 
-                ret.push(ev);
-            }
+            ret.push(ev);
+        }
 
-            tenhou::ActionItem::Naki(naki_string) => {
-                let naki = naki_string.as_bytes();
+        tenhou::ActionItem::Tsumogiri(_) => {
+            let ev = mjai::Event::Dahai {
+                actor,
+                pai: Pai::Unknown, // must be filled later
+                tsumogiri: true,
+            };
 
-                // only ankan, kakan and reach are possible
-                if let Some(idx) = naki_string.find('k') {
-                    // kakan
+            ret.push(ev);
+        }
 
-                    if naki_string.len() != 9 {
-                        return Err(ConvertError::InvalidNaki(naki_string.clone()));
-                    }
+        tenhou::ActionItem::Naki(naki_string) => {
+            let naki = naki_string.as_bytes();
 
-                    let ev = match idx {
-                        // previously pon from toimen
-                        // e.g. "k16161616" => pon 6m from kamicha then kan
-                        0 => mjai::Event::Kakan {
-                            actor,
-                            pai: pai_from_bytes(&naki[1..3])?,
-                            consumed: mjai::Consumed3([
-                                pai_from_bytes(&naki[3..5])?,
-                                pai_from_bytes(&naki[5..7])?,
-                                pai_from_bytes(&naki[7..9])?,
-                            ]),
-                        },
-
-                        // previously pon from toimen
-                        // e.g. "41k414141" => pon 1z from toimen then kan
-                        2 => mjai::Event::Kakan {
-                            actor,
-                            pai: pai_from_bytes(&naki[3..5])?,
-                            consumed: mjai::Consumed3([
-                                pai_from_bytes(&naki[0..2])?,
-                                pai_from_bytes(&naki[5..7])?,
-                                pai_from_bytes(&naki[7..9])?,
-                            ]),
-                        },
-
-                        // previously pon from shimocha
-                        // e.g. "4646k4646" => pon 6z from shimocha then kan
-                        4 => mjai::Event::Kakan {
-                            actor,
-                            pai: pai_from_bytes(&naki[5..7])?,
-                            consumed: mjai::Consumed3([
-                                pai_from_bytes(&naki[0..2])?,
-                                pai_from_bytes(&naki[2..4])?,
-                                pai_from_bytes(&naki[7..9])?,
-                            ]),
-                        },
-
-                        // ???
-                        _ => {
-                            return Err(ConvertError::InvalidNaki(naki_string.clone()));
-                        }
-                    };
+            // only ankan, kakan, nukidora and reach are possible
+            if naki_string.starts_with('f') {
+                // nukidora, only in sanma
+                // e.g. "f44" => nukidora 4z
 
-                    ret.push(ev);
-                } else if naki.contains(&b'a') {
-                    // ankan
-                    // for ankan, 'a' can only appear at [6]
-                    // e.g. "424242a42" => ankan 2z
+                if naki_string.len() != 3 {
+                    return Err(ConvertError::InvalidNaki(naki_string.clone()));
+                }
 
-                    if naki_string.len() != 9 {
-                        return Err(ConvertError::InvalidNaki(naki_string.clone()));
-                    }
+                ret.push(mjai::Event::Nukidora {
+                    actor,
+                    pai: pai_from_bytes(&naki[1..3])?,
+                });
+            } else if let Some(idx) = naki_string.find('k') {
+                // kakan
 
-                    let pai = pai_from_bytes(&naki[7..9])?;
-                    let ev = mjai::Event::Ankan {
+                if naki_string.len() != 9 {
+                    return Err(ConvertError::InvalidNaki(naki_string.clone()));
+                }
+
+                let ev = match idx {
+                    // previously pon from toimen
+                    // e.g. "k16161616" => pon 6m from kamicha then kan
+                    0 => mjai::Event::Kakan {
                         actor,
-                        consumed: mjai::Consumed4([
+                        pai: pai_from_bytes(&naki[1..3])?,
+                        consumed: mjai::Consumed3([
+                            pai_from_bytes(&naki[3..5])?,
+                            pai_from_bytes(&naki[5..7])?,
+                            pai_from_bytes(&naki[7..9])?,
+                        ]),
+                    },
+
+                    // previously pon from toimen
+                    // e.g. "41k414141" => pon 1z from toimen then kan
+                    2 => mjai::Event::Kakan {
+                        actor,
+                        pai: pai_from_bytes(&naki[3..5])?,
+                        consumed: mjai::Consumed3([
                             pai_from_bytes(&naki[0..2])?,
-                            pai_from_bytes(&naki[2..4])?,
-                            pai_from_bytes(&naki[4..6])?,
-                            pai,
+                            pai_from_bytes(&naki[5..7])?,
+                            pai_from_bytes(&naki[7..9])?,
                         ]),
-                    };
+                    },
 
-                    ret.push(ev);
-                } else {
-                    // reach
-                    // e.g. "r35" => discard 5s to reach
+                    // previously pon from shimocha
+                    // e.g. "4646k4646" => pon 6z from shimocha then kan
+                    4 => mjai::Event::Kakan {
+                        actor,
+                        pai: pai_from_bytes(&naki[5..7])?,
+                        consumed: mjai::Consumed3([
+                            pai_from_bytes(&naki[0..2])?,
+                            pai_from_bytes(&naki[2..4])?,
+                            pai_from_bytes(&naki[7..9])?,
+                        ]),
+                    },
 
-                    if naki_string.len() != 3 {
+                    // ???
+                    _ => {
                         return Err(ConvertError::InvalidNaki(naki_string.clone()));
                     }
+                };
 
-                    let pai = if &naki[1..3] == b"60" {
-                        Pai::Unknown
-                    } else {
-                        pai_from_bytes(&naki[1..3])?
-                    };
+                ret.push(ev);
+            } else if naki.contains(&b'a') {
+                // ankan
+                // for ankan, 'a' can only appear at [6]
+                // e.g. "424242a42" => ankan 2z
 
-                    ret.push(mjai::Event::Reach { actor });
-                    ret.push(mjai::Event::Dahai {
-                        actor,
-                        pai, // must be filled later if it is tsumogiri
-                        tsumogiri: pai == Pai::Unknown,
-                    });
+                if naki_string.len() != 9 {
+                    return Err(ConvertError::InvalidNaki(naki_string.clone()));
                 }
+
+                let pai = pai_from_bytes(&naki[7..9])?;
+                let ev = mjai::Event::Ankan {
+                    actor,
+                    consumed: mjai::Consumed4([
+                        pai_from_bytes(&naki[0..2])?,
+                        pai_from_bytes(&naki[2..4])?,
+                        pai_from_bytes(&naki[4..6])?,
+                        pai,
+                    ]),
+                };
+
+                ret.push(ev);
+            } else {
+                // reach
+                // e.g. "r35" => discard 5s to reach
+
+                if naki_string.len() != 3 {
+                    return Err(ConvertError::InvalidNaki(naki_string.clone()));
+                }
+
+                let pai = if &naki[1..3] == b"60" {
+                    Pai::Unknown
+                } else {
+                    pai_from_bytes(&naki[1..3])?
+                };
+
+                ret.push(mjai::Event::Reach { actor });
+                ret.push(mjai::Event::Dahai {
+                    actor,
+                    pai, // must be filled later if it is tsumogiri
+                    tsumogiri: pai == Pai::Unknown,
+                });
             }
-        };
-    }
+        }
+    };
 
-    Ok(ret)
+    Ok(())
 }
 
 fn end_kyoku(events: &mut Vec<mjai::Event>, kyoku: &tenhou::Kyoku) {
     match &kyoku.end_status {
         tenhou::kyoku::EndStatus::Hora { details } => {
-            events.extend(details.iter().map(|detail| mjai::Event::Hora {
-                actor: detail.who,
-                target: detail.target,
-                deltas: Some(detail.score_deltas),
+            events.extend(details.iter().map(|detail| {
+                mjai::Event::Hora {
+                    actor: detail.who,
+                    target: detail.target,
+                    deltas: Some(detail.score_deltas),
+                    pao: detail.pao,
+                    fu: Some(detail.fu).filter(|&fu| fu > 0),
+                    fan: Some(detail.han).filter(|&han| han > 0),
+                    limit: detail.limit,
+                    yakus: Some(
+                        detail
+                            .yaku
+                            .iter()
+                            .map(|y| (y.name.clone(), y.han))
+                            .collect(),
+                    )
+                    .filter(|yakus: &Vec<_>| !yakus.is_empty()),
+                    uradora_markers: Some(kyoku.ura_indicators.clone())
+                        .filter(|ura| !ura.is_empty()),
+                }
             }));
         }
 
-        tenhou::kyoku::EndStatus::Ryukyoku { score_deltas } => {
+        tenhou::kyoku::EndStatus::Ryukyoku {
+            kind,
+            score_deltas,
+            tenpai,
+        } => {
             events.push(mjai::Event::Ryukyoku {
                 deltas: Some(*score_deltas),
+                reason: Some(*kind),
+                tenpais: Some(*tenpai).filter(|_| *kind == tenhou::RyukyokuKind::Exhaustive),
             });
         }
     };
@@ -1,86 +1,265 @@
+use std::num::ParseIntError;
 use std::str::FromStr;
 
 use thiserror::Error;
 
-#[derive(Debug, Clone)]
+/// Selects kyokus from a log.
+///
+/// The syntax is a comma separated list of rules, where each rule is one of
+///
+/// * `E1`: E1 with 0 honba.
+/// * `E1.2`: E1 with 2 honba.
+/// * `E1.*`: E1 with any honba.
+/// * `E3-S2`: every kyoku from E3 to S2 with any honba, both inclusive.
+///   Endpoints can have honba as well, e.g. `E3.1-S2.0`.
+/// * `last:3`: the last 3 kyokus in the log.
+/// * `#3`: the kyoku at index 3 of the log, counting from 0, which is the same
+///   as `ts=3` in a tenhou.net log URL.
+///
+/// A rule prefixed with `!` excludes the kyokus it matches. If there are only
+/// exclusions, all the other kyokus are selected, so that `!S4` means
+/// everything but S4. An empty string selects everything.
+#[derive(Debug, Clone, Default)]
 pub struct KyokuFilter {
-    whitelist: [Vec<u8>; 16],
+    includes: Vec<Rule>,
+    excludes: Vec<Rule>,
+}
+
+/// `(kyoku, honba)`, where kyoku counts from 0 (E1) to 15 (N4).
+type Point = (u8, u8);
+
+#[derive(Debug, Clone, Copy)]
+enum Rule {
+    /// Inclusive on both ends.
+    Range(Point, Point),
+    Last(usize),
+    Index(usize),
 }
 
 #[derive(Debug, Error)]
 pub enum ParseError {
-    #[error("missing bakaze")]
-    MissingBakaze,
-    #[error("missing kyoku")]
-    MissingKyoku,
-
-    #[error(r#"invalid bakaze: {0:?} (expected one of "E", "S", "W", "N")"#)]
-    InvalidBakaze(String),
-    #[error("invalid kyoku: {0:?}")]
-    InvalidKyoku(#[source] <u8 as FromStr>::Err),
-    #[error("invalid honba: {0:?}")]
-    InvalidHonba(#[source] <u8 as FromStr>::Err),
-
-    #[error("invalid kyoku range: {0:?} (expected within [1, 4])")]
-    InvalidKyokuRange(u8),
+    #[error("empty rule at position {pos}")]
+    EmptyRule { pos: usize },
+    #[error("missing bakaze at position {pos}")]
+    MissingBakaze { pos: usize },
+    #[error("missing kyoku at position {pos}")]
+    MissingKyoku { pos: usize },
+
+    #[error(r#"invalid bakaze {found:?} at position {pos} (expected one of "E", "S", "W", "N")"#)]
+    InvalidBakaze { pos: usize, found: String },
+    #[error("invalid kyoku at position {pos}")]
+    InvalidKyoku {
+        pos: usize,
+        #[source]
+        source: ParseIntError,
+    },
+    #[error("invalid honba at position {pos}")]
+    InvalidHonba {
+        pos: usize,
+        #[source]
+        source: ParseIntError,
+    },
+    #[error("invalid count of last at position {pos}")]
+    InvalidLast {
+        pos: usize,
+        #[source]
+        source: ParseIntError,
+    },
+    #[error("invalid index at position {pos}")]
+    InvalidIndex {
+        pos: usize,
+        #[source]
+        source: ParseIntError,
+    },
+
+    #[error("invalid kyoku range at position {pos}: {kyoku} (expected within [1, 4])")]
+    InvalidKyokuRange { pos: usize, kyoku: u8 },
+    #[error("reversed range at position {pos}")]
+    ReversedRange { pos: usize },
+}
+
+impl ParseError {
+    /// Byte offset in the input where parsing failed.
+    #[inline]
+    pub fn position(&self) -> usize {
+        match *self {
+            ParseError::EmptyRule { pos }
+            | ParseError::MissingBakaze { pos }
+            | ParseError::MissingKyoku { pos }
+            | ParseError::InvalidBakaze { pos, .. }
+            | ParseError::InvalidKyoku { pos, .. }
+            | ParseError::InvalidHonba { pos, .. }
+            | ParseError::InvalidLast { pos, .. }
+            | ParseError::InvalidIndex { pos, .. }
+            | ParseError::InvalidKyokuRange { pos, .. }
+            | ParseError::ReversedRange { pos } => pos,
+        }
+    }
 }
 
 impl FromStr for KyokuFilter {
     type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut whitelist: [Vec<u8>; 16] = Default::default();
+        let mut filter = Self::default();
+        if s.trim().is_empty() {
+            return Ok(filter);
+        }
 
+        let mut offset = 0;
         for part in s.split(',') {
-            let mut chars = part.chars();
-
-            let bakaze = chars
-                .next()
-                .map(|p| p.to_uppercase().to_string())
-                .ok_or(ParseError::MissingBakaze)?;
-
-            let offset = match bakaze.as_ref() {
-                "E" => 0,
-                "S" => 4,
-                "W" => 8,
-                "N" => 12,
-                _ => return Err(ParseError::InvalidBakaze(bakaze)),
-            };
-            let kyoku_num: u8 = chars
-                .next()
-                .ok_or(ParseError::MissingKyoku)?
-                .to_string()
-                .parse()
-                .map_err(ParseError::InvalidKyoku)?;
-
-            if kyoku_num < 1 || kyoku_num > 4 {
-                return Err(ParseError::InvalidKyokuRange(kyoku_num));
+            let pos = offset + (part.len() - part.trim_start().len());
+            offset += part.len() + 1;
+
+            let part = part.trim();
+            if part.is_empty() {
+                return Err(ParseError::EmptyRule { pos });
             }
 
-            let kyoku = offset + kyoku_num - 1;
-            let honba = if let Some('.') = chars.next() {
-                chars
-                    .collect::<String>()
-                    .parse()
-                    .map_err(ParseError::InvalidHonba)?
-            } else {
-                0
+            let (is_exclusion, rule, pos) = match part.strip_prefix('!') {
+                Some(rest) => (true, rest, pos + 1),
+                None => (false, part, pos),
             };
+            let rule = parse_rule(rule, pos)?;
+
+            if is_exclusion {
+                filter.excludes.push(rule);
+            } else {
+                filter.includes.push(rule);
+            }
+        }
+
+        Ok(filter)
+    }
+}
 
-            whitelist[kyoku as usize].push(honba);
+fn parse_rule(s: &str, pos: usize) -> Result<Rule, ParseError> {
+    if let Some(n) = s.strip_prefix("last:") {
+        let n = n.parse().map_err(|source| ParseError::InvalidLast {
+            pos: pos + "last:".len(),
+            source,
+        })?;
+        return Ok(Rule::Last(n));
+    }
+    if let Some(n) = s.strip_prefix('#') {
+        let n = n.parse().map_err(|source| ParseError::InvalidIndex {
+            pos: pos + 1,
+            source,
+        })?;
+        return Ok(Rule::Index(n));
+    }
+
+    let (start, end) = match s.find('-') {
+        Some(idx) => {
+            let start = parse_point(&s[..idx], pos, false)?;
+            let end = parse_point(&s[idx + 1..], pos + idx + 1, true)?;
+            (start.0, end.1)
         }
+        None => parse_point(s, pos, false)?,
+    };
+
+    if start > end {
+        return Err(ParseError::ReversedRange { pos });
+    }
+    Ok(Rule::Range(start, end))
+}
+
+/// Parses something like "E1", "E1.2" or "E1.*" into the range of
+/// `(kyoku, honba)` it covers. Without an explicit honba, it covers 0 honba
+/// only, or any honba if `any_honba_by_default` is set, which is the case for
+/// the end of a range.
+fn parse_point(
+    s: &str,
+    pos: usize,
+    any_honba_by_default: bool,
+) -> Result<(Point, Point), ParseError> {
+    let mut chars = s.chars();
+
+    let bakaze_char = chars.next().ok_or(ParseError::MissingBakaze { pos })?;
+    let bakaze = bakaze_char.to_uppercase().to_string();
+    let offset = match bakaze.as_ref() {
+        "E" => 0,
+        "S" => 4,
+        "W" => 8,
+        "N" => 12,
+        _ => return Err(ParseError::InvalidBakaze { pos, found: bakaze }),
+    };
+
+    let kyoku_pos = pos + bakaze_char.len_utf8();
+    let rest = chars.as_str();
+    let (kyoku_str, honba_str) = match rest.find('.') {
+        Some(idx) => (&rest[..idx], Some(&rest[idx + 1..])),
+        None => (rest, None),
+    };
+    if kyoku_str.is_empty() {
+        return Err(ParseError::MissingKyoku { pos: kyoku_pos });
+    }
 
-        Ok(Self { whitelist })
+    let kyoku_num: u8 = kyoku_str
+        .parse()
+        .map_err(|source| ParseError::InvalidKyoku {
+            pos: kyoku_pos,
+            source,
+        })?;
+    if !(1..=4).contains(&kyoku_num) {
+        return Err(ParseError::InvalidKyokuRange {
+            pos: kyoku_pos,
+            kyoku: kyoku_num,
+        });
+    }
+    let kyoku = offset + kyoku_num - 1;
+
+    let honba_pos = kyoku_pos + kyoku_str.len() + 1;
+    match honba_str {
+        None if !any_honba_by_default => Ok(((kyoku, 0), (kyoku, 0))),
+        None | Some("*") => Ok(((kyoku, 0), (kyoku, u8::MAX))),
+        Some(h) => {
+            let honba = h.parse().map_err(|source| ParseError::InvalidHonba {
+                pos: honba_pos,
+                source,
+            })?;
+            Ok(((kyoku, honba), (kyoku, honba)))
+        }
     }
 }
 
 impl KyokuFilter {
+    /// Selects only the kyoku at `index` of the log, counting from 0.
+    #[inline]
+    pub fn nth(index: usize) -> Self {
+        Self {
+            includes: vec![Rule::Index(index)],
+            excludes: vec![],
+        }
+    }
+
+    /// Tests a kyoku, where `index` is its position in the log of `len`
+    /// kyokus, starting from 0.
     #[inline]
-    pub fn test(&self, kyoku: u8, honba: u8) -> bool {
-        if kyoku > 16 {
-            return false;
+    pub fn test(&self, kyoku: u8, honba: u8, index: usize, len: usize) -> bool {
+        let matches = |rule: &Rule| match *rule {
+            Rule::Range(start, end) => (start..=end).contains(&(kyoku, honba)),
+            Rule::Last(n) => len - index <= n,
+            Rule::Index(n) => index == n,
         };
 
-        self.whitelist[kyoku as usize].iter().any(|&h| h == honba)
+        (self.includes.is_empty() || self.includes.iter().any(matches))
+            && !self.excludes.iter().any(matches)
+    }
+
+    /// Retains only the items selected by the filter, where `key` returns the
+    /// `(kyoku, honba)` of an item.
+    pub fn retain<T, F>(&self, items: &mut Vec<T>, key: F)
+    where
+        F: Fn(&T) -> Point,
+    {
+        let len = items.len();
+        let mut index = 0;
+        items.retain(|item| {
+            let (kyoku, honba) = key(item);
+            let selected = self.test(kyoku, honba, index, len);
+            index += 1;
+            selected
+        });
     }
 }
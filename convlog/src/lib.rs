@@ -1,13 +1,31 @@
-//! Provides methods to transform mahjong logs from tenhou.net/6 format into
+//! Provides methods to transform mahjong logs between tenhou.net/6 format and
 //! mjai format.
 
+mod anonymize;
+pub mod board;
 mod conv;
 mod kyoku_filter;
+mod log_ref;
+pub mod majsoul;
 pub mod mjai;
+pub mod mjlog;
+pub mod notation;
 pub mod pai;
+mod reverse;
+pub mod score;
+pub mod shanten;
 pub mod tenhou;
+mod validate;
 
+pub use anonymize::Anonymizer;
+pub use board::Board;
 pub use conv::tenhou_to_mjai;
-pub use conv::ConvertError;
+pub use conv::tenhou_to_mjai_iter;
+pub use conv::tenhou_to_mjai_lenient;
+pub use conv::{ActionPosition, ActionSequence, ConvertError, Diagnostic, KyokuEvents, Resolution};
 pub use kyoku_filter::KyokuFilter;
-pub use pai::Pai;
+pub use log_ref::{TenhouLogRef, TenhouLogRefError};
+pub use pai::{Pai, Suit, TileCounts};
+pub use reverse::mjai_to_tenhou;
+pub use reverse::ReverseConvertError;
+pub use validate::{validate, ValidationError, ValidationErrorKind};
@@ -0,0 +1,162 @@
+use crate::KyokuFilter;
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+/// A reference to a log on tenhou.net, parsed from either a bare log ID like
+/// `2019050417gm-0029-0000-4f2a8622` or a URL of the viewers, such as
+///
+/// * `https://tenhou.net/0/?log=2019050417gm-0029-0000-4f2a8622&tw=2&ts=3`
+/// * `http://tenhou.net/3/?log=2019050417gm-0029-0000-4f2a8622&tw=2`
+/// * `https://tenhou.net/5/?log=2019050417gm-0029-0000-4f2a8622`
+/// * `tenhou.net/6/?log=2019050417gm-0029-0000-4f2a8622&tw=1`
+///
+/// Unknown parameters are ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TenhouLogRef {
+    pub id: String,
+    /// The seat the viewer is looking from, `tw=` in the URL.
+    pub actor: Option<u8>,
+    /// The index of the kyoku the viewer starts from, `ts=` in the URL,
+    /// counting from 0.
+    pub kyoku_index: Option<usize>,
+}
+
+#[derive(Debug, Error)]
+pub enum TenhouLogRefError {
+    #[error("empty tenhou log reference")]
+    Empty,
+    #[error("unsupported tenhou.net URL {0:?}")]
+    UnsupportedUrl(String),
+    #[error("missing log ID in {0:?}")]
+    MissingId(String),
+    #[error("invalid tenhou log ID {0:?}")]
+    InvalidId(String),
+    #[error("invalid value {value:?} of parameter {name:?}")]
+    InvalidParam { name: &'static str, value: String },
+}
+
+impl TenhouLogRef {
+    /// Returns a filter that selects the kyoku of `ts=` only, if there is
+    /// one.
+    #[inline]
+    pub fn kyoku_filter(&self) -> Option<KyokuFilter> {
+        self.kyoku_index.map(KyokuFilter::nth)
+    }
+}
+
+impl FromStr for TenhouLogRef {
+    type Err = TenhouLogRefError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(TenhouLogRefError::Empty);
+        }
+
+        if !s.contains('/') && !s.contains('?') {
+            check_id(s)?;
+            return Ok(Self {
+                id: s.to_owned(),
+                actor: None,
+                kyoku_index: None,
+            });
+        }
+
+        let rest = s
+            .strip_prefix("https://")
+            .or_else(|| s.strip_prefix("http://"))
+            .unwrap_or(s);
+        let rest = rest.strip_prefix("www.").unwrap_or(rest);
+        let (path, query) = match rest.find('?') {
+            Some(idx) => (&rest[..idx], &rest[idx + 1..]),
+            None => return Err(TenhouLogRefError::MissingId(s.to_owned())),
+        };
+        let path = path.trim_end_matches('/');
+        if !matches!(
+            path,
+            "tenhou.net/0" | "tenhou.net/3" | "tenhou.net/5" | "tenhou.net/6"
+        ) {
+            return Err(TenhouLogRefError::UnsupportedUrl(s.to_owned()));
+        }
+
+        // the viewers also take the parameters after '#'.
+        let mut log_ref = Self {
+            id: String::new(),
+            actor: None,
+            kyoku_index: None,
+        };
+        for param in query.split(['&', '#']) {
+            let (name, value) = match param.find('=') {
+                Some(idx) => (&param[..idx], &param[idx + 1..]),
+                None => continue,
+            };
+            match name {
+                "log" => log_ref.id = value.to_owned(),
+                "tw" => {
+                    let actor = value.parse().ok().filter(|&a| a < 4).ok_or_else(|| {
+                        TenhouLogRefError::InvalidParam {
+                            name: "tw",
+                            value: value.to_owned(),
+                        }
+                    })?;
+                    log_ref.actor = Some(actor);
+                }
+                "ts" => {
+                    let index = value.parse().map_err(|_| TenhouLogRefError::InvalidParam {
+                        name: "ts",
+                        value: value.to_owned(),
+                    })?;
+                    log_ref.kyoku_index = Some(index);
+                }
+                _ => (),
+            }
+        }
+
+        if log_ref.id.is_empty() {
+            return Err(TenhouLogRefError::MissingId(s.to_owned()));
+        }
+        check_id(&log_ref.id)?;
+
+        Ok(log_ref)
+    }
+}
+
+impl fmt::Display for TenhouLogRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "https://tenhou.net/0/?log={}", self.id)?;
+        if let Some(actor) = self.actor {
+            write!(f, "&tw={}", actor)?;
+        }
+        if let Some(index) = self.kyoku_index {
+            write!(f, "&ts={}", index)?;
+        }
+        Ok(())
+    }
+}
+
+/// A log ID looks like `2019050417gm-0029-0000-4f2a8622`, that is the hour
+/// the game started, "gm", the rule and lobby, and a hash, which is prefixed
+/// with "x" when obfuscated.
+fn check_id(id: &str) -> Result<(), TenhouLogRefError> {
+    let invalid = || TenhouLogRefError::InvalidId(id.to_owned());
+
+    let mut parts = id.split('-');
+    let time = parts.next().ok_or_else(invalid)?;
+    let digits = time.strip_suffix("gm").ok_or_else(invalid)?;
+    if digits.len() != 10 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    let rest: Vec<_> = parts.collect();
+    let is_hex = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_hexdigit());
+    match rest.as_slice() {
+        [rule, lobby, hash]
+            if is_hex(rule) && is_hex(lobby) && is_hex(hash.trim_start_matches('x')) =>
+        {
+            Ok(())
+        }
+        _ => Err(invalid()),
+    }
+}
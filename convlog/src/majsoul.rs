@@ -0,0 +1,812 @@
+//! Provides methods to parse Mahjong Soul (雀魂) game records.
+//!
+//! The input is a game record decoded from protobuf into JSON, which looks
+//! like
+//!
+//! ```json
+//! {
+//!     "head": {
+//!         "config": {"mode": {"mode": 2}},
+//!         "accounts": [{"seat": 0, "nickname": "..."}, ...]
+//!     },
+//!     "actions": [
+//!         {"name": "RecordNewRound", "data": {...}},
+//!         {"name": "RecordDiscardTile", "data": {...}},
+//!         ...
+//!     ]
+//! }
+//! ```
+//!
+//! where `actions` can also be named `records`. Unknown actions are ignored.
+//! Fields of zero, e.g. seat 0, can be left out as protobuf does in JSON.
+//!
+//! The yaku, fu and han of hora are not decoded, only the score deltas and
+//! ura dora indicators are kept.
+
+use crate::mjai;
+use crate::reverse::{called_position, naki_string};
+use crate::tenhou::{self, kyoku, ActionItem, ActionTable, GameLength, RuleSet};
+use crate::{tenhou_to_mjai, ConvertError, Pai};
+
+use std::convert::TryFrom;
+use std::io::prelude::*;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json as json;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ParseError {
+    #[error("failed to parse Mahjong Soul record: {0}")]
+    Json(#[from] json::Error),
+
+    #[error("invalid tile: {0:?}")]
+    InvalidTile(String),
+
+    #[error("invalid seat: {0}")]
+    InvalidSeat(u8),
+
+    #[error("invalid meld in {record}: {tiles:?}")]
+    InvalidMeld {
+        record: &'static str,
+        tiles: Vec<String>,
+    },
+
+    #[error("unexpected {0} outside of a kyoku")]
+    RecordOutsideKyoku(String),
+
+    #[error("failed to convert into mjai events: {0}")]
+    Convert(#[from] ConvertError),
+}
+
+pub type Result<T> = std::result::Result<T, ParseError>;
+
+/// Parse a Mahjong Soul record from a reader.
+pub fn parse_reader<R>(reader: R) -> Result<tenhou::Log>
+where
+    R: Read,
+{
+    let record: Record = json::from_reader(reader)?;
+    parse_record(record)
+}
+
+/// Parse a Mahjong Soul record from JSON string.
+pub fn parse_str(s: &str) -> Result<tenhou::Log> {
+    let record: Record = json::from_str(s)?;
+    parse_record(record)
+}
+
+/// Parse a Mahjong Soul record from JSON string and transform it into mjai
+/// format directly.
+pub fn parse_str_to_mjai(s: &str) -> Result<Vec<mjai::Event>> {
+    let log = parse_str(s)?;
+    Ok(tenhou_to_mjai(&log)?)
+}
+
+#[derive(Debug, Deserialize)]
+struct Record {
+    #[serde(default)]
+    head: Head,
+    #[serde(alias = "records")]
+    actions: Vec<Action>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Head {
+    config: Config,
+    accounts: Vec<Account>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Config {
+    mode: Mode,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Mode {
+    /// 1 and 2 for 4-player east and south, 11 and 12 for 3-player ones.
+    mode: u32,
+    detail_rule: DetailRule,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct DetailRule {
+    /// Number of aka dora, absent means the default (with aka).
+    dora_count: Option<u8>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Account {
+    #[serde(default)]
+    seat: u8,
+    #[serde(default)]
+    nickname: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Action {
+    name: String,
+    #[serde(default)]
+    data: json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct NewRound {
+    #[serde(default)]
+    chang: u8,
+    #[serde(default)]
+    ju: u8,
+    #[serde(default)]
+    ben: u8,
+    #[serde(default)]
+    liqibang: u8,
+    scores: Vec<i32>,
+    #[serde(default)]
+    doras: Vec<String>,
+    #[serde(default)]
+    dora: Option<String>,
+    #[serde(default)]
+    tiles0: Vec<String>,
+    #[serde(default)]
+    tiles1: Vec<String>,
+    #[serde(default)]
+    tiles2: Vec<String>,
+    #[serde(default)]
+    tiles3: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DealTile {
+    #[serde(default)]
+    seat: u8,
+    tile: String,
+    #[serde(default)]
+    doras: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiscardTile {
+    #[serde(default)]
+    seat: u8,
+    tile: String,
+    #[serde(default)]
+    moqie: bool,
+    #[serde(default)]
+    is_liqi: bool,
+    #[serde(default)]
+    is_wliqi: bool,
+    #[serde(default)]
+    doras: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChiPengGang {
+    #[serde(default)]
+    seat: u8,
+    /// 0 for chi, 1 for pon and 2 for daiminkan.
+    #[serde(default, rename = "type")]
+    kind: u8,
+    tiles: Vec<String>,
+    froms: Vec<u8>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnGangAddGang {
+    #[serde(default)]
+    seat: u8,
+    /// 2 for kakan and 3 for ankan.
+    #[serde(rename = "type")]
+    kind: u8,
+    tiles: String,
+    #[serde(default)]
+    doras: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BaBei {
+    #[serde(default)]
+    seat: u8,
+}
+
+#[derive(Debug, Deserialize)]
+struct Hule {
+    hules: Vec<HuleInfo>,
+    #[serde(default)]
+    delta_scores: Vec<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HuleInfo {
+    #[serde(default)]
+    seat: u8,
+    #[serde(default)]
+    zimo: bool,
+    #[serde(default)]
+    point_rong: i32,
+    #[serde(default)]
+    li_doras: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NoTile {
+    #[serde(default)]
+    liujumanguan: bool,
+    #[serde(default)]
+    players: Vec<NoTilePlayer>,
+    #[serde(default)]
+    scores: Vec<NoTileScore>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NoTilePlayer {
+    #[serde(default)]
+    tingpai: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct NoTileScore {
+    #[serde(default)]
+    delta_scores: Vec<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LiuJu {
+    #[serde(default, rename = "type")]
+    kind: u8,
+}
+
+fn parse_record(record: Record) -> Result<tenhou::Log> {
+    let mode = &record.head.config.mode;
+
+    let mut names: [String; 4] = Default::default();
+    for account in &record.head.accounts {
+        let name = names
+            .get_mut(account.seat as usize)
+            .ok_or(ParseError::InvalidSeat(account.seat))?;
+        *name = account.nickname.clone();
+    }
+
+    let game_length = if mode.mode % 10 == 1 {
+        GameLength::Tonpuu
+    } else {
+        GameLength::Hanchan
+    };
+    let has_aka = mode.detail_rule.dora_count != Some(0);
+    let mut is_sanma = (11..=12).contains(&mode.mode);
+
+    let mut kyokus = vec![];
+    let mut kyoku: Option<KyokuBuilder> = None;
+
+    for action in record.actions {
+        if action.name == "RecordNewRound" {
+            if let Some(builder) = kyoku.take() {
+                kyokus.push(builder.build());
+            }
+
+            let new_round: NewRound = from_data(action.data)?;
+            // sanma records have only 3 scores.
+            is_sanma |= new_round.scores.len() == 3;
+            kyoku = Some(KyokuBuilder::new(new_round, has_aka, is_sanma)?);
+            continue;
+        }
+
+        let builder = match kyoku.as_mut() {
+            Some(builder) => builder,
+            None => match action.name.as_str() {
+                "RecordDealTile"
+                | "RecordDiscardTile"
+                | "RecordChiPengGang"
+                | "RecordAnGangAddGang"
+                | "RecordBaBei"
+                | "RecordHule"
+                | "RecordNoTile"
+                | "RecordLiuJu" => return Err(ParseError::RecordOutsideKyoku(action.name)),
+                _ => continue,
+            },
+        };
+        builder.push(&action.name, action.data)?;
+    }
+
+    if let Some(builder) = kyoku.take() {
+        kyokus.push(builder.build());
+    }
+
+    Ok(tenhou::Log {
+        names,
+        rule: RuleSet {
+            is_sanma,
+            game_length,
+            aka_counts: if has_aka { [1; 3] } else { [0; 3] },
+            ..Default::default()
+        },
+        players: Default::default(),
+        lobby: Default::default(),
+        kyokus,
+    })
+}
+
+struct KyokuBuilder {
+    num_players: u8,
+    has_aka: bool,
+    oya: u8,
+    meta: kyoku::Meta,
+    scoreboard: [i32; 4],
+    dora_indicators: Vec<Pai>,
+    ura_indicators: Vec<Pai>,
+    action_tables: [ActionTable; 4],
+    end_status: Option<kyoku::EndStatus>,
+
+    // (target, called pai, consumed) of every pon, so that kakan can find out
+    // where the original pon came from.
+    pons: [Vec<(u8, Pai, [Pai; 2])>; 4],
+    // the seat who discarded or kakan-ed last, as the target of ron.
+    last_discarder: u8,
+}
+
+impl KyokuBuilder {
+    fn new(new_round: NewRound, has_aka: bool, is_sanma: bool) -> Result<Self> {
+        let num_players = if is_sanma { 3 } else { 4 };
+        let oya = new_round.ju;
+
+        let mut scoreboard = [0; 4];
+        for (s, &score) in scoreboard.iter_mut().zip(&new_round.scores) {
+            *s = score;
+        }
+
+        let doras = if new_round.doras.is_empty() {
+            new_round.dora.into_iter().collect()
+        } else {
+            new_round.doras
+        };
+        let dora_indicators = doras
+            .iter()
+            .map(|s| pai_from_str(s))
+            .collect::<Result<_>>()?;
+
+        let all_tiles = [
+            new_round.tiles0,
+            new_round.tiles1,
+            new_round.tiles2,
+            new_round.tiles3,
+        ];
+        let mut action_tables = [
+            empty_action_table(),
+            empty_action_table(),
+            empty_action_table(),
+            empty_action_table(),
+        ];
+        for (seat, (table, tiles)) in action_tables.iter_mut().zip(&all_tiles).enumerate() {
+            let mut pais = tiles
+                .iter()
+                .map(|s| pai_from_str(s))
+                .collect::<Result<Vec<_>>>()?;
+
+            // The oya is dealt 14 tiles, where the last one is regarded as
+            // the first tsumo.
+            if seat == oya as usize && pais.len() == 14 {
+                let first_tsumo = pais.pop().unwrap_or_default();
+                table.takes.push(ActionItem::Pai(first_tsumo));
+            }
+
+            match pais.len() {
+                13 => {
+                    sort_haipai(&mut pais);
+                    table.haipai.copy_from_slice(&pais);
+                }
+                // the absent seat in sanma.
+                0 if seat as u8 >= num_players => (),
+                _ => return Err(ParseError::InvalidTile(tiles.join(","))),
+            };
+        }
+
+        Ok(Self {
+            num_players,
+            has_aka,
+            oya,
+            meta: kyoku::Meta {
+                kyoku_num: new_round.chang * 4 + new_round.ju,
+                honba: new_round.ben,
+                kyotaku: new_round.liqibang,
+            },
+            scoreboard,
+            dora_indicators,
+            ura_indicators: vec![],
+            action_tables,
+            end_status: None,
+            pons: Default::default(),
+            last_discarder: oya,
+        })
+    }
+
+    fn push(&mut self, name: &str, data: json::Value) -> Result<()> {
+        match name {
+            "RecordDealTile" => {
+                let deal: DealTile = from_data(data)?;
+                let seat = self.check_seat(deal.seat)?;
+                let pai = pai_from_str(&deal.tile)?;
+
+                self.action_tables[seat].takes.push(ActionItem::Pai(pai));
+                self.update_doras(&deal.doras)?;
+            }
+
+            "RecordDiscardTile" => {
+                let discard: DiscardTile = from_data(data)?;
+                let seat = self.check_seat(discard.seat)?;
+                let pai = pai_from_str(&discard.tile)?;
+
+                if discard.moqie {
+                    self.fix_first_tsumo(seat, pai);
+                }
+
+                let item = if discard.is_liqi || discard.is_wliqi {
+                    // e.g. "r35" => discard 5s to reach, "r60" for tsumogiri
+                    let id = if discard.moqie { 60 } else { pai.as_u8() };
+                    ActionItem::Naki(format!("r{}", id))
+                } else if discard.moqie {
+                    ActionItem::Tsumogiri(60)
+                } else {
+                    ActionItem::Pai(pai)
+                };
+
+                self.action_tables[seat].discards.push(item);
+                self.last_discarder = seat as u8;
+                self.update_doras(&discard.doras)?;
+            }
+
+            "RecordChiPengGang" => {
+                let meld: ChiPengGang = from_data(data)?;
+                self.push_chi_peng_gang(meld)?;
+            }
+
+            "RecordAnGangAddGang" => {
+                let meld: AnGangAddGang = from_data(data)?;
+                self.push_an_gang_add_gang(meld)?;
+            }
+
+            "RecordBaBei" => {
+                let babei: BaBei = from_data(data)?;
+                let seat = self.check_seat(babei.seat)?;
+
+                // e.g. "f44" => nukidora 4z
+                let naki = format!("f{}", Pai::North.as_u8());
+                self.action_tables[seat]
+                    .discards
+                    .push(ActionItem::Naki(naki));
+                self.last_discarder = seat as u8;
+            }
+
+            "RecordHule" => {
+                let hule: Hule = from_data(data)?;
+                self.push_hule(hule)?;
+            }
+
+            "RecordNoTile" => {
+                let no_tile: NoTile = from_data(data)?;
+
+                // there can be multiple entries for nagashi mangan.
+                let mut score_deltas = [0; 4];
+                for score in &no_tile.scores {
+                    for (d, &delta) in score_deltas.iter_mut().zip(&score.delta_scores) {
+                        *d += delta;
+                    }
+                }
+
+                let (kind, tenpai) = if no_tile.liujumanguan {
+                    (kyoku::RyukyokuKind::NagashiMangan, [false; 4])
+                } else {
+                    let mut tenpai = [false; 4];
+                    for (t, player) in tenpai.iter_mut().zip(&no_tile.players) {
+                        *t = player.tingpai;
+                    }
+                    (kyoku::RyukyokuKind::Exhaustive, tenpai)
+                };
+
+                self.end_status = Some(kyoku::EndStatus::Ryukyoku {
+                    kind,
+                    score_deltas,
+                    tenpai,
+                });
+            }
+
+            "RecordLiuJu" => {
+                let liu_ju: LiuJu = from_data(data)?;
+                let kind = match liu_ju.kind {
+                    1 => kyoku::RyukyokuKind::KyuushuKyuuhai,
+                    2 => kyoku::RyukyokuKind::SuufonRenda,
+                    3 => kyoku::RyukyokuKind::SuukanSanra,
+                    4 => kyoku::RyukyokuKind::SuuchaRiichi,
+                    5 => kyoku::RyukyokuKind::SanchaHoo,
+                    _ => kyoku::RyukyokuKind::Exhaustive,
+                };
+
+                self.end_status = Some(kyoku::EndStatus::Ryukyoku {
+                    kind,
+                    score_deltas: [0; 4],
+                    tenpai: [false; 4],
+                });
+            }
+
+            _ => (),
+        };
+
+        Ok(())
+    }
+
+    fn push_chi_peng_gang(&mut self, meld: ChiPengGang) -> Result<()> {
+        let seat = self.check_seat(meld.seat)?;
+        let invalid = || ParseError::InvalidMeld {
+            record: "RecordChiPengGang",
+            tiles: meld.tiles.clone(),
+        };
+
+        if meld.tiles.len() != meld.froms.len() {
+            return Err(invalid());
+        }
+
+        let mut target = None;
+        let mut called = Pai::Unknown;
+        let mut consumed = vec![];
+        for (tile, &from) in meld.tiles.iter().zip(&meld.froms) {
+            let pai = pai_from_str(tile)?;
+            if from == meld.seat {
+                consumed.push(pai);
+            } else {
+                target = Some(self.check_seat(from)? as u8);
+                called = pai;
+            }
+        }
+        let target = target.ok_or_else(invalid)?;
+        let actor = meld.seat;
+
+        match (meld.kind, consumed.len()) {
+            (0, 2) => {
+                // e.g. "c275226" => chi 7p with 06p from kamicha
+                let naki = format!(
+                    "c{}{}{}",
+                    called.as_u8(),
+                    consumed[0].as_u8(),
+                    consumed[1].as_u8()
+                );
+                self.action_tables[seat].takes.push(ActionItem::Naki(naki));
+            }
+
+            (1, 2) => {
+                let naki = naki_string(actor, target, self.num_players, 'p', called, &consumed);
+                self.action_tables[seat].takes.push(ActionItem::Naki(naki));
+                self.pons[seat].push((target, called, [consumed[0], consumed[1]]));
+            }
+
+            (2, 3) => {
+                let naki = naki_string(actor, target, self.num_players, 'm', called, &consumed);
+                let table = &mut self.action_tables[seat];
+                table.takes.push(ActionItem::Naki(naki));
+
+                // daiminkan leaves a placeholder "0" in the discard table.
+                table.discards.push(ActionItem::Pai(Pai::Unknown));
+            }
+
+            _ => return Err(invalid()),
+        };
+
+        Ok(())
+    }
+
+    fn push_an_gang_add_gang(&mut self, meld: AnGangAddGang) -> Result<()> {
+        let seat = self.check_seat(meld.seat)?;
+        let actor = meld.seat;
+        let pai = pai_from_str(&meld.tiles)?;
+        let invalid = || ParseError::InvalidMeld {
+            record: "RecordAnGangAddGang",
+            tiles: vec![meld.tiles.clone()],
+        };
+
+        match meld.kind {
+            2 => {
+                let &(target, called, consumed) = self.pons[seat]
+                    .iter()
+                    .find(|&&(_, called, _)| called.deaka() == pai.deaka())
+                    .ok_or_else(invalid)?;
+
+                // For kakan, the called pai is placed at where it was taken,
+                // followed by the added one.
+                let mut pon = consumed.to_vec();
+                pon.insert(called_position(actor, target, self.num_players), called);
+
+                let naki = naki_string(actor, target, self.num_players, 'k', pai, &pon);
+                self.action_tables[seat]
+                    .discards
+                    .push(ActionItem::Naki(naki));
+            }
+
+            3 => {
+                // the record only tells the kind of pai, while all 4 of them
+                // are consumed, including the aka one.
+                let normal = pai.deaka();
+                let last = match normal {
+                    Pai::Man5 if self.has_aka => Pai::AkaMan5,
+                    Pai::Pin5 if self.has_aka => Pai::AkaPin5,
+                    Pai::Sou5 if self.has_aka => Pai::AkaSou5,
+                    _ => normal,
+                };
+
+                // e.g. "424242a42" => ankan 2z
+                let naki = format!(
+                    "{}{}{}a{}",
+                    normal.as_u8(),
+                    normal.as_u8(),
+                    normal.as_u8(),
+                    last.as_u8()
+                );
+                self.action_tables[seat]
+                    .discards
+                    .push(ActionItem::Naki(naki));
+            }
+
+            _ => return Err(invalid()),
+        };
+
+        // Chankan robs the kan, including a kokushi ron on ankan.
+        self.last_discarder = actor;
+        self.update_doras(&meld.doras)
+    }
+
+    fn push_hule(&mut self, hule: Hule) -> Result<()> {
+        let mut details = vec![];
+
+        for info in &hule.hules {
+            let who = self.check_seat(info.seat)? as u8;
+            let target = if info.zimo { who } else { self.last_discarder };
+
+            if self.ura_indicators.is_empty() {
+                self.ura_indicators = info
+                    .li_doras
+                    .iter()
+                    .map(|s| pai_from_str(s))
+                    .collect::<Result<_>>()?;
+            }
+
+            let mut score_deltas = [0; 4];
+            if hule.hules.len() > 1 {
+                score_deltas[who as usize] += info.point_rong;
+                score_deltas[target as usize] -= info.point_rong;
+            }
+
+            details.push(kyoku::HoraDetail {
+                who,
+                target,
+                score_deltas,
+                ..Default::default()
+            });
+        }
+
+        // The record only tells the total deltas, so the rest other than the
+        // points of each ron (honba and kyotaku) goes to the first one.
+        let mut rest = to_deltas(&hule.delta_scores);
+        for detail in &details {
+            for (r, d) in rest.iter_mut().zip(&detail.score_deltas) {
+                *r -= d;
+            }
+        }
+        if let Some(first) = details.first_mut() {
+            for (d, r) in first.score_deltas.iter_mut().zip(&rest) {
+                *d += r;
+            }
+        }
+
+        self.end_status = Some(kyoku::EndStatus::Hora { details });
+        Ok(())
+    }
+
+    /// The oya's first tsumo is unknown from the record, which is guessed as
+    /// the last one of the 14 tiles dealt. If it turns out to be a tsumogiri
+    /// of another pai, swap them.
+    fn fix_first_tsumo(&mut self, seat: usize, pai: Pai) {
+        let table = &mut self.action_tables[seat];
+        if seat != self.oya as usize || !table.discards.is_empty() || table.takes.len() != 1 {
+            return;
+        }
+
+        if let ActionItem::Pai(first_tsumo) = table.takes[0] {
+            if first_tsumo == pai {
+                return;
+            }
+
+            if let Some(p) = table.haipai.iter_mut().find(|p| **p == pai) {
+                *p = first_tsumo;
+                table.takes[0] = ActionItem::Pai(pai);
+                sort_haipai(&mut table.haipai);
+            }
+        }
+    }
+
+    /// Doras are given in full list each time, only the new ones are appended.
+    fn update_doras(&mut self, doras: &[String]) -> Result<()> {
+        for s in doras.iter().skip(self.dora_indicators.len()) {
+            self.dora_indicators.push(pai_from_str(s)?);
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn check_seat(&self, seat: u8) -> Result<usize> {
+        if seat < self.num_players {
+            Ok(seat as usize)
+        } else {
+            Err(ParseError::InvalidSeat(seat))
+        }
+    }
+
+    fn build(self) -> tenhou::Kyoku {
+        tenhou::Kyoku {
+            meta: self.meta,
+            scoreboard: self.scoreboard,
+            dora_indicators: self.dora_indicators,
+            ura_indicators: self.ura_indicators,
+            action_tables: self.action_tables,
+            end_status: self.end_status.unwrap_or_default(),
+        }
+    }
+}
+
+#[inline]
+fn empty_action_table() -> ActionTable {
+    ActionTable {
+        haipai: [Pai::Unknown; 13],
+        takes: vec![],
+        discards: vec![],
+    }
+}
+
+#[inline]
+fn from_data<T>(data: json::Value) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    Ok(json::from_value(data)?)
+}
+
+/// Convert a tile in Mahjong Soul notation into `Pai`, e.g. "1m", "0p" (aka
+/// 5p) and "7z" (chun).
+fn pai_from_str(s: &str) -> Result<Pai> {
+    let invalid = || ParseError::InvalidTile(s.to_owned());
+
+    let bytes = s.as_bytes();
+    if bytes.len() != 2 || !bytes[0].is_ascii_digit() {
+        return Err(invalid());
+    }
+    let num = bytes[0] - b'0';
+
+    let id = match (bytes[1], num) {
+        (b'm', 0) => 51,
+        (b'p', 0) => 52,
+        (b's', 0) => 53,
+        (b'm', 1..=9) => 10 + num,
+        (b'p', 1..=9) => 20 + num,
+        (b's', 1..=9) => 30 + num,
+        (b'z', 1..=7) => 40 + num,
+        _ => return Err(invalid()),
+    };
+
+    Pai::try_from(id).map_err(|_| invalid())
+}
+
+/// Same order as tenhou.net/6, where aka comes after the normal 5.
+#[inline]
+fn sort_haipai(pais: &mut [Pai]) {
+    pais.sort_unstable_by_key(|&p| p.sort_key());
+}
+
+#[inline]
+fn to_deltas(v: &[i32]) -> [i32; 4] {
+    let mut deltas = [0; 4];
+    for (d, &delta) in deltas.iter_mut().zip(v) {
+        *d = delta;
+    }
+    deltas
+}
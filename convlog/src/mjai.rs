@@ -1,4 +1,5 @@
 use crate::pai::Pai;
+use crate::tenhou::{Limit, RyukyokuKind};
 
 use std::fmt;
 
@@ -19,6 +20,11 @@ pub enum Event {
         kyoku_first: u8,
         aka_flag: bool,
         names: [String; 4],
+
+        // not a standard mjai field. In sanma, the 4th seat is absent, whose
+        // tehai in StartKyoku will be all "?".
+        #[serde(default, skip_serializing_if = "is_false")]
+        sanma: bool,
     },
     StartKyoku {
         #[serde(deserialize_with = "Pai::deserialize_mjai_str")]
@@ -81,6 +87,11 @@ pub enum Event {
         #[serde(deserialize_with = "Pai::deserialize_mjai_str")]
         dora_marker: Pai,
     },
+    Nukidora {
+        actor: u8,
+        #[serde(deserialize_with = "Pai::deserialize_mjai_str")]
+        pai: Pai,
+    },
 
     Reach {
         actor: u8,
@@ -97,10 +108,33 @@ pub enum Event {
         // record the field.
         #[serde(skip_serializing_if = "Option::is_none")]
         deltas: Option<[i32; 4]>,
+
+        // The following fields are not sent by akochan either, they are only
+        // available when converted from a log.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pao: Option<u8>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fu: Option<u8>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fan: Option<u8>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        limit: Option<Limit>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        yakus: Option<Vec<(String, u8)>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[serde(deserialize_with = "deserialize_optional_pais_from_str")]
+        uradora_markers: Option<Vec<Pai>>,
     },
     Ryukyoku {
         #[serde(skip_serializing_if = "Option::is_none")]
         deltas: Option<[i32; 4]>,
+
+        // Extra fields, not part of the mjai protocol.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<RyukyokuKind>,
+        /// Only present for exhaustive draws.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tenpais: Option<[bool; 4]>,
     },
 
     EndKyoku,
@@ -152,6 +186,11 @@ build_consumed_struct!(Consumed2; 2; 0, 1);
 build_consumed_struct!(Consumed3; 3; 0, 1, 2);
 build_consumed_struct!(Consumed4; 4; 0, 1, 2, 3);
 
+#[inline]
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
 fn deserialize_tehais_from_str<'de, D>(deserializer: D) -> Result<[[Pai; 13]; 4], D::Error>
 where
     D: Deserializer<'de>,
@@ -166,6 +205,15 @@ where
     ])
 }
 
+fn deserialize_optional_pais_from_str<'de, D>(deserializer: D) -> Result<Option<Vec<Pai>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = <Option<Vec<String>>>::deserialize(deserializer)?;
+    s.map(|v| v.iter().map(|p| p.parse().map_err(Error::custom)).collect())
+        .transpose()
+}
+
 impl Event {
     #[inline]
     pub fn actor(&self) -> Option<u8> {
@@ -177,6 +225,7 @@ impl Event {
             | Event::Daiminkan { actor, .. }
             | Event::Kakan { actor, .. }
             | Event::Ankan { actor, .. }
+            | Event::Nukidora { actor, .. }
             | Event::Reach { actor, .. }
             | Event::ReachAccepted { actor, .. }
             | Event::Hora { actor, .. } => Some(actor),
@@ -0,0 +1,756 @@
+//! Provides methods to parse logs in Tenhou's native mjlog XML format.
+
+use crate::reverse::{called_position, naki_string};
+use crate::tenhou::{self, kyoku, ActionItem, ActionTable, GameLength, Limit, RuleSet, Tier, Yaku};
+use crate::Pai;
+
+use std::convert::TryFrom;
+use std::io::prelude::*;
+
+use flate2::read::GzDecoder;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ParseError {
+    #[error("failed to read mjlog: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("mjlog is not valid UTF-8: {0}")]
+    Utf8(#[from] std::str::Utf8Error),
+
+    #[error("failed to parse mjlog XML: {0}")]
+    Xml(#[from] roxmltree::Error),
+
+    #[error("missing attribute {attr:?} in <{tag}>")]
+    MissingAttribute { tag: String, attr: &'static str },
+
+    #[error("invalid attribute {attr:?} in <{tag}>: {value:?}")]
+    InvalidAttribute {
+        tag: String,
+        attr: &'static str,
+        value: String,
+    },
+
+    #[error("invalid tile id: {0}")]
+    InvalidTileId(u16),
+
+    #[error("invalid meld code: {0}")]
+    InvalidMeld(u16),
+
+    #[error("unexpected <{0}> outside of a kyoku")]
+    TagOutsideKyoku(String),
+}
+
+pub type Result<T> = std::result::Result<T, ParseError>;
+
+/// Parse a mjlog from a reader, which can be either plain XML or gzip
+/// compressed XML (as in the `.mjlog` files).
+pub fn parse_reader<R>(mut reader: R) -> Result<tenhou::Log>
+where
+    R: Read,
+{
+    let mut buf = vec![];
+    reader.read_to_end(&mut buf)?;
+    parse_bytes(&buf)
+}
+
+/// Parse a mjlog from bytes, which can be either plain XML or gzip compressed
+/// XML (as in the `.mjlog` files).
+pub fn parse_bytes(bytes: &[u8]) -> Result<tenhou::Log> {
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut xml = String::new();
+        GzDecoder::new(bytes).read_to_string(&mut xml)?;
+        parse_str(&xml)
+    } else {
+        parse_str(std::str::from_utf8(bytes)?)
+    }
+}
+
+/// Parse a mjlog from XML string.
+pub fn parse_str(xml: &str) -> Result<tenhou::Log> {
+    let doc = roxmltree::Document::parse(xml)?;
+
+    let mut names: [String; 4] = Default::default();
+    let mut rule = RuleSet::default();
+    let mut players: [tenhou::PlayerInfo; 4] = Default::default();
+    let mut lobby = tenhou::LobbyInfo::default();
+    let mut kyokus = vec![];
+    let mut kyoku: Option<KyokuBuilder> = None;
+
+    for node in doc.root_element().children().filter(|n| n.is_element()) {
+        let tag = node.tag_name().name();
+
+        match tag {
+            "GO" => {
+                let go_type: u16 = parse_attr(&node, "type")?;
+                rule = rule_from_go_type(go_type);
+                lobby.lobby = node.attribute("lobby").and_then(|l| l.parse().ok());
+            }
+
+            // The first UN tells the names, the rest are reconnections.
+            "UN" if node.has_attribute("dan") => {
+                for (i, name) in names.iter_mut().enumerate() {
+                    if let Some(encoded) = node.attribute(["n0", "n1", "n2", "n3"][i]) {
+                        *name = percent_decode(encoded);
+                    }
+                }
+
+                // the profiles are nice to have, so broken ones are ignored
+                // instead of failing the whole log.
+                let dan: Vec<usize> = parse_list_attr(&node, "dan").unwrap_or_default();
+                let rate: Vec<f64> = parse_list_attr(&node, "rate").unwrap_or_default();
+                let sx: Vec<String> = parse_list_attr(&node, "sx").unwrap_or_default();
+                for (i, player) in players.iter_mut().enumerate() {
+                    if names[i].is_empty() {
+                        continue;
+                    }
+                    player.dan = dan
+                        .get(i)
+                        .and_then(|&d| DAN_NAMES.get(d))
+                        .map(|&d| d.to_owned());
+                    player.rate = rate.get(i).copied();
+                    player.sex = sx.get(i).and_then(|s| tenhou::Sex::from_tenhou_str(s));
+                }
+            }
+
+            "INIT" => {
+                if let Some(builder) = kyoku.take() {
+                    kyokus.push(builder.build());
+                }
+
+                let seed: Vec<u16> = parse_list_attr(&node, "seed")?;
+                let mut ten: Vec<i32> = parse_list_attr(&node, "ten")?;
+                if seed.len() != 6 {
+                    return Err(invalid_attr(&node, "seed"));
+                }
+                // sanma logs may omit the score of the absent seat.
+                if rule.is_sanma && ten.len() == 3 {
+                    ten.push(0);
+                }
+                if ten.len() != 4 {
+                    return Err(invalid_attr(&node, "ten"));
+                }
+
+                let mut haipais = [[Pai::Unknown; 13]; 4];
+                for (i, haipai) in haipais.iter_mut().enumerate() {
+                    let attr = ["hai0", "hai1", "hai2", "hai3"][i];
+                    let mut ids: Vec<u16> = parse_list_attr(&node, attr)?;
+                    // the absent seat in sanma has no haipai.
+                    if rule.is_sanma && i == 3 && ids.is_empty() {
+                        continue;
+                    }
+                    if ids.len() != 13 {
+                        return Err(invalid_attr(&node, attr));
+                    }
+
+                    // same order as tenhou.net/6, where aka comes after the
+                    // normal 5.
+                    ids.sort_unstable_by_key(|&id| (id / 4, rule.has_aka() && is_aka_id(id), id));
+                    for (pai, &id) in haipai.iter_mut().zip(&ids) {
+                        *pai = pai_from_id(id, rule.has_aka())?;
+                    }
+                }
+
+                kyoku = Some(KyokuBuilder {
+                    num_players: rule.num_players(),
+                    meta: kyoku::Meta {
+                        kyoku_num: seed[0] as u8,
+                        honba: seed[1] as u8,
+                        kyotaku: seed[2] as u8,
+                    },
+                    scoreboard: [ten[0] * 100, ten[1] * 100, ten[2] * 100, ten[3] * 100],
+                    dora_indicators: vec![pai_from_id(seed[5], rule.has_aka())?],
+                    ura_indicators: vec![],
+                    action_tables: [
+                        empty_action_table(haipais[0]),
+                        empty_action_table(haipais[1]),
+                        empty_action_table(haipais[2]),
+                        empty_action_table(haipais[3]),
+                    ],
+                    end_status: None,
+                    last_tsumo: [None; 4],
+                    reach_declared: [false; 4],
+                });
+            }
+
+            "SHUFFLE" | "TAIKYOKU" | "UN" | "BYE" => (),
+
+            _ => {
+                let builder = kyoku
+                    .as_mut()
+                    .ok_or_else(|| ParseError::TagOutsideKyoku(tag.to_owned()))?;
+                builder.push(&node, rule.has_aka())?;
+            }
+        };
+    }
+
+    if let Some(builder) = kyoku.take() {
+        kyokus.push(builder.build());
+    }
+
+    Ok(tenhou::Log {
+        names,
+        rule,
+        players,
+        lobby,
+        kyokus,
+    })
+}
+
+struct KyokuBuilder {
+    num_players: u8,
+    meta: kyoku::Meta,
+    scoreboard: [i32; 4],
+    dora_indicators: Vec<Pai>,
+    ura_indicators: Vec<Pai>,
+    action_tables: [ActionTable; 4],
+    end_status: Option<kyoku::EndStatus>,
+
+    // tile id of the last tsumo, used to tell tsumogiri.
+    last_tsumo: [Option<u16>; 4],
+    reach_declared: [bool; 4],
+}
+
+impl KyokuBuilder {
+    fn push(&mut self, node: &roxmltree::Node, has_aka: bool) -> Result<()> {
+        let tag = node.tag_name().name();
+
+        // tsumo and dahai, e.g. <T36/> <D36/>
+        if let Some((who, id)) = parse_draw_or_discard(tag, "TUVW") {
+            let pai = pai_from_id(id, has_aka)?;
+            self.action_tables[who].takes.push(ActionItem::Pai(pai));
+            self.last_tsumo[who] = Some(id);
+            return Ok(());
+        }
+        if let Some((who, id)) = parse_draw_or_discard(tag, "DEFG") {
+            let pai = pai_from_id(id, has_aka)?;
+            let tsumogiri = self.last_tsumo[who].take() == Some(id);
+
+            let item = if self.reach_declared[who] {
+                // e.g. "r35" => discard 5s to reach, "r60" for tsumogiri
+                self.reach_declared[who] = false;
+                let id = if tsumogiri { 60 } else { pai.as_u8() };
+                ActionItem::Naki(format!("r{}", id))
+            } else if tsumogiri {
+                ActionItem::Tsumogiri(60)
+            } else {
+                ActionItem::Pai(pai)
+            };
+            self.action_tables[who].discards.push(item);
+            return Ok(());
+        }
+
+        match tag {
+            "N" => {
+                let who: usize = parse_attr(node, "who")?;
+                let code: u16 = parse_attr(node, "m")?;
+                if who > 3 {
+                    return Err(invalid_attr(node, "who"));
+                }
+
+                self.last_tsumo[who] = None;
+                self.push_meld(who as u8, code, has_aka)?;
+            }
+
+            "REACH" => {
+                let who: usize = parse_attr(node, "who")?;
+                let step: u8 = parse_attr(node, "step")?;
+                if who > 3 {
+                    return Err(invalid_attr(node, "who"));
+                }
+
+                if step == 1 {
+                    self.reach_declared[who] = true;
+                }
+            }
+
+            "DORA" => {
+                let id: u16 = parse_attr(node, "hai")?;
+                self.dora_indicators.push(pai_from_id(id, has_aka)?);
+            }
+
+            "AGARI" => {
+                let who: u8 = parse_attr(node, "who")?;
+                let target: u8 = parse_attr(node, "fromWho")?;
+                let score_deltas = parse_score_deltas(node)?;
+
+                if node.has_attribute("doraHaiUra") {
+                    let ura_ids: Vec<u16> = parse_list_attr(node, "doraHaiUra")?;
+                    self.ura_indicators = ura_ids
+                        .into_iter()
+                        .map(|id| pai_from_id(id, has_aka))
+                        .collect::<Result<_>>()?;
+                }
+
+                let detail = self.parse_hora_detail(node, who, target, score_deltas)?;
+                match &mut self.end_status {
+                    Some(kyoku::EndStatus::Hora { details }) => details.push(detail),
+                    _ => {
+                        self.end_status = Some(kyoku::EndStatus::Hora {
+                            details: vec![detail],
+                        })
+                    }
+                };
+            }
+
+            "RYUUKYOKU" => {
+                let score_deltas = parse_score_deltas(node)?;
+                let kind = match node.attribute("type") {
+                    Some("yao9") => kyoku::RyukyokuKind::KyuushuKyuuhai,
+                    Some("reach4") => kyoku::RyukyokuKind::SuuchaRiichi,
+                    Some("ron3") => kyoku::RyukyokuKind::SanchaHoo,
+                    Some("kan4") => kyoku::RyukyokuKind::SuukanSanra,
+                    Some("kaze4") => kyoku::RyukyokuKind::SuufonRenda,
+                    Some("nm") => kyoku::RyukyokuKind::NagashiMangan,
+                    _ => kyoku::RyukyokuKind::Exhaustive,
+                };
+
+                // Tenpai players reveal their hands in `hai{i}`.
+                let mut tenpai = [false; 4];
+                if kind == kyoku::RyukyokuKind::Exhaustive {
+                    for (i, t) in tenpai.iter_mut().enumerate() {
+                        *t = node.has_attribute(["hai0", "hai1", "hai2", "hai3"][i]);
+                    }
+                }
+
+                self.end_status = Some(kyoku::EndStatus::Ryukyoku {
+                    kind,
+                    score_deltas,
+                    tenpai,
+                });
+            }
+
+            _ => (),
+        };
+
+        Ok(())
+    }
+
+    /// Parse the fu, han, limit and yaku of an AGARI, e.g.
+    /// `ten="30,2000,0" yaku="18,1,54,1"`.
+    fn parse_hora_detail(
+        &self,
+        node: &roxmltree::Node,
+        who: u8,
+        target: u8,
+        score_deltas: [i32; 4],
+    ) -> Result<kyoku::HoraDetail> {
+        let ten: Vec<u32> = parse_list_attr(node, "ten")?;
+        if ten.len() != 3 {
+            return Err(invalid_attr(node, "ten"));
+        }
+        let limit = match ten[2] {
+            0 => None,
+            1 => Some(Limit::Mangan),
+            2 => Some(Limit::Haneman),
+            3 => Some(Limit::Baiman),
+            4 => Some(Limit::Sanbaiman),
+            5 => Some(Limit::Yakuman),
+            _ => return Err(invalid_attr(node, "ten")),
+        };
+
+        let yaku_name = |id: u32| {
+            YAKU_NAMES
+                .get(id as usize)
+                .map(|&name| name.to_owned())
+                .ok_or_else(|| invalid_attr(node, "yaku"))
+        };
+        let mut yaku = vec![];
+        if node.has_attribute("yaku") {
+            let pairs: Vec<u32> = parse_list_attr(node, "yaku")?;
+            for pair in pairs.chunks(2) {
+                // tenhou.net/6 omits those with 0 han, like 裏ドラ(0飜).
+                match *pair {
+                    [_, 0] => (),
+                    [id, han] => yaku.push(Yaku {
+                        name: yaku_name(id)?,
+                        han: han as u8,
+                    }),
+                    _ => return Err(invalid_attr(node, "yaku")),
+                }
+            }
+        }
+        if node.has_attribute("yakuman") {
+            let ids: Vec<u32> = parse_list_attr(node, "yakuman")?;
+            for id in ids {
+                yaku.push(Yaku {
+                    name: yaku_name(id)?,
+                    han: 13,
+                });
+            }
+        }
+
+        let pao = if node.has_attribute("paoWho") {
+            Some(parse_attr(node, "paoWho")?)
+        } else {
+            None
+        };
+
+        // The split of tsumo is not recorded, so it is derived from the total
+        // points.
+        let points = ten[1];
+        let points = if who != target {
+            format!("{}点", points)
+        } else if who == self.meta.kyoku_num % 4 {
+            format!("{}点∀", points / (self.num_players as u32 - 1))
+        } else {
+            let ko = (points / (self.num_players as u32)).div_ceil(100) * 100;
+            let oya = points - ko * (self.num_players as u32 - 2);
+            format!("{}-{}点", ko, oya)
+        };
+
+        Ok(kyoku::HoraDetail {
+            who,
+            target,
+            score_deltas,
+            pao,
+            fu: if limit.is_none() { ten[0] as u8 } else { 0 },
+            han: yaku.iter().map(|y| y.han).sum(),
+            limit,
+            yaku,
+            points,
+        })
+    }
+
+    /// Decode the meld bitfield and push the corresponding naki string.
+    fn push_meld(&mut self, actor: u8, code: u16, has_aka: bool) -> Result<()> {
+        let target = (actor + (code & 3) as u8) % self.num_players;
+        let pai = |id: u16| pai_from_id(id, has_aka);
+
+        if code & 0x0004 != 0 {
+            // chi
+            let t = code >> 10;
+            let called = (t % 3) as usize;
+            let base = t / 3;
+            let base = base / 7 * 9 + base % 7;
+            let ids = [
+                base * 4 + ((code >> 3) & 3),
+                (base + 1) * 4 + ((code >> 5) & 3),
+                (base + 2) * 4 + ((code >> 7) & 3),
+            ];
+
+            let consumed: Vec<_> = (0..3).filter(|&i| i != called).map(|i| ids[i]).collect();
+            // e.g. "c275226" => chi 7p with 06p from kamicha
+            let naki = format!(
+                "c{}{}{}",
+                pai(ids[called])?.as_u8(),
+                pai(consumed[0])?.as_u8(),
+                pai(consumed[1])?.as_u8(),
+            );
+            self.action_tables[actor as usize]
+                .takes
+                .push(ActionItem::Naki(naki));
+        } else if code & 0x0018 != 0 {
+            // pon or kakan
+            let unused = (code >> 5) & 3;
+            let t = code >> 9;
+            let called = (t % 3) as usize;
+            let base = t / 3;
+            if base >= 34 {
+                return Err(ParseError::InvalidMeld(code));
+            }
+
+            let ids: Vec<_> = (0..4)
+                .filter(|&i| i != unused)
+                .map(|i| base * 4 + i)
+                .collect();
+            let own: Vec<_> = (0..3)
+                .filter(|&i| i != called)
+                .map(|i| pai(ids[i]))
+                .collect::<Result<_>>()?;
+            let called_pai = pai(ids[called])?;
+
+            if code & 0x0008 != 0 {
+                let naki = naki_string(actor, target, self.num_players, 'p', called_pai, &own);
+                self.action_tables[actor as usize]
+                    .takes
+                    .push(ActionItem::Naki(naki));
+            } else {
+                // For kakan, the called pai is placed at where it was taken,
+                // followed by the added one.
+                let mut pon = own;
+                let idx = called_position(actor, target, self.num_players);
+                pon.insert(idx, called_pai);
+
+                let added = pai(base * 4 + unused)?;
+                let naki = naki_string(actor, target, self.num_players, 'k', added, &pon);
+                self.action_tables[actor as usize]
+                    .discards
+                    .push(ActionItem::Naki(naki));
+            }
+        } else if code & 0x0020 != 0 {
+            // nukidora, which only exists in sanma
+            // e.g. "f44" => nukidora 4z
+            let pai = pai(code >> 8)?;
+            if pai != Pai::North {
+                return Err(ParseError::InvalidMeld(code));
+            }
+            self.action_tables[actor as usize]
+                .discards
+                .push(ActionItem::Naki(format!("f{}", pai.as_u8())));
+        } else {
+            // daiminkan or ankan
+            let called_id = code >> 8;
+            let base = called_id / 4 * 4;
+            let others: Vec<_> = (base..base + 4)
+                .filter(|&id| id != called_id)
+                .map(pai)
+                .collect::<Result<_>>()?;
+            let called_pai = pai(called_id)?;
+
+            if code & 3 == 0 {
+                // e.g. "424242a42" => ankan 2z
+                let naki = format!(
+                    "{}{}{}a{}",
+                    others[0].as_u8(),
+                    others[1].as_u8(),
+                    others[2].as_u8(),
+                    called_pai.as_u8(),
+                );
+                self.action_tables[actor as usize]
+                    .discards
+                    .push(ActionItem::Naki(naki));
+            } else {
+                let naki = naki_string(actor, target, self.num_players, 'm', called_pai, &others);
+                let table = &mut self.action_tables[actor as usize];
+                table.takes.push(ActionItem::Naki(naki));
+
+                // daiminkan leaves a placeholder "0" in the discard table.
+                table.discards.push(ActionItem::Pai(Pai::Unknown));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn build(self) -> tenhou::Kyoku {
+        tenhou::Kyoku {
+            meta: self.meta,
+            scoreboard: self.scoreboard,
+            dora_indicators: self.dora_indicators,
+            ura_indicators: self.ura_indicators,
+            action_tables: self.action_tables,
+            end_status: self.end_status.unwrap_or_default(),
+        }
+    }
+}
+
+/// Names of yaku in tenhou.net/6, indexed by the yaku id in mjlog.
+const YAKU_NAMES: &[&str] = &[
+    "門前清自摸和",
+    "立直",
+    "一発",
+    "槍槓",
+    "嶺上開花",
+    "海底摸月",
+    "河底撈魚",
+    "平和",
+    "断幺九",
+    "一盃口",
+    "自風 東",
+    "自風 南",
+    "自風 西",
+    "自風 北",
+    "場風 東",
+    "場風 南",
+    "場風 西",
+    "場風 北",
+    "役牌 白",
+    "役牌 發",
+    "役牌 中",
+    "両立直",
+    "七対子",
+    "混全帯幺九",
+    "一気通貫",
+    "三色同順",
+    "三色同刻",
+    "三槓子",
+    "対々和",
+    "三暗刻",
+    "小三元",
+    "混老頭",
+    "二盃口",
+    "純全帯幺九",
+    "混一色",
+    "清一色",
+    "人和",
+    "天和",
+    "地和",
+    "大三元",
+    "四暗刻",
+    "四暗刻単騎",
+    "字一色",
+    "緑一色",
+    "清老頭",
+    "九蓮宝燈",
+    "純正九蓮宝燈",
+    "国士無双",
+    "国士無双１３面",
+    "大四喜",
+    "小四喜",
+    "四槓子",
+    "ドラ",
+    "裏ドラ",
+    "赤ドラ",
+];
+
+#[inline]
+fn empty_action_table(haipai: [Pai; 13]) -> ActionTable {
+    ActionTable {
+        haipai,
+        takes: vec![],
+        discards: vec![],
+    }
+}
+
+#[inline]
+fn is_aka_id(id: u16) -> bool {
+    matches!(id, 16 | 52 | 88)
+}
+
+/// Convert a tile id in mjlog (0~135) into `Pai`.
+///
+/// When aka is enabled, 16, 52 and 88 are the aka 5m, 5p and 5s.
+fn pai_from_id(id: u16, has_aka: bool) -> Result<Pai> {
+    if has_aka && is_aka_id(id) {
+        return Ok(match id {
+            16 => Pai::AkaMan5,
+            52 => Pai::AkaPin5,
+            _ => Pai::AkaSou5,
+        });
+    }
+
+    let kind = id / 4;
+    let num = match kind {
+        0..=8 => 11 + kind,
+        9..=17 => 21 + kind - 9,
+        18..=26 => 31 + kind - 18,
+        27..=33 => 41 + kind - 27,
+        _ => return Err(ParseError::InvalidTileId(id)),
+    };
+
+    Pai::try_from(num as u8).map_err(|_| ParseError::InvalidTileId(id))
+}
+
+/// Parse tags like "T36" and "D36". `prefixes` are the tag names for seat 0 to
+/// seat 3.
+fn parse_draw_or_discard(tag: &str, prefixes: &str) -> Option<(usize, u16)> {
+    let mut chars = tag.chars();
+    let who = prefixes.find(chars.next()?)?;
+    let rest = chars.as_str();
+
+    if rest.is_empty() || !rest.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    rest.parse().ok().map(|id| (who, id))
+}
+
+/// Parse the "sc" attribute, which is in the form of
+/// "before0,delta0,before1,delta1,..." in 100 points. Sanma logs may omit the
+/// absent seat.
+fn parse_score_deltas(node: &roxmltree::Node) -> Result<[i32; 4]> {
+    let sc: Vec<i32> = parse_list_attr(node, "sc")?;
+    if sc.len() != 8 && sc.len() != 6 {
+        return Err(invalid_attr(node, "sc"));
+    }
+
+    let delta = |i: usize| sc.get(i * 2 + 1).map_or(0, |d| d * 100);
+    Ok([delta(0), delta(1), delta(2), delta(3)])
+}
+
+/// Decodes the `type` attribute of `<GO>`.
+fn rule_from_go_type(go_type: u16) -> RuleSet {
+    // both 0x20 and 0x80 for houou.
+    let tier = match go_type & 0xa0 {
+        0x00 => Tier::Ippan,
+        0x80 => Tier::Joukyuu,
+        0x20 => Tier::Tokujou,
+        _ => Tier::Houou,
+    };
+
+    RuleSet {
+        tier: Some(tier),
+        is_sanma: go_type & 0x10 != 0,
+        game_length: if go_type & 0x08 != 0 {
+            GameLength::Hanchan
+        } else {
+            GameLength::Tonpuu
+        },
+        has_kuitan: go_type & 0x04 == 0,
+        aka_counts: if go_type & 0x02 == 0 { [1; 3] } else { [0; 3] },
+        is_fast: go_type & 0x40 != 0,
+    }
+}
+
+/// Indexed by the `dan` attribute of `<UN>`.
+const DAN_NAMES: &[&str] = &[
+    "新人", "９級", "８級", "７級", "６級", "５級", "４級", "３級", "２級", "１級", "初段", "二段",
+    "三段", "四段", "五段", "六段", "七段", "八段", "九段", "十段", "天鳳",
+];
+
+fn parse_attr<T>(node: &roxmltree::Node, attr: &'static str) -> Result<T>
+where
+    T: std::str::FromStr,
+{
+    node.attribute(attr)
+        .ok_or_else(|| ParseError::MissingAttribute {
+            tag: node.tag_name().name().to_owned(),
+            attr,
+        })?
+        .parse()
+        .map_err(|_| invalid_attr(node, attr))
+}
+
+fn parse_list_attr<T>(node: &roxmltree::Node, attr: &'static str) -> Result<Vec<T>>
+where
+    T: std::str::FromStr,
+{
+    let value = node
+        .attribute(attr)
+        .ok_or_else(|| ParseError::MissingAttribute {
+            tag: node.tag_name().name().to_owned(),
+            attr,
+        })?;
+
+    if value.is_empty() {
+        return Ok(vec![]);
+    }
+
+    value
+        .split(',')
+        .map(|v| v.parse().map_err(|_| invalid_attr(node, attr)))
+        .collect()
+}
+
+#[inline]
+fn invalid_attr(node: &roxmltree::Node, attr: &'static str) -> ParseError {
+    ParseError::InvalidAttribute {
+        tag: node.tag_name().name().to_owned(),
+        attr,
+        value: node.attribute(attr).unwrap_or_default().to_owned(),
+    }
+}
+
+/// Names in mjlog are percent-encoded UTF-8.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+            if let Ok(b) = u8::from_str_radix(hex, 16) {
+                decoded.push(b);
+                i += 3;
+                continue;
+            }
+        }
+
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
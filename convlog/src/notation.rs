@@ -0,0 +1,326 @@
+//! Compact tile notation for hands and melds, e.g. `123m406p789s1122z`.
+//!
+//! Numbers are followed by their suit, `m`, `p`, `s` or `z`, where `0` is the
+//! aka five and honors are `1z` to `7z` in the order of E, S, W, N, P, F, C.
+//! An unknown pai is written as `?`.
+
+use crate::board::Fuuro;
+use crate::mjai::{Consumed2, Consumed3, Consumed4};
+use crate::Pai;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+const SUITS: &[char] = &['m', 'p', 's', 'z'];
+
+/// A list of pais that parses from and formats to the compact notation.
+///
+/// Consecutive pais of the same suit share the suit letter, while the order
+/// of the pais is always preserved, so that `12m1p3m` stays as is.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pais(pub Vec<Pai>);
+
+#[derive(Debug, Error)]
+pub enum ParseError {
+    #[error("invalid character {found:?} at position {pos}")]
+    InvalidChar { pos: usize, found: char },
+
+    #[error("missing suit for the numbers at position {pos}")]
+    MissingSuit { pos: usize },
+
+    #[error("invalid honor {number}z at position {pos} (expected within [1, 7])")]
+    InvalidHonor { pos: usize, number: u8 },
+
+    #[error("invalid meld {0:?}")]
+    InvalidMeld(String),
+}
+
+impl Deref for Pais {
+    type Target = [Pai];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<Vec<Pai>> for Pais {
+    #[inline]
+    fn from(pais: Vec<Pai>) -> Self {
+        Self(pais)
+    }
+}
+
+impl From<&[Pai]> for Pais {
+    #[inline]
+    fn from(pais: &[Pai]) -> Self {
+        Self(pais.to_vec())
+    }
+}
+
+impl From<Pais> for Vec<Pai> {
+    #[inline]
+    fn from(pais: Pais) -> Self {
+        pais.0
+    }
+}
+
+impl FromStr for Pais {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pais = vec![];
+        // (pos, number) of the numbers waiting for their suit.
+        let mut pending = vec![];
+
+        for (pos, c) in s.char_indices() {
+            match c {
+                '0'..='9' => pending.push((pos, c as u8 - b'0')),
+                '?' if pending.is_empty() => pais.push(Pai::Unknown),
+                _ => {
+                    let suit = SUITS
+                        .iter()
+                        .position(|&s| s == c)
+                        .ok_or(ParseError::InvalidChar { pos, found: c })?;
+                    for (pos, number) in pending.drain(..) {
+                        pais.push(pai_from_parts(suit, number, pos)?);
+                    }
+                }
+            }
+        }
+
+        if let Some(&(pos, _)) = pending.first() {
+            return Err(ParseError::MissingSuit { pos });
+        }
+
+        Ok(Self(pais))
+    }
+}
+
+impl fmt::Display for Pais {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", display(&self.0))
+    }
+}
+
+/// Formats a slice of pais in the compact notation without copying it.
+#[inline]
+pub fn display(pais: &[Pai]) -> impl fmt::Display + '_ {
+    Display(pais)
+}
+
+struct Display<'a>(&'a [Pai]);
+
+impl fmt::Display for Display<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut pending_suit = None;
+
+        for &pai in self.0 {
+            let parts = pai_to_parts(pai);
+            let suit = parts.map(|(suit, _)| suit);
+            if let Some(pending) = pending_suit {
+                if suit != Some(pending) {
+                    write!(f, "{}", SUITS[pending])?;
+                }
+            }
+
+            match parts {
+                Some((suit, number)) => {
+                    write!(f, "{}", number)?;
+                    pending_suit = Some(suit);
+                }
+                None => {
+                    write!(f, "?")?;
+                    pending_suit = None;
+                }
+            }
+        }
+
+        if let Some(pending) = pending_suit {
+            write!(f, "{}", SUITS[pending])?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the index into `SUITS` and the number of a pai, where aka fives
+/// have 0 as the number.
+#[inline]
+fn pai_to_parts(pai: Pai) -> Option<(usize, u8)> {
+    match pai.as_u8() {
+        0 => None,
+        id @ 51..=53 => Some((id as usize - 51, 0)),
+        id => Some((id as usize / 10 - 1, id % 10)),
+    }
+}
+
+#[inline]
+fn pai_from_parts(suit: usize, number: u8, pos: usize) -> Result<Pai, ParseError> {
+    let id = match (suit, number) {
+        (3, 0) | (3, 8..=9) => return Err(ParseError::InvalidHonor { pos, number }),
+        (_, 0) => 51 + suit as u8,
+        _ => (suit as u8 + 1) * 10 + number,
+    };
+    Ok(Pai::try_from(id).unwrap_or_default())
+}
+
+/// Formats a meld, with the called pai in brackets followed by the consumed
+/// pais, the seat it is called from after `@`, and for kakan, the added pai
+/// after `+`.
+///
+/// * `[3m]45m@3`: chi of 3m from seat 3.
+/// * `[5p]05p@1`: pon of 5p from seat 1.
+/// * `[1z]111z@0`: daiminkan of E from seat 0.
+/// * `[5s]55s@2+0s`: kakan of aka 5s onto a pon of 5s from seat 2.
+/// * `1111z`: ankan of E.
+impl fmt::Display for Fuuro {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Fuuro::Chi {
+                target,
+                pai,
+                consumed,
+            }
+            | Fuuro::Pon {
+                target,
+                pai,
+                consumed,
+            } => write!(
+                f,
+                "[{}]{}@{}",
+                display(&[pai]),
+                display(&consumed.0),
+                target,
+            ),
+            Fuuro::Daiminkan {
+                target,
+                pai,
+                consumed,
+            } => write!(
+                f,
+                "[{}]{}@{}",
+                display(&[pai]),
+                display(&consumed.0),
+                target,
+            ),
+            Fuuro::Kakan {
+                pai,
+                previous_pon_target,
+                previous_pon_pai,
+                consumed,
+            } => write!(
+                f,
+                "[{}]{}@{}+{}",
+                display(&[previous_pon_pai]),
+                display(&consumed.0),
+                previous_pon_target,
+                display(&[pai]),
+            ),
+            Fuuro::Ankan { consumed } => write!(f, "{}", display(&consumed.0)),
+        }
+    }
+}
+
+impl FromStr for Fuuro {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseError::InvalidMeld(s.to_owned());
+
+        let rest = match s.strip_prefix('[') {
+            Some(rest) => rest,
+            None => {
+                let consumed =
+                    <[Pai; 4]>::try_from(&*s.parse::<Pais>()?.0).map_err(|_| invalid())?;
+                return Ok(Fuuro::Ankan {
+                    consumed: Consumed4(consumed),
+                });
+            }
+        };
+
+        let (called, rest) = rest.split_once(']').ok_or_else(invalid)?;
+        let (consumed, rest) = rest.split_once('@').ok_or_else(invalid)?;
+        let (target, added) = match rest.split_once('+') {
+            Some((target, added)) => (target, Some(added)),
+            None => (rest, None),
+        };
+
+        let pai = match *called.parse::<Pais>()?.0 {
+            [pai] => pai,
+            _ => return Err(invalid()),
+        };
+        let consumed = consumed.parse::<Pais>()?.0;
+        let target = target.parse().map_err(|_| invalid())?;
+        if target > 3 {
+            return Err(invalid());
+        }
+
+        let fuuro = match (consumed.as_slice(), added) {
+            (&[a, b], None) => {
+                let consumed = Consumed2([a, b]);
+                if is_same_kind(pai, a) && is_same_kind(pai, b) {
+                    Fuuro::Pon {
+                        target,
+                        pai,
+                        consumed,
+                    }
+                } else if is_shuntsu(pai, a, b) {
+                    Fuuro::Chi {
+                        target,
+                        pai,
+                        consumed,
+                    }
+                } else {
+                    return Err(invalid());
+                }
+            }
+            (&[a, b], Some(added)) => {
+                let added = match *added.parse::<Pais>()?.0 {
+                    [added] if [pai, a, b].iter().all(|&p| is_same_kind(added, p)) => added,
+                    _ => return Err(invalid()),
+                };
+                Fuuro::Kakan {
+                    pai: added,
+                    previous_pon_target: target,
+                    previous_pon_pai: pai,
+                    consumed: Consumed2([a, b]),
+                }
+            }
+            (&[a, b, c], None) if [a, b, c].iter().all(|&p| is_same_kind(pai, p)) => {
+                Fuuro::Daiminkan {
+                    target,
+                    pai,
+                    consumed: Consumed3([a, b, c]),
+                }
+            }
+            _ => return Err(invalid()),
+        };
+
+        Ok(fuuro)
+    }
+}
+
+#[inline]
+fn is_same_kind(a: Pai, b: Pai) -> bool {
+    a != Pai::Unknown && a.deaka() == b.deaka()
+}
+
+#[inline]
+fn is_shuntsu(a: Pai, b: Pai, c: Pai) -> bool {
+    let suit = a.suit();
+    if suit.is_none() || a.is_honor() || b.suit() != suit || c.suit() != suit {
+        return false;
+    }
+
+    let mut ns = [a.number(), b.number(), c.number()];
+    ns.sort_unstable();
+    match ns {
+        [Some(x), Some(y), Some(z)] => y == x + 1 && z == y + 1,
+        _ => false,
+    }
+}
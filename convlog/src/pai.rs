@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt;
+use std::iter::FromIterator;
+use std::ops::{Index, IndexMut};
 use std::str::FromStr;
 
 use num_enum::TryFromPrimitive;
@@ -64,6 +66,77 @@ pub enum Pai {
 
 impl Eq for Pai {}
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Suit {
+    Man,
+    Pin,
+    Sou,
+    /// Winds and dragons.
+    Ji,
+}
+
+/// All the kinds of pai without aka, in the order of their 34-index.
+const PAIS_34: [Pai; 34] = [
+    Pai::Man1,
+    Pai::Man2,
+    Pai::Man3,
+    Pai::Man4,
+    Pai::Man5,
+    Pai::Man6,
+    Pai::Man7,
+    Pai::Man8,
+    Pai::Man9,
+    Pai::Pin1,
+    Pai::Pin2,
+    Pai::Pin3,
+    Pai::Pin4,
+    Pai::Pin5,
+    Pai::Pin6,
+    Pai::Pin7,
+    Pai::Pin8,
+    Pai::Pin9,
+    Pai::Sou1,
+    Pai::Sou2,
+    Pai::Sou3,
+    Pai::Sou4,
+    Pai::Sou5,
+    Pai::Sou6,
+    Pai::Sou7,
+    Pai::Sou8,
+    Pai::Sou9,
+    Pai::East,
+    Pai::South,
+    Pai::West,
+    Pai::North,
+    Pai::Haku,
+    Pai::Hatsu,
+    Pai::Chun,
+];
+
+/// Kinds of pai by their 34-index of [`Pai::to_id34`], which the shanten and
+/// score calculations work on.
+pub(crate) mod id34 {
+    #[inline]
+    pub fn is_honor(id: usize) -> bool {
+        id >= 27
+    }
+
+    #[inline]
+    pub fn is_terminal(id: usize) -> bool {
+        id < 27 && matches!(id % 9, 0 | 8)
+    }
+
+    #[inline]
+    pub fn is_yaochuu(id: usize) -> bool {
+        is_honor(id) || is_terminal(id)
+    }
+
+    #[inline]
+    pub fn is_dragon(id: usize) -> bool {
+        (31..34).contains(&id)
+    }
+}
+
 const MJAI_PAI_STRINGS: &[&str] = &[
     "?", "?", "?", "?", "?", "?", "?", "?", "?", "?", // 0~9
     "?", "1m", "2m", "3m", "4m", "5m", "6m", "7m", "8m", "9m", // 10~19
@@ -143,6 +216,112 @@ impl Pai {
         self as usize
     }
 
+    /// Returns `None` for `Unknown`.
+    #[inline]
+    pub fn suit(self) -> Option<Suit> {
+        match self.as_u8() {
+            11..=19 | 51 => Some(Suit::Man),
+            21..=29 | 52 => Some(Suit::Pin),
+            31..=39 | 53 => Some(Suit::Sou),
+            41..=47 => Some(Suit::Ji),
+            _ => None,
+        }
+    }
+
+    /// Returns 1~9 for number pais, where aka pai is 5, and 1~7 for winds and
+    /// dragons in the order of E, S, W, N, P, F, C. Returns `None` for
+    /// `Unknown`.
+    #[inline]
+    pub fn number(self) -> Option<u8> {
+        match self.as_u8() {
+            0 => None,
+            51..=53 => Some(5),
+            id => Some(id % 10),
+        }
+    }
+
+    #[inline]
+    pub fn is_honor(self) -> bool {
+        self.to_id34().is_some_and(id34::is_honor)
+    }
+
+    /// Whether it is 1 or 9 of a suit.
+    #[inline]
+    pub fn is_terminal(self) -> bool {
+        self.to_id34().is_some_and(id34::is_terminal)
+    }
+
+    /// Whether it is a terminal or an honor.
+    #[inline]
+    pub fn is_yaochuu(self) -> bool {
+        self.to_id34().is_some_and(id34::is_yaochuu)
+    }
+
+    #[inline]
+    pub fn is_aka(self) -> bool {
+        matches!(self, Pai::AkaMan5 | Pai::AkaPin5 | Pai::AkaSou5)
+    }
+
+    /// Turns an aka pai into the normal one. Other pais are returned as is.
+    #[inline]
+    pub fn deaka(self) -> Self {
+        match self {
+            Pai::AkaMan5 => Pai::Man5,
+            Pai::AkaPin5 => Pai::Pin5,
+            Pai::AkaSou5 => Pai::Sou5,
+            _ => self,
+        }
+    }
+
+    /// A key to sort pais in the order of tenhou.net/6, which is 1m~9m, 1p~9p,
+    /// 1s~9s, then E~C, where aka pai comes right after the normal one and
+    /// `Unknown` comes last. Different pais never share a key.
+    #[inline]
+    pub fn sort_key(self) -> u8 {
+        match self {
+            Pai::Unknown => u8::MAX,
+            _ => self.deaka().as_u8() * 2 + self.is_aka() as u8,
+        }
+    }
+
+    /// Returns the dora indicated by `self` as a dora indicator, which is the
+    /// next one of the same suit, wrapping 9 to 1, N to E and C to P.
+    ///
+    /// In sanma, 1m indicates 9m as there is no 2m, which is not handled here.
+    #[inline]
+    pub fn indicated_dora(self) -> Self {
+        let id = self.deaka().as_u8();
+        let next = match id {
+            0 => 0,
+            19 | 29 | 39 => id - 8,
+            44 => 41,
+            47 => 45,
+            _ => id + 1,
+        };
+        Self::try_from(next).unwrap_or_default()
+    }
+
+    /// Maps to 0~33 in the order of 1m~9m, 1p~9p, 1s~9s, E, S, W, N, P, F,
+    /// C, with aka pai treated as the normal one. Returns `None` for
+    /// `Unknown`.
+    #[inline]
+    pub fn to_id34(self) -> Option<usize> {
+        let id = self.deaka().as_usize();
+        match id {
+            11..=19 => Some(id - 11),
+            21..=29 => Some(id - 21 + 9),
+            31..=39 => Some(id - 31 + 18),
+            41..=47 => Some(id - 41 + 27),
+            _ => None,
+        }
+    }
+
+    /// The inverse of [`Pai::to_id34`], which never returns aka pai.
+    #[inline]
+    pub fn from_id34(id: usize) -> Option<Self> {
+        PAIS_34.get(id).copied()
+    }
+
     #[allow(clippy::trivially_copy_pass_by_ref)]
     #[inline]
     pub fn serialize_literal<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -176,3 +355,113 @@ impl Pai {
         Ok(pai)
     }
 }
+
+/// Counts of each kind of pai, indexed by [`Pai::to_id34`], with aka pai
+/// counted as the normal one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TileCounts([u8; 34]);
+
+impl Default for TileCounts {
+    #[inline]
+    fn default() -> Self {
+        Self([0; 34])
+    }
+}
+
+impl TileCounts {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn get(&self, pai: Pai) -> u8 {
+        pai.to_id34().map(|id| self.0[id]).unwrap_or(0)
+    }
+
+    /// Returns `false` without counting if `pai` is `Unknown`.
+    #[inline]
+    pub fn add(&mut self, pai: Pai) -> bool {
+        match pai.to_id34() {
+            Some(id) => {
+                self.0[id] += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns `false` without changing anything if there is no `pai`.
+    #[inline]
+    pub fn remove(&mut self, pai: Pai) -> bool {
+        match pai.to_id34() {
+            Some(id) if self.0[id] > 0 => {
+                self.0[id] -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Total number of pais counted.
+    #[inline]
+    pub fn total(&self) -> u32 {
+        self.0.iter().map(|&c| c as u32).sum()
+    }
+
+    /// Iterates over the kinds with a non-zero count.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (Pai, u8)> + '_ {
+        self.0
+            .iter()
+            .zip(PAIS_34.iter())
+            .filter(|&(&count, _)| count > 0)
+            .map(|(&count, &pai)| (pai, count))
+    }
+
+    #[inline]
+    pub fn as_array(&self) -> &[u8; 34] {
+        &self.0
+    }
+}
+
+impl Index<usize> for TileCounts {
+    type Output = u8;
+
+    #[inline]
+    fn index(&self, id: usize) -> &Self::Output {
+        &self.0[id]
+    }
+}
+
+impl IndexMut<usize> for TileCounts {
+    #[inline]
+    fn index_mut(&mut self, id: usize) -> &mut Self::Output {
+        &mut self.0[id]
+    }
+}
+
+impl Extend<Pai> for TileCounts {
+    #[inline]
+    fn extend<T: IntoIterator<Item = Pai>>(&mut self, iter: T) {
+        for pai in iter {
+            self.add(pai);
+        }
+    }
+}
+
+impl FromIterator<Pai> for TileCounts {
+    #[inline]
+    fn from_iter<T: IntoIterator<Item = Pai>>(iter: T) -> Self {
+        let mut counts = Self::new();
+        counts.extend(iter);
+        counts
+    }
+}
+
+impl From<&[Pai]> for TileCounts {
+    #[inline]
+    fn from(pais: &[Pai]) -> Self {
+        pais.iter().copied().collect()
+    }
+}
@@ -0,0 +1,467 @@
+use crate::mjai;
+use crate::tenhou;
+use crate::{Board, Pai};
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ReverseConvertError {
+    #[error("missing start_game event")]
+    MissingStartGame,
+
+    #[error("unexpected {event} event outside of a kyoku: at index {index}")]
+    EventOutsideKyoku { index: usize, event: &'static str },
+
+    #[error("kakan without a previous pon: at index {0}")]
+    PonNotFound(usize),
+
+    #[error("unfinished kyoku: missing end_kyoku")]
+    UnfinishedKyoku,
+}
+
+pub type Result<T> = std::result::Result<T, ReverseConvertError>;
+
+/// Transform a mjai format log into tenhou.net/6 format.
+///
+/// Information that mjai events do not carry, such as the points of a hora,
+/// will be left empty.
+pub fn mjai_to_tenhou(events: &[mjai::Event]) -> Result<tenhou::RawLog> {
+    let mut log: Option<tenhou::Log> = None;
+    let mut kyoku: Option<KyokuBuilder> = None;
+
+    for (index, event) in events.iter().enumerate() {
+        match event {
+            mjai::Event::StartGame {
+                kyoku_first,
+                aka_flag,
+                names,
+                sanma,
+            } => {
+                let game_length = if *kyoku_first == tenhou::GameLength::Tonpuu as u8 {
+                    tenhou::GameLength::Tonpuu
+                } else {
+                    tenhou::GameLength::Hanchan
+                };
+
+                log = Some(tenhou::Log {
+                    names: names.clone(),
+                    rule: tenhou::RuleSet {
+                        is_sanma: *sanma,
+                        game_length,
+                        aka_counts: if *aka_flag { [1; 3] } else { [0; 3] },
+                        ..Default::default()
+                    },
+                    players: Default::default(),
+                    lobby: Default::default(),
+                    kyokus: vec![],
+                });
+            }
+
+            mjai::Event::StartKyoku {
+                bakaze,
+                dora_marker,
+                kyoku: kyoku_num,
+                honba,
+                kyotaku,
+                scores,
+                tehais,
+                ..
+            } => {
+                if kyoku.is_some() {
+                    return Err(ReverseConvertError::UnfinishedKyoku);
+                }
+
+                let num_players = log
+                    .as_ref()
+                    .ok_or(ReverseConvertError::MissingStartGame)?
+                    .num_players();
+                let mut builder = KyokuBuilder::new(
+                    num_players,
+                    tenhou::kyoku::Meta {
+                        kyoku_num: (bakaze.as_u8() - Pai::East.as_u8()) * 4 + kyoku_num - 1,
+                        honba: *honba,
+                        kyotaku: *kyotaku,
+                    },
+                    *scores,
+                    *dora_marker,
+                    tehais,
+                );
+                builder.track(event);
+                kyoku = Some(builder);
+            }
+
+            mjai::Event::EndKyoku => {
+                let builder = kyoku.take().ok_or(ReverseConvertError::EventOutsideKyoku {
+                    index,
+                    event: "end_kyoku",
+                })?;
+
+                log.as_mut()
+                    .ok_or(ReverseConvertError::MissingStartGame)?
+                    .kyokus
+                    .push(builder.build());
+            }
+
+            mjai::Event::None | mjai::Event::EndGame => (),
+
+            _ => {
+                let builder = kyoku
+                    .as_mut()
+                    .ok_or(ReverseConvertError::EventOutsideKyoku {
+                        index,
+                        event: event_name(event),
+                    })?;
+
+                builder.push(index, event)?;
+            }
+        };
+    }
+
+    if kyoku.is_some() {
+        return Err(ReverseConvertError::UnfinishedKyoku);
+    }
+
+    log.map(tenhou::RawLog::from)
+        .ok_or(ReverseConvertError::MissingStartGame)
+}
+
+struct KyokuBuilder {
+    num_players: u8,
+    meta: tenhou::kyoku::Meta,
+    scoreboard: [i32; 4],
+    dora_indicators: Vec<Pai>,
+    ura_indicators: Vec<Pai>,
+    action_tables: [tenhou::ActionTable; 4],
+    end_status: Option<tenhou::kyoku::EndStatus>,
+
+    // (target, pai) of every pon, so that kakan can find out where the
+    // original pon came from.
+    pons: [Vec<(u8, Pai)>; 4],
+    reach_declared: [bool; 4],
+    // the position so far, to tell who is tenpai when the log does not. It
+    // is `None` once the events do not make sense to the board.
+    board: Option<Board>,
+}
+
+impl KyokuBuilder {
+    fn new(
+        num_players: u8,
+        meta: tenhou::kyoku::Meta,
+        scoreboard: [i32; 4],
+        dora_marker: Pai,
+        tehais: &[[Pai; 13]; 4],
+    ) -> Self {
+        let action_table = |i: usize| tenhou::ActionTable {
+            haipai: tehais[i],
+            takes: vec![],
+            discards: vec![],
+        };
+
+        let mut board = Board::new();
+        board.num_players = num_players;
+
+        Self {
+            num_players,
+            meta,
+            scoreboard,
+            dora_indicators: vec![dora_marker],
+            ura_indicators: vec![],
+            action_tables: [
+                action_table(0),
+                action_table(1),
+                action_table(2),
+                action_table(3),
+            ],
+            end_status: None,
+            pons: Default::default(),
+            reach_declared: [false; 4],
+            board: Some(board),
+        }
+    }
+
+    fn track(&mut self, event: &mjai::Event) {
+        self.board = self
+            .board
+            .take()
+            .and_then(|mut board| board.update(event).ok().map(|()| board));
+    }
+
+    fn push(&mut self, index: usize, event: &mjai::Event) -> Result<()> {
+        self.track(event);
+
+        match *event {
+            mjai::Event::Tsumo { actor, pai } => {
+                self.takes(actor).push(tenhou::ActionItem::Pai(pai));
+            }
+
+            mjai::Event::Dahai {
+                actor,
+                pai,
+                tsumogiri,
+            } => {
+                let item = if self.reach_declared[actor as usize] {
+                    // e.g. "r35" => discard 5s to reach, "r60" for tsumogiri
+                    self.reach_declared[actor as usize] = false;
+                    let id = if tsumogiri { 60 } else { pai.as_u8() };
+                    tenhou::ActionItem::Naki(format!("r{}", id))
+                } else if tsumogiri {
+                    tenhou::ActionItem::Tsumogiri(60)
+                } else {
+                    tenhou::ActionItem::Pai(pai)
+                };
+
+                self.discards(actor).push(item);
+            }
+
+            mjai::Event::Chi {
+                actor,
+                pai,
+                consumed,
+                ..
+            } => {
+                // e.g. "c275226" => chi 7p with 06p from kamicha
+                let naki = format!(
+                    "c{}{}{}",
+                    pai.as_u8(),
+                    consumed.0[0].as_u8(),
+                    consumed.0[1].as_u8()
+                );
+                self.takes(actor).push(tenhou::ActionItem::Naki(naki));
+            }
+
+            mjai::Event::Pon {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => {
+                let naki = naki_string(actor, target, self.num_players, 'p', pai, &consumed.0);
+                self.takes(actor).push(tenhou::ActionItem::Naki(naki));
+                self.pons[actor as usize].push((target, pai));
+            }
+
+            mjai::Event::Daiminkan {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => {
+                let naki = naki_string(actor, target, self.num_players, 'm', pai, &consumed.0);
+                self.takes(actor).push(tenhou::ActionItem::Naki(naki));
+
+                // daiminkan leaves a placeholder "0" in the discard table.
+                self.discards(actor)
+                    .push(tenhou::ActionItem::Pai(Pai::Unknown));
+            }
+
+            mjai::Event::Kakan {
+                actor,
+                pai,
+                consumed,
+            } => {
+                let target = self.pons[actor as usize]
+                    .iter()
+                    .find(|&&(_, pon_pai)| pon_pai.deaka() == pai.deaka())
+                    .map(|&(target, _)| target)
+                    .ok_or(ReverseConvertError::PonNotFound(index))?;
+
+                let naki = naki_string(actor, target, self.num_players, 'k', pai, &consumed.0);
+                self.discards(actor).push(tenhou::ActionItem::Naki(naki));
+            }
+
+            mjai::Event::Ankan { actor, consumed } => {
+                // e.g. "424242a42" => ankan 2z
+                let naki = format!(
+                    "{}{}{}a{}",
+                    consumed.0[0].as_u8(),
+                    consumed.0[1].as_u8(),
+                    consumed.0[2].as_u8(),
+                    consumed.0[3].as_u8(),
+                );
+                self.discards(actor).push(tenhou::ActionItem::Naki(naki));
+            }
+
+            mjai::Event::Nukidora { actor, pai } => {
+                // e.g. "f44" => nukidora 4z
+                let naki = format!("f{}", pai.as_u8());
+                self.discards(actor).push(tenhou::ActionItem::Naki(naki));
+            }
+
+            mjai::Event::Dora { dora_marker } => {
+                self.dora_indicators.push(dora_marker);
+            }
+
+            mjai::Event::Reach { actor } => {
+                self.reach_declared[actor as usize] = true;
+            }
+
+            mjai::Event::Hora {
+                actor,
+                target,
+                deltas,
+                pao,
+                fu,
+                fan,
+                limit,
+                ref yakus,
+                ref uradora_markers,
+            } => {
+                let detail = tenhou::kyoku::HoraDetail {
+                    who: actor,
+                    target,
+                    score_deltas: deltas.unwrap_or_default(),
+                    pao,
+                    fu: fu.unwrap_or(0),
+                    han: fan.unwrap_or(0),
+                    limit,
+                    yaku: yakus
+                        .iter()
+                        .flatten()
+                        .map(|(name, han)| tenhou::Yaku {
+                            name: name.clone(),
+                            han: *han,
+                        })
+                        .collect(),
+                    points: String::new(),
+                };
+                if let Some(ura) = uradora_markers {
+                    self.ura_indicators = ura.clone();
+                }
+
+                match &mut self.end_status {
+                    Some(tenhou::kyoku::EndStatus::Hora { details }) => details.push(detail),
+                    _ => {
+                        self.end_status = Some(tenhou::kyoku::EndStatus::Hora {
+                            details: vec![detail],
+                        })
+                    }
+                };
+            }
+
+            mjai::Event::Ryukyoku {
+                deltas,
+                reason,
+                tenpais,
+            } => {
+                let score_deltas = deltas.unwrap_or_default();
+                let kind = reason.unwrap_or_default();
+                // Plain mjai logs do not tell who is tenpai, guess it from
+                // the noten bappu, where the tenpai ones receive. If nobody
+                // pays, either all or none are tenpai, so look at the tehais.
+                let tenpai = tenpais.unwrap_or_else(|| {
+                    let mut tenpai = [false; 4];
+                    if kind != tenhou::RyukyokuKind::Exhaustive {
+                        return tenpai;
+                    }
+                    if score_deltas.iter().any(|&d| d != 0) {
+                        for (t, &d) in tenpai.iter_mut().zip(&score_deltas) {
+                            *t = d > 0;
+                        }
+                    } else if let Some(board) = &self.board {
+                        for (t, player) in tenpai.iter_mut().zip(board.active_players()) {
+                            *t = !player.waits().is_empty();
+                        }
+                    }
+                    tenpai
+                });
+
+                self.end_status = Some(tenhou::kyoku::EndStatus::Ryukyoku {
+                    kind,
+                    score_deltas,
+                    tenpai,
+                });
+            }
+
+            _ => (),
+        };
+
+        Ok(())
+    }
+
+    fn build(self) -> tenhou::Kyoku {
+        tenhou::Kyoku {
+            meta: self.meta,
+            scoreboard: self.scoreboard,
+            dora_indicators: self.dora_indicators,
+            ura_indicators: self.ura_indicators,
+            action_tables: self.action_tables,
+            end_status: self.end_status.unwrap_or_default(),
+        }
+    }
+
+    #[inline]
+    fn takes(&mut self, actor: u8) -> &mut Vec<tenhou::ActionItem> {
+        &mut self.action_tables[actor as usize].takes
+    }
+
+    #[inline]
+    fn discards(&mut self, actor: u8) -> &mut Vec<tenhou::ActionItem> {
+        &mut self.action_tables[actor as usize].discards
+    }
+}
+
+/// Build the naki string for pon, daiminkan and kakan, where the position of
+/// the marker indicates which seat the pai was taken from.
+///
+/// e.g. "p252525", "12p1212", "3737p37" for pon from kamicha, toimen and
+/// shimocha respectively.
+pub(crate) fn naki_string(
+    actor: u8,
+    target: u8,
+    num_players: u8,
+    marker: char,
+    pai: Pai,
+    consumed: &[Pai],
+) -> String {
+    let called = format!("{}{}", marker, pai.as_u8());
+    let mut parts: Vec<_> = consumed.iter().map(|p| p.as_u8().to_string()).collect();
+
+    let idx = match called_position(actor, target, num_players) {
+        // from kamicha
+        0 => 0,
+        // from toimen
+        1 => 1,
+        // from shimocha, the called pai is put right before the last one for
+        // kakan and at the end for others.
+        _ => {
+            if marker == 'k' {
+                parts.len() - 1
+            } else {
+                parts.len()
+            }
+        }
+    };
+    parts.insert(idx, called);
+
+    parts.concat()
+}
+
+/// Return where the called pai is placed in a pon, that is 0 for kamicha, 1
+/// for toimen and 2 for shimocha. There is no toimen in sanma.
+pub(crate) fn called_position(actor: u8, target: u8, num_players: u8) -> usize {
+    match (target + num_players - actor) % num_players {
+        rel if rel == num_players - 1 => 0,
+        2 if num_players == 4 => 1,
+        _ => 2,
+    }
+}
+
+#[inline]
+fn event_name(event: &mjai::Event) -> &'static str {
+    match event {
+        mjai::Event::Tsumo { .. } => "tsumo",
+        mjai::Event::Dahai { .. } => "dahai",
+        mjai::Event::Chi { .. } => "chi",
+        mjai::Event::Pon { .. } => "pon",
+        mjai::Event::Daiminkan { .. } => "daiminkan",
+        mjai::Event::Kakan { .. } => "kakan",
+        mjai::Event::Ankan { .. } => "ankan",
+        mjai::Event::Dora { .. } => "dora",
+        mjai::Event::Nukidora { .. } => "nukidora",
+        mjai::Event::Reach { .. } => "reach",
+        mjai::Event::ReachAccepted { .. } => "reach_accepted",
+        mjai::Event::Hora { .. } => "hora",
+        mjai::Event::Ryukyoku { .. } => "ryukyoku",
+        _ => "other",
+    }
+}
@@ -0,0 +1,764 @@
+//! Computes the yaku, han, fu and payment of a hora, following the rules of
+//! tenhou.net, where kuitan and multiple yakuman are allowed, double wind
+//! pairs are worth 4 fu, and there is no kiriage mangan.
+
+use crate::board::Fuuro;
+use crate::pai::id34::{is_dragon, is_honor, is_terminal, is_yaochuu};
+use crate::tenhou::{Limit, Yaku};
+use crate::{Pai, TileCounts};
+
+/// Everything about a hora needed to value it.
+#[derive(Debug, Clone, Default)]
+pub struct HoraContext {
+    pub num_players: u8,
+    pub actor: u8,
+    /// Same as `actor` for tsumo.
+    pub target: u8,
+    /// The one who is responsible for the yakuman (包), if any.
+    pub pao: Option<u8>,
+    pub oya: u8,
+    pub bakaze: Pai,
+
+    /// The closed part of the tehai without the winning pai.
+    pub tehai: Vec<Pai>,
+    pub fuuros: Vec<Fuuro>,
+    pub winning_pai: Pai,
+
+    pub dora_markers: Vec<Pai>,
+    /// Only counted with reach.
+    pub uradora_markers: Vec<Pai>,
+    pub nukidora: u8,
+
+    pub is_reach: bool,
+    pub is_double_reach: bool,
+    pub is_ippatsu: bool,
+    pub is_rinshan: bool,
+    pub is_chankan: bool,
+    /// Whether there is no pai left in the live wall, i.e. haitei for tsumo
+    /// and houtei for ron.
+    pub is_last_pai: bool,
+    /// Whether it is the first draw of the actor without any call before,
+    /// i.e. tenhou or chiihou for tsumo.
+    pub is_first_tsumo: bool,
+
+    pub honba: u8,
+    pub kyotaku: u8,
+}
+
+/// The value of a hora.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandValue {
+    /// Including dora, where a yakuman counts as 13 han.
+    pub yaku: Vec<Yaku>,
+    pub han: u8,
+    /// 0 for yakuman.
+    pub fu: u8,
+    pub limit: Option<Limit>,
+    /// Including honba and kyotaku.
+    pub deltas: [i32; 4],
+}
+
+impl HoraContext {
+    #[inline]
+    pub fn is_tsumo(&self) -> bool {
+        self.actor == self.target
+    }
+
+    #[inline]
+    pub fn is_oya(&self) -> bool {
+        self.actor == self.oya
+    }
+
+    #[inline]
+    pub fn jikaze(&self) -> Pai {
+        let seat = (self.actor + self.num_players - self.oya) % self.num_players;
+        Pai::from_id34(27 + seat as usize).unwrap_or_default()
+    }
+
+    #[inline]
+    fn is_menzen(&self) -> bool {
+        self.fuuros.iter().all(|f| matches!(f, Fuuro::Ankan { .. }))
+    }
+}
+
+/// Returns `None` if the pais do not form a complete hand or there is no
+/// yaku.
+pub fn calculate(ctx: &HoraContext) -> Option<HandValue> {
+    let mut counts = TileCounts::from(ctx.tehai.as_slice());
+    if !counts.add(ctx.winning_pai) {
+        return None;
+    }
+    let win = ctx.winning_pai.to_id34()?;
+
+    let mut best: Option<HandValue> = None;
+    let mut consider = |value: HandValue| {
+        let is_better = best.as_ref().is_none_or(|b| {
+            (value.deltas[ctx.actor as usize], value.han, value.fu)
+                > (b.deltas[ctx.actor as usize], b.han, b.fu)
+        });
+        if is_better {
+            best = Some(value);
+        }
+    };
+
+    if ctx.fuuros.is_empty() {
+        if let Some(value) = kokushi(ctx, &counts) {
+            consider(value);
+        }
+        if counts.iter().count() == 7 && counts.iter().all(|(_, c)| c == 2) {
+            if let Some(value) = chiitoitsu(ctx, &counts) {
+                consider(value);
+            }
+        }
+    }
+
+    let fuuro_mentsu: Vec<_> = ctx.fuuros.iter().filter_map(Mentsu::from_fuuro).collect();
+    for (pair, closed) in decompose(&counts) {
+        let all: Vec<_> = closed.iter().chain(&fuuro_mentsu).copied().collect();
+        if all.len() != 4 {
+            continue;
+        }
+
+        // every way the winning pai can complete the hand.
+        if pair == win {
+            if let Some(value) = standard(ctx, &all, pair, Wait::Tanki, None) {
+                consider(value);
+            }
+        }
+        for (i, m) in closed.iter().enumerate() {
+            if let Some(wait) = m.wait_for(win) {
+                if let Some(value) = standard(ctx, &all, pair, wait, Some(i)) {
+                    consider(value);
+                }
+            }
+        }
+    }
+
+    best
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MentsuKind {
+    Shuntsu,
+    Koutsu,
+    Kantsu,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Mentsu {
+    kind: MentsuKind,
+    /// The lowest pai in 34-index.
+    first: usize,
+    is_open: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Wait {
+    Ryanmen,
+    Kanchan,
+    Penchan,
+    Shanpon,
+    Tanki,
+}
+
+impl Mentsu {
+    fn from_fuuro(fuuro: &Fuuro) -> Option<Self> {
+        let first = fuuro.pais().iter().filter_map(|p| p.to_id34()).min()?;
+        let (kind, is_open) = match fuuro {
+            Fuuro::Chi { .. } => (MentsuKind::Shuntsu, true),
+            Fuuro::Pon { .. } => (MentsuKind::Koutsu, true),
+            Fuuro::Daiminkan { .. } | Fuuro::Kakan { .. } => (MentsuKind::Kantsu, true),
+            Fuuro::Ankan { .. } => (MentsuKind::Kantsu, false),
+        };
+        Some(Mentsu {
+            kind,
+            first,
+            is_open,
+        })
+    }
+
+    #[inline]
+    fn ids(&self) -> [usize; 3] {
+        match self.kind {
+            MentsuKind::Shuntsu => [self.first, self.first + 1, self.first + 2],
+            _ => [self.first; 3],
+        }
+    }
+
+    #[inline]
+    fn is_koutsu_like(&self) -> bool {
+        self.kind != MentsuKind::Shuntsu
+    }
+
+    #[inline]
+    fn has_yaochuu(&self) -> bool {
+        self.ids().iter().any(|&id| is_yaochuu(id))
+    }
+
+    /// The wait if the mentsu is completed by the pai `win`.
+    fn wait_for(&self, win: usize) -> Option<Wait> {
+        if !self.ids().contains(&win) {
+            return None;
+        }
+        // the number of the first pai in its suit, from 0.
+        let start = self.first % 9;
+        let wait = match self.kind {
+            MentsuKind::Shuntsu if win == self.first + 1 => Wait::Kanchan,
+            MentsuKind::Shuntsu if win == self.first && start == 6 => Wait::Penchan,
+            MentsuKind::Shuntsu if win == self.first + 2 && start == 0 => Wait::Penchan,
+            MentsuKind::Shuntsu => Wait::Ryanmen,
+            _ => Wait::Shanpon,
+        };
+        Some(wait)
+    }
+}
+
+/// Enumerates every way to split 3n+2 pais into a pair and closed koutsu or
+/// shuntsu.
+fn decompose(counts: &TileCounts) -> Vec<(usize, Vec<Mentsu>)> {
+    let mut counts = *counts.as_array();
+    let mut found = vec![];
+
+    for pair in 0..34 {
+        if counts[pair] < 2 {
+            continue;
+        }
+        counts[pair] -= 2;
+        let mut mentsu = vec![];
+        decompose_mentsu(&mut counts, 0, &mut mentsu, &mut |m| {
+            found.push((pair, m.to_vec()))
+        });
+        counts[pair] += 2;
+    }
+
+    found
+}
+
+fn decompose_mentsu(
+    counts: &mut [u8; 34],
+    start: usize,
+    mentsu: &mut Vec<Mentsu>,
+    found: &mut dyn FnMut(&[Mentsu]),
+) {
+    let id = match (start..34).find(|&id| counts[id] > 0) {
+        Some(id) => id,
+        None => return found(mentsu),
+    };
+
+    if counts[id] >= 3 {
+        counts[id] -= 3;
+        mentsu.push(Mentsu {
+            kind: MentsuKind::Koutsu,
+            first: id,
+            is_open: false,
+        });
+        decompose_mentsu(counts, id, mentsu, found);
+        mentsu.pop();
+        counts[id] += 3;
+    }
+    if id < 27 && id % 9 <= 6 && counts[id + 1] > 0 && counts[id + 2] > 0 {
+        counts[id] -= 1;
+        counts[id + 1] -= 1;
+        counts[id + 2] -= 1;
+        mentsu.push(Mentsu {
+            kind: MentsuKind::Shuntsu,
+            first: id,
+            is_open: false,
+        });
+        decompose_mentsu(counts, id, mentsu, found);
+        mentsu.pop();
+        counts[id] += 1;
+        counts[id + 1] += 1;
+        counts[id + 2] += 1;
+    }
+}
+
+/// Collects yaku and counts han.
+#[derive(Default)]
+struct YakuList {
+    yaku: Vec<Yaku>,
+    num_yakuman: u8,
+}
+
+impl YakuList {
+    #[inline]
+    fn add(&mut self, name: &str, han: u8) {
+        self.yaku.push(Yaku {
+            name: name.to_owned(),
+            han,
+        });
+    }
+
+    #[inline]
+    fn add_yakuman(&mut self, name: &str) {
+        self.add(name, 13);
+        self.num_yakuman += 1;
+    }
+
+    #[inline]
+    fn han(&self) -> u8 {
+        self.yaku.iter().map(|y| y.han).sum()
+    }
+
+    /// Yaku that do not depend on the shape of the hand.
+    fn add_situational(&mut self, ctx: &HoraContext) {
+        if ctx.is_menzen() && ctx.is_tsumo() {
+            self.add("門前清自摸和", 1);
+        }
+        if ctx.is_double_reach {
+            self.add("両立直", 2);
+        } else if ctx.is_reach {
+            self.add("立直", 1);
+        }
+        if ctx.is_ippatsu {
+            self.add("一発", 1);
+        }
+        if ctx.is_chankan {
+            self.add("槍槓", 1);
+        }
+        if ctx.is_rinshan {
+            self.add("嶺上開花", 1);
+        }
+        if ctx.is_last_pai && !ctx.is_rinshan {
+            if ctx.is_tsumo() {
+                self.add("海底摸月", 1);
+            } else {
+                self.add("河底撈魚", 1);
+            }
+        }
+    }
+
+    fn add_situational_yakuman(&mut self, ctx: &HoraContext) {
+        if ctx.is_first_tsumo && ctx.is_tsumo() {
+            if ctx.is_oya() {
+                self.add_yakuman("天和");
+            } else {
+                self.add_yakuman("地和");
+            }
+        }
+    }
+
+    /// Yaku that only look at the pais, shared by the standard form and
+    /// chiitoitsu.
+    fn add_by_pais(&mut self, ids: &[usize], is_menzen: bool) {
+        if ids.iter().all(|&id| !is_yaochuu(id)) {
+            self.add("断幺九", 1);
+        }
+        if ids.iter().all(|&id| is_yaochuu(id)) && ids.iter().any(|&id| !is_honor(id)) {
+            self.add("混老頭", 2);
+        }
+
+        let mut suits = ids.iter().filter(|&&id| !is_honor(id)).map(|&id| id / 9);
+        if let Some(suit) = suits.next() {
+            if suits.all(|s| s == suit) {
+                if ids.iter().any(|&id| is_honor(id)) {
+                    self.add("混一色", if is_menzen { 3 } else { 2 });
+                } else {
+                    self.add("清一色", if is_menzen { 6 } else { 5 });
+                }
+            }
+        }
+    }
+
+    fn add_yakuman_by_pais(&mut self, ids: &[usize]) {
+        if ids.iter().all(|&id| is_honor(id)) {
+            self.add_yakuman("字一色");
+        }
+        if ids.iter().all(|&id| is_terminal(id)) {
+            self.add_yakuman("清老頭");
+        }
+        // 2s, 3s, 4s, 6s, 8s and F.
+        if ids.iter().all(|id| [19, 20, 21, 23, 25, 32].contains(id)) {
+            self.add_yakuman("緑一色");
+        }
+    }
+}
+
+fn kokushi(ctx: &HoraContext, counts: &TileCounts) -> Option<HandValue> {
+    let yaochuu: Vec<_> = (0..34).filter(|&id| is_yaochuu(id)).collect();
+    if yaochuu.iter().map(|&id| counts[id]).sum::<u8>() != 14
+        || yaochuu.iter().any(|&id| counts[id] == 0)
+    {
+        return None;
+    }
+
+    let mut list = YakuList::default();
+    list.add_situational_yakuman(ctx);
+    let win = ctx.winning_pai.to_id34()?;
+    if counts[win] == 2 {
+        list.add_yakuman("国士無双１３面");
+    } else {
+        list.add_yakuman("国士無双");
+    }
+
+    Some(finish(ctx, list, 0))
+}
+
+fn chiitoitsu(ctx: &HoraContext, counts: &TileCounts) -> Option<HandValue> {
+    let ids: Vec<_> = counts
+        .iter()
+        .flat_map(|(pai, c)| std::iter::repeat_n(pai.to_id34().unwrap_or(0), c as usize))
+        .collect();
+
+    let mut list = YakuList::default();
+    list.add_situational_yakuman(ctx);
+    list.add_yakuman_by_pais(&ids);
+    if list.num_yakuman > 0 {
+        return Some(finish(ctx, list, 0));
+    }
+
+    list.add_situational(ctx);
+    list.add("七対子", 2);
+    list.add_by_pais(&ids, true);
+    add_dora(ctx, &mut list);
+
+    Some(finish(ctx, list, 25))
+}
+
+/// Values the standard form of four mentsu and a pair, where `won` is the
+/// index of the closed mentsu completed by the winning pai, or `None` for
+/// tanki.
+fn standard(
+    ctx: &HoraContext,
+    mentsu: &[Mentsu],
+    pair: usize,
+    wait: Wait,
+    won: Option<usize>,
+) -> Option<HandValue> {
+    let is_menzen = ctx.is_menzen();
+    let is_tsumo = ctx.is_tsumo();
+    let jikaze = ctx.jikaze().to_id34()?;
+    let bakaze = ctx.bakaze.to_id34()?;
+
+    // a koutsu completed by ron is not concealed.
+    let is_concealed =
+        |i: usize, m: &Mentsu| !m.is_open && (won != Some(i) || is_tsumo || wait != Wait::Shanpon);
+    let num_ankou = mentsu
+        .iter()
+        .enumerate()
+        .filter(|&(i, m)| m.is_koutsu_like() && is_concealed(i, m))
+        .count();
+    let num_kantsu = mentsu
+        .iter()
+        .filter(|m| m.kind == MentsuKind::Kantsu)
+        .count();
+    let koutsu_ids: Vec<_> = mentsu
+        .iter()
+        .filter(|m| m.is_koutsu_like())
+        .map(|m| m.first)
+        .collect();
+    let shuntsu_ids: Vec<_> = mentsu
+        .iter()
+        .filter(|m| m.kind == MentsuKind::Shuntsu)
+        .map(|m| m.first)
+        .collect();
+    let mut ids: Vec<_> = mentsu.iter().flat_map(|m| m.ids().to_vec()).collect();
+    ids.extend_from_slice(&[pair, pair]);
+
+    let mut list = YakuList::default();
+
+    list.add_situational_yakuman(ctx);
+    list.add_yakuman_by_pais(&ids);
+    if num_ankou == 4 {
+        if wait == Wait::Tanki {
+            list.add_yakuman("四暗刻単騎");
+        } else {
+            list.add_yakuman("四暗刻");
+        }
+    }
+    let num_dragon_koutsu = koutsu_ids.iter().filter(|&&id| is_dragon(id)).count();
+    if num_dragon_koutsu == 3 {
+        list.add_yakuman("大三元");
+    }
+    let num_wind_koutsu = koutsu_ids
+        .iter()
+        .filter(|&&id| is_honor(id) && !is_dragon(id))
+        .count();
+    if num_wind_koutsu == 4 {
+        list.add_yakuman("大四喜");
+    } else if num_wind_koutsu == 3 && is_honor(pair) && !is_dragon(pair) {
+        list.add_yakuman("小四喜");
+    }
+    if num_kantsu == 4 {
+        list.add_yakuman("四槓子");
+    }
+    if is_menzen {
+        let mut counts = [0u8; 9];
+        let suit = pair / 9;
+        if ids.iter().all(|&id| !is_honor(id) && id / 9 == suit) {
+            ids.iter().for_each(|&id| counts[id % 9] += 1);
+            let base = [3, 1, 1, 1, 1, 1, 1, 1, 3];
+            if counts.iter().zip(&base).all(|(c, b)| c >= b) {
+                let win = ctx.winning_pai.to_id34()?;
+                // the extra pai is the winning one, i.e. 9-sided wait.
+                if counts[win % 9] == base[win % 9] + 1 {
+                    list.add_yakuman("純正九蓮宝燈");
+                } else {
+                    list.add_yakuman("九蓮宝燈");
+                }
+            }
+        }
+    }
+    if list.num_yakuman > 0 {
+        return Some(finish(ctx, list, 0));
+    }
+
+    list.add_situational(ctx);
+
+    let is_yakuhai = |id: usize| is_dragon(id) || id == jikaze || id == bakaze;
+    let is_pinfu =
+        is_menzen && shuntsu_ids.len() == 4 && !is_yakuhai(pair) && wait == Wait::Ryanmen;
+    if is_pinfu {
+        list.add("平和", 1);
+    }
+
+    if is_menzen {
+        let mut sorted = shuntsu_ids.clone();
+        sorted.sort_unstable();
+        let mut peikou = 0;
+        let mut i = 0;
+        while i + 1 < sorted.len() {
+            if sorted[i] == sorted[i + 1] {
+                peikou += 1;
+                i += 2;
+            } else {
+                i += 1;
+            }
+        }
+        match peikou {
+            1 => list.add("一盃口", 1),
+            2 => list.add("二盃口", 3),
+            _ => (),
+        }
+    }
+
+    const WIND_NAMES: [&str; 4] = ["東", "南", "西", "北"];
+    const DRAGON_NAMES: [&str; 3] = ["白", "發", "中"];
+    for &id in &koutsu_ids {
+        if id == jikaze {
+            list.add(&format!("自風 {}", WIND_NAMES[id - 27]), 1);
+        }
+        if id == bakaze {
+            list.add(&format!("場風 {}", WIND_NAMES[id - 27]), 1);
+        }
+        if is_dragon(id) {
+            list.add(&format!("役牌 {}", DRAGON_NAMES[id - 31]), 1);
+        }
+    }
+
+    let all_blocks_yaochuu = mentsu.iter().all(|m| m.has_yaochuu()) && is_yaochuu(pair);
+    if all_blocks_yaochuu && !shuntsu_ids.is_empty() {
+        if ids.iter().any(|&id| is_honor(id)) {
+            list.add("混全帯幺九", if is_menzen { 2 } else { 1 });
+        } else {
+            list.add("純全帯幺九", if is_menzen { 3 } else { 2 });
+        }
+    }
+
+    for suit in 0..3 {
+        if [0, 3, 6]
+            .iter()
+            .all(|offset| shuntsu_ids.contains(&(suit * 9 + offset)))
+        {
+            list.add("一気通貫", if is_menzen { 2 } else { 1 });
+        }
+    }
+    for n in 0..9 {
+        if (0..3).all(|suit| shuntsu_ids.contains(&(suit * 9 + n))) {
+            list.add("三色同順", if is_menzen { 2 } else { 1 });
+        }
+        if (0..3).all(|suit| koutsu_ids.contains(&(suit * 9 + n))) {
+            list.add("三色同刻", 2);
+        }
+    }
+
+    if num_kantsu == 3 {
+        list.add("三槓子", 2);
+    }
+    if koutsu_ids.len() == 4 {
+        list.add("対々和", 2);
+    }
+    if num_ankou == 3 {
+        list.add("三暗刻", 2);
+    }
+    if num_dragon_koutsu == 2 && is_dragon(pair) {
+        list.add("小三元", 2);
+    }
+
+    list.add_by_pais(&ids, is_menzen);
+    if list.han() == 0 {
+        return None;
+    }
+    add_dora(ctx, &mut list);
+
+    // fu
+    let fu = if is_pinfu {
+        if is_tsumo {
+            20
+        } else {
+            30
+        }
+    } else {
+        let mut fu: u8 = 20;
+        if is_menzen && !is_tsumo {
+            fu += 10;
+        }
+        if is_tsumo {
+            fu += 2;
+        }
+        for (i, m) in mentsu.iter().enumerate() {
+            if !m.is_koutsu_like() {
+                continue;
+            }
+            let mut mentsu_fu = if is_concealed(i, m) { 4 } else { 2 };
+            if is_yaochuu(m.first) {
+                mentsu_fu *= 2;
+            }
+            if m.kind == MentsuKind::Kantsu {
+                mentsu_fu *= 4;
+            }
+            fu += mentsu_fu;
+        }
+        if is_dragon(pair) {
+            fu += 2;
+        }
+        if pair == jikaze {
+            fu += 2;
+        }
+        if pair == bakaze {
+            fu += 2;
+        }
+        if matches!(wait, Wait::Kanchan | Wait::Penchan | Wait::Tanki) {
+            fu += 2;
+        }
+
+        // open pinfu shape still counts as 30 fu.
+        (fu.div_ceil(10) * 10).max(30)
+    };
+
+    Some(finish(ctx, list, fu))
+}
+
+fn add_dora(ctx: &HoraContext, list: &mut YakuList) {
+    let mut pais = ctx.tehai.clone();
+    pais.push(ctx.winning_pai);
+    for fuuro in &ctx.fuuros {
+        pais.extend(fuuro.pais());
+    }
+
+    let count = |markers: &[Pai]| -> u8 {
+        markers
+            .iter()
+            .map(|&marker| {
+                let dora = indicated_dora(marker, ctx.num_players);
+                let in_hand = pais.iter().filter(|p| p.deaka() == dora).count() as u8;
+                let nukidora = if dora == Pai::North { ctx.nukidora } else { 0 };
+                in_hand + nukidora
+            })
+            .sum()
+    };
+
+    // tenhou.net counts each nukidora as a dora.
+    let dora = count(&ctx.dora_markers) + ctx.nukidora;
+    if dora > 0 {
+        list.add("ドラ", dora);
+    }
+    let aka = pais.iter().filter(|p| p.is_aka()).count() as u8;
+    if aka > 0 {
+        list.add("赤ドラ", aka);
+    }
+    if ctx.is_reach {
+        let ura = count(&ctx.uradora_markers);
+        if ura > 0 {
+            list.add("裏ドラ", ura);
+        }
+    }
+}
+
+/// In sanma, 1m indicates 9m as there is no 2m to 8m.
+#[inline]
+fn indicated_dora(marker: Pai, num_players: u8) -> Pai {
+    if num_players == 3 && marker.deaka() == Pai::Man1 {
+        Pai::Man9
+    } else {
+        marker.indicated_dora()
+    }
+}
+
+/// Settles the limit and the payment.
+fn finish(ctx: &HoraContext, list: YakuList, fu: u8) -> HandValue {
+    let han = list.han();
+
+    let (limit, base) = if list.num_yakuman > 0 {
+        (Some(Limit::Yakuman), 8000 * list.num_yakuman as i32)
+    } else {
+        match han {
+            0..=4 => {
+                let base = fu as i32 * (1 << (han + 2));
+                if base >= 2000 {
+                    (Some(Limit::Mangan), 2000)
+                } else {
+                    (None, base)
+                }
+            }
+            5 => (Some(Limit::Mangan), 2000),
+            6..=7 => (Some(Limit::Haneman), 3000),
+            8..=10 => (Some(Limit::Baiman), 4000),
+            11..=12 => (Some(Limit::Sanbaiman), 6000),
+            _ => (Some(Limit::Yakuman), 8000),
+        }
+    };
+
+    HandValue {
+        deltas: payment(ctx, base),
+        yaku: list.yaku,
+        han,
+        fu: if list.num_yakuman > 0 { 0 } else { fu },
+        limit,
+    }
+}
+
+#[inline]
+fn ceil100(points: i32) -> i32 {
+    (points + 99) / 100 * 100
+}
+
+fn payment(ctx: &HoraContext, base: i32) -> [i32; 4] {
+    let mut deltas = [0; 4];
+    let actor = ctx.actor as usize;
+    let honba = ctx.honba as i32;
+    let num_payers = ctx.num_players as i32 - 1;
+
+    if ctx.is_tsumo() {
+        let pays: Vec<_> = (0..ctx.num_players)
+            .filter(|&i| i != ctx.actor)
+            .map(|i| {
+                // Sanma pays the same as yonma minus the absent player, i.e.
+                // with tsumo loss (ツモ損) as on tenhou.net.
+                let points = ceil100(base * if ctx.is_oya() || i == ctx.oya { 2 } else { 1 });
+                (i as usize, points + honba * 100)
+            })
+            .collect();
+        match ctx.pao {
+            Some(pao) => {
+                let total: i32 = pays.iter().map(|&(_, p)| p).sum();
+                deltas[pao as usize] -= total;
+            }
+            None => pays.iter().for_each(|&(i, p)| deltas[i] -= p),
+        }
+    } else {
+        let target = ctx.target as usize;
+        let points = ceil100(base * if ctx.is_oya() { 6 } else { 4 });
+        match ctx.pao {
+            Some(pao) if pao != ctx.target => {
+                deltas[pao as usize] -= points / 2;
+                deltas[target] -= points / 2;
+            }
+            _ => deltas[target] -= points,
+        }
+        deltas[target] -= honba * 100 * num_payers;
+    }
+
+    deltas[actor] = -deltas.iter().sum::<i32>() + ctx.kyotaku as i32 * 1000;
+    deltas
+}
@@ -0,0 +1,268 @@
+//! Shanten, waits and ukeire of a tehai.
+//!
+//! All the functions here take the closed part of a tehai as [`TileCounts`]
+//! and the number of fuuros separately, so that a 3n+1 tehai is waiting for
+//! a pai and a 3n+2 one is about to discard.
+
+use crate::pai::id34::is_yaochuu;
+use crate::{Pai, TileCounts};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Shanten of the tehai, which is the minimum of the standard form,
+/// chiitoitsu and kokushi, where -1 means agari and 0 means tenpai.
+/// Chiitoitsu and kokushi are only considered without fuuros.
+pub fn shanten(counts: &TileCounts, num_fuuros: usize) -> i8 {
+    let normal = normal_shanten(counts, num_fuuros);
+    if num_fuuros > 0 {
+        return normal;
+    }
+
+    normal
+        .min(chiitoitsu_shanten(counts))
+        .min(kokushi_shanten(counts))
+}
+
+/// Shanten in the standard form of four mentsu and a pair.
+pub fn normal_shanten(counts: &TileCounts, num_fuuros: usize) -> i8 {
+    let counts = counts.as_array();
+
+    // start from the fuuros, then add the blocks of each suit.
+    let mut blocks = 1 << block_bit(num_fuuros.min(4) as u8, 0, false);
+    for (start, len) in &[(0, 9), (9, 9), (18, 9), (27, 7)] {
+        let suit_blocks = SUIT_BLOCKS_CACHE.with(|cache| {
+            *cache
+                .borrow_mut()
+                .entry(suit_key(&counts[*start..*start + len]))
+                .or_insert_with(|| {
+                    let mut suit = counts[*start..*start + len].to_vec();
+                    let mut found = 0;
+                    search_blocks(&mut suit, 0, 0, 0, false, &mut found);
+                    found
+                })
+        });
+        blocks = combine_blocks(blocks, suit_blocks);
+    }
+
+    iter_blocks(blocks)
+        .map(|(mentsu, taatsu, has_pair)| {
+            let taatsu = taatsu.min(4 - mentsu);
+            8 - mentsu as i8 * 2 - taatsu as i8 - has_pair as i8
+        })
+        .min()
+        .unwrap_or(8)
+}
+
+/// A set of `(mentsu, taatsu, has_pair)` as a bitset, where mentsu and taatsu
+/// are capped at 4 as more never helps.
+type Blocks = u64;
+
+thread_local! {
+    /// Maps the counts of a suit to its `Blocks`. Honors are told apart from
+    /// the others by the length of 7.
+    static SUIT_BLOCKS_CACHE: RefCell<HashMap<u32, Blocks>> = RefCell::new(HashMap::new());
+}
+
+#[inline]
+fn suit_key(counts: &[u8]) -> u32 {
+    counts
+        .iter()
+        .fold(counts.len() as u32, |key, &c| key * 5 + c.min(4) as u32)
+}
+
+#[inline]
+fn block_bit(mentsu: u8, taatsu: u8, has_pair: bool) -> u32 {
+    has_pair as u32 * 25 + mentsu.min(4) as u32 * 5 + taatsu.min(4) as u32
+}
+
+#[inline]
+fn iter_blocks(blocks: Blocks) -> impl Iterator<Item = (u8, u8, bool)> {
+    (0..50)
+        .filter(move |bit| blocks & (1 << bit) != 0)
+        .map(|bit| ((bit % 25 / 5) as u8, (bit % 5) as u8, bit >= 25))
+}
+
+fn combine_blocks(a: Blocks, b: Blocks) -> Blocks {
+    let mut combined = 0;
+    for (ma, ta, pa) in iter_blocks(a) {
+        for (mb, tb, pb) in iter_blocks(b) {
+            if !(pa && pb) {
+                combined |= 1 << block_bit(ma + mb, ta + tb, pa || pb);
+            }
+        }
+    }
+    combined
+}
+
+/// Collects every way to split the counts of a single suit into blocks,
+/// taking them from the lowest pai at or after `start`.
+fn search_blocks(
+    counts: &mut [u8],
+    start: usize,
+    mentsu: u8,
+    taatsu: u8,
+    has_pair: bool,
+    found: &mut Blocks,
+) {
+    let id = match (start..counts.len()).find(|&id| counts[id] > 0) {
+        Some(id) => id,
+        None => {
+            *found |= 1 << block_bit(mentsu, taatsu, has_pair);
+            return;
+        }
+    };
+
+    // honors have 7 kinds only.
+    let is_number = counts.len() == 9;
+
+    if counts[id] >= 3 {
+        counts[id] -= 3;
+        search_blocks(counts, id, mentsu + 1, taatsu, has_pair, found);
+        counts[id] += 3;
+    }
+    if is_number && id <= 6 && counts[id + 1] > 0 && counts[id + 2] > 0 {
+        counts[id] -= 1;
+        counts[id + 1] -= 1;
+        counts[id + 2] -= 1;
+        search_blocks(counts, id, mentsu + 1, taatsu, has_pair, found);
+        counts[id] += 1;
+        counts[id + 1] += 1;
+        counts[id + 2] += 1;
+    }
+    if counts[id] >= 2 {
+        counts[id] -= 2;
+        if !has_pair {
+            search_blocks(counts, id, mentsu, taatsu, true, found);
+        }
+        search_blocks(counts, id, mentsu, taatsu + 1, has_pair, found);
+        counts[id] += 2;
+    }
+    for gap in 1..=2 {
+        if is_number && id + gap <= 8 && counts[id + gap] > 0 {
+            counts[id] -= 1;
+            counts[id + gap] -= 1;
+            search_blocks(counts, id, mentsu, taatsu + 1, has_pair, found);
+            counts[id] += 1;
+            counts[id + gap] += 1;
+        }
+    }
+
+    // leave it isolated.
+    counts[id] -= 1;
+    search_blocks(counts, id, mentsu, taatsu, has_pair, found);
+    counts[id] += 1;
+}
+
+pub fn chiitoitsu_shanten(counts: &TileCounts) -> i8 {
+    let counts = counts.as_array();
+    let pairs = counts.iter().filter(|&&c| c >= 2).count() as i8;
+    let kinds = counts.iter().filter(|&&c| c > 0).count() as i8;
+
+    // four of a kind counts as one pair only.
+    6 - pairs + (7 - kinds).max(0)
+}
+
+pub fn kokushi_shanten(counts: &TileCounts) -> i8 {
+    let yaochuu = (0..34).filter(|&id| is_yaochuu(id)).map(|id| counts[id]);
+    let kinds = yaochuu.clone().filter(|&c| c > 0).count() as i8;
+    let has_pair = yaochuu.clone().any(|c| c >= 2);
+
+    13 - kinds - has_pair as i8
+}
+
+/// Returns the pais that complete a 3n+1 tehai, or an empty `Vec` if it is
+/// not tenpai.
+pub fn waits(counts: &TileCounts, num_fuuros: usize) -> Vec<Pai> {
+    let mut counts = *counts;
+
+    (0..34)
+        .filter(|&id| {
+            if counts[id] >= 4 {
+                return false;
+            }
+            counts[id] += 1;
+            let is_agari = shanten(&counts, num_fuuros) == -1;
+            counts[id] -= 1;
+            is_agari
+        })
+        .filter_map(Pai::from_id34)
+        .collect()
+}
+
+/// Pais that reduce the shanten of a 3n+1 tehai.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ukeire {
+    pub shanten: i8,
+    pub pais: Vec<UkeirePai>,
+    /// Sum of `left` of all the pais.
+    pub total: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UkeirePai {
+    #[serde(deserialize_with = "Pai::deserialize_mjai_str")]
+    pub pai: Pai,
+    /// Number of the pai that can still be drawn, as far as the player can
+    /// see.
+    pub left: u8,
+}
+
+/// Counts the ukeire of a 3n+1 tehai. `visible` is the pais visible to the
+/// player outside of the tehai, such as kawa, fuuros and dora markers.
+pub fn ukeire(counts: &TileCounts, num_fuuros: usize, visible: &TileCounts) -> Ukeire {
+    let shanten = shanten(counts, num_fuuros);
+    let mut after = *counts;
+
+    let mut pais = vec![];
+    for id in 0..34 {
+        if after[id] >= 4 {
+            continue;
+        }
+        after[id] += 1;
+        if self::shanten(&after, num_fuuros) < shanten {
+            let left = 4u8.saturating_sub(counts[id] + visible[id]);
+            if let Some(pai) = Pai::from_id34(id) {
+                pais.push(UkeirePai { pai, left });
+            }
+        }
+        after[id] -= 1;
+    }
+
+    let total = pais.iter().map(|p| p.left).sum();
+    Ukeire {
+        shanten,
+        pais,
+        total,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiscardCandidate {
+    pub pai: Pai,
+    pub ukeire: Ukeire,
+}
+
+/// Evaluates every kind of pai in a 3n+2 tehai as the discard, sorted from
+/// the best, which is the lowest shanten and then the most ukeire.
+pub fn discard_candidates(
+    counts: &TileCounts,
+    num_fuuros: usize,
+    visible: &TileCounts,
+) -> Vec<DiscardCandidate> {
+    let mut candidates: Vec<_> = counts
+        .iter()
+        .map(|(pai, _)| {
+            let mut after = *counts;
+            after.remove(pai);
+            DiscardCandidate {
+                pai,
+                ukeire: ukeire(&after, num_fuuros, visible),
+            }
+        })
+        .collect();
+
+    candidates.sort_by_key(|c| (c.ukeire.shanten, u8::MAX - c.ukeire.total));
+    candidates
+}
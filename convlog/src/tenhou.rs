@@ -1,4 +1,4 @@
-use crate::{KyokuFilter, Pai};
+use crate::{Anonymizer, KyokuFilter, Pai};
 
 use std::fmt;
 
@@ -11,17 +11,223 @@ use serde_tuple::{Deserialize_tuple as DeserializeTuple, Serialize_tuple as Seri
 #[derive(Debug, Clone)]
 pub struct Log {
     pub names: [String; 4],
-    pub game_length: GameLength,
-    pub has_aka: bool,
+    pub rule: RuleSet,
+    /// Profiles of the players by seat, only known for logs from tenhou.net.
+    pub players: [PlayerInfo; 4],
+    pub lobby: LobbyInfo,
     pub kyokus: Vec<Kyoku>,
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Profile of a player at the time of the game.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PlayerInfo {
+    /// Like "四段" or "新人".
+    pub dan: Option<String>,
+    /// R, the rating.
+    pub rate: Option<f64>,
+    pub sex: Option<Sex>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sex {
+    Male,
+    Female,
+    /// Bots.
+    Computer,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LobbyInfo {
+    /// 0 for the public lobby, otherwise the number of a private lobby.
+    pub lobby: Option<i32>,
+    /// The rating class, like "PF4" for the four-player ranked games.
+    pub rating_class: Option<String>,
+}
+
+impl Sex {
+    /// Parses "M", "F" or "C" as used by tenhou.net.
+    #[inline]
+    pub fn from_tenhou_str(s: &str) -> Option<Self> {
+        match s {
+            "M" => Some(Sex::Male),
+            "F" => Some(Sex::Female),
+            "C" => Some(Sex::Computer),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_tenhou_str(self) -> &'static str {
+        match self {
+            Sex::Male => "M",
+            Sex::Female => "F",
+            Sex::Computer => "C",
+        }
+    }
+}
+
+/// The rule of a game, which tenhou.net shows like "三鳳南喰赤速".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleSet {
+    /// `None` if the rule does not tell, which is the case for some logs not
+    /// from the ranked lobbies.
+    pub tier: Option<Tier>,
+    /// Three-player mahjong, where the 4th seat is absent and manzu 2~8 are
+    /// removed from the wall.
+    pub is_sanma: bool,
+    pub game_length: GameLength,
+    /// Whether open tanyao is allowed (喰断).
+    pub has_kuitan: bool,
+    /// Number of aka 5m, 5p and 5s.
+    pub aka_counts: [u8; 3],
+    /// Shorter time to think (速).
+    pub is_fast: bool,
+}
+
+/// The table of the ranked lobby.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tier {
+    /// 般
+    Ippan,
+    /// 上
+    Joukyuu,
+    /// 特
+    Tokujou,
+    /// 鳳
+    Houou,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GameLength {
     Hanchan = 0,
     Tonpuu = 4,
 }
 
+impl Default for RuleSet {
+    /// Four-player hanchan with kuitan and one aka for each suit.
+    #[inline]
+    fn default() -> Self {
+        RuleSet {
+            tier: None,
+            is_sanma: false,
+            game_length: GameLength::Hanchan,
+            has_kuitan: true,
+            aka_counts: [1; 3],
+            is_fast: false,
+        }
+    }
+}
+
+impl RuleSet {
+    /// Decodes something like "三鳳南喰赤速", ignoring unknown characters.
+    /// Aka is either none or one for each suit as told by "赤".
+    pub fn from_disp(disp: &str) -> Self {
+        let tier = disp.chars().find_map(|c| match c {
+            '般' => Some(Tier::Ippan),
+            '上' => Some(Tier::Joukyuu),
+            '特' => Some(Tier::Tokujou),
+            '鳳' => Some(Tier::Houou),
+            _ => None,
+        });
+        let game_length = if disp.contains('東') {
+            GameLength::Tonpuu
+        } else {
+            GameLength::Hanchan
+        };
+
+        RuleSet {
+            tier,
+            is_sanma: disp.contains('三'),
+            game_length,
+            has_kuitan: disp.contains('喰'),
+            aka_counts: if disp.contains('赤') { [1; 3] } else { [0; 3] },
+            is_fast: disp.contains('速'),
+        }
+    }
+
+    #[inline]
+    pub fn has_aka(&self) -> bool {
+        self.aka_counts.iter().any(|&c| c > 0)
+    }
+
+    #[inline]
+    pub fn num_players(&self) -> u8 {
+        if self.is_sanma {
+            3
+        } else {
+            4
+        }
+    }
+
+    fn from_raw(rule: &json_scheme::Rule) -> Self {
+        let mut rule_set = Self::from_disp(&rule.disp);
+
+        // the explicit counts take precedence over "赤".
+        if rule.aka51 + rule.aka52 + rule.aka53 > 0 {
+            rule_set.aka_counts = [rule.aka51, rule.aka52, rule.aka53];
+        } else {
+            rule_set.aka_counts = [rule.aka; 3];
+        }
+
+        rule_set
+    }
+
+    fn to_raw(&self) -> json_scheme::Rule {
+        let [aka51, aka52, aka53] = self.aka_counts;
+        let mut rule = json_scheme::Rule {
+            disp: self.to_string(),
+            ..Default::default()
+        };
+        if aka51 == aka52 && aka52 == aka53 {
+            rule.aka = aka51;
+        } else {
+            rule.aka51 = aka51;
+            rule.aka52 = aka52;
+            rule.aka53 = aka53;
+        }
+
+        rule
+    }
+}
+
+impl fmt::Display for RuleSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_sanma {
+            write!(f, "三")?;
+        }
+        if let Some(tier) = self.tier {
+            write!(f, "{}", tier)?;
+        }
+        match self.game_length {
+            GameLength::Hanchan => write!(f, "南")?,
+            GameLength::Tonpuu => write!(f, "東")?,
+        };
+        if self.has_kuitan {
+            write!(f, "喰")?;
+        }
+        if self.has_aka() {
+            write!(f, "赤")?;
+        }
+        if self.is_fast {
+            write!(f, "速")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Tier {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Tier::Ippan => write!(f, "般"),
+            Tier::Joukyuu => write!(f, "上"),
+            Tier::Tokujou => write!(f, "特"),
+            Tier::Houou => write!(f, "鳳"),
+        }
+    }
+}
+
 impl fmt::Display for GameLength {
     #[inline]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -55,8 +261,81 @@ pub mod kyoku {
 
     #[derive(Debug, Clone)]
     pub enum EndStatus {
-        Hora { details: Vec<HoraDetail> },
-        Ryukyoku { score_deltas: [i32; 4] },
+        Hora {
+            details: Vec<HoraDetail>,
+        },
+        Ryukyoku {
+            kind: RyukyokuKind,
+            score_deltas: [i32; 4],
+            /// Who is tenpai at the end of an exhaustive draw. Always all
+            /// `false` for abortive draws.
+            tenpai: [bool; 4],
+        },
+    }
+
+    impl Default for EndStatus {
+        #[inline]
+        fn default() -> Self {
+            EndStatus::Ryukyoku {
+                kind: RyukyokuKind::default(),
+                score_deltas: [0; 4],
+                tenpai: [false; 4],
+            }
+        }
+    }
+
+    /// The reason of a ryukyoku.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum RyukyokuKind {
+        /// 荒牌平局, the wall is exhausted.
+        #[default]
+        Exhaustive,
+        KyuushuKyuuhai,
+        SuufonRenda,
+        SuuchaRiichi,
+        SuukanSanra,
+        SanchaHoo,
+        NagashiMangan,
+    }
+
+    impl RyukyokuKind {
+        /// Parse the status text in results. Exhaustive draws are written as
+        /// "流局", "全員聴牌" or "全員不聴", all of which yield
+        /// `Exhaustive`. Unknown text also falls back to `Exhaustive`.
+        pub fn from_status_text(text: &str) -> Self {
+            match text {
+                "九種九牌" => RyukyokuKind::KyuushuKyuuhai,
+                "四風連打" => RyukyokuKind::SuufonRenda,
+                "四家立直" => RyukyokuKind::SuuchaRiichi,
+                "四槓散了" | "四開槓" => RyukyokuKind::SuukanSanra,
+                "三家和了" | "三家和" => RyukyokuKind::SanchaHoo,
+                "流し満貫" => RyukyokuKind::NagashiMangan,
+                _ => RyukyokuKind::Exhaustive,
+            }
+        }
+
+        /// Whether the game is aborted, in which case there is no score
+        /// change.
+        #[inline]
+        pub fn is_abortive(self) -> bool {
+            !matches!(self, RyukyokuKind::Exhaustive | RyukyokuKind::NagashiMangan)
+        }
+    }
+
+    impl fmt::Display for RyukyokuKind {
+        #[inline]
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                RyukyokuKind::Exhaustive => write!(f, "流局"),
+                RyukyokuKind::KyuushuKyuuhai => write!(f, "九種九牌"),
+                RyukyokuKind::SuufonRenda => write!(f, "四風連打"),
+                RyukyokuKind::SuuchaRiichi => write!(f, "四家立直"),
+                RyukyokuKind::SuukanSanra => write!(f, "四槓散了"),
+                RyukyokuKind::SanchaHoo => write!(f, "三家和了"),
+                RyukyokuKind::NagashiMangan => write!(f, "流し満貫"),
+            }
+        }
     }
 
     #[derive(Debug, Clone, Default)]
@@ -64,13 +343,182 @@ pub mod kyoku {
         pub who: u8,
         pub target: u8,
         pub score_deltas: [i32; 4],
+
+        /// The one who is responsible for the yakuman (包), if any.
+        pub pao: Option<u8>,
+        /// 0 if it is not shown, which is the case for mangan and above.
+        pub fu: u8,
+        pub han: u8,
+        pub limit: Option<Limit>,
+        pub yaku: Vec<Yaku>,
+        /// The rest of the point string after fu, han or limit, e.g.
+        /// "1000-2000点" and "4000点∀".
+        pub points: String,
+    }
+
+    /// A yaku along with its han, where a yakuman counts as 13 han.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Yaku {
+        pub name: String,
+        pub han: u8,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum Limit {
+        Mangan,
+        Haneman,
+        Baiman,
+        Sanbaiman,
+        Yakuman,
+    }
+
+    impl fmt::Display for Limit {
+        #[inline]
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Limit::Mangan => write!(f, "満貫"),
+                Limit::Haneman => write!(f, "跳満"),
+                Limit::Baiman => write!(f, "倍満"),
+                Limit::Sanbaiman => write!(f, "三倍満"),
+                Limit::Yakuman => write!(f, "役満"),
+            }
+        }
+    }
+
+    impl HoraDetail {
+        /// Parse the hora detail item in results, which is in the form of
+        /// `[who, target, pao, point string, yaku...]`, e.g.
+        /// `[1, 0, 1, "30符2飜2000点", "役牌 白(1飜)", "赤ドラ(1飜)"]`.
+        ///
+        /// Malformed or missing items are left as default.
+        pub(super) fn from_values(score_deltas: [i32; 4], values: &[Value]) -> Self {
+            let int = |i: usize| values.get(i).and_then(Value::as_u64).unwrap_or(0) as u8;
+            let text = |v: &Value| v.as_str().unwrap_or("").to_owned();
+
+            let who = int(0);
+            let pao = values
+                .get(2)
+                .and_then(Value::as_u64)
+                .map(|p| p as u8)
+                .filter(|&p| p != who);
+
+            let yaku: Vec<_> = values
+                .iter()
+                .skip(4)
+                .filter_map(|v| Yaku::parse(&text(v)))
+                .collect();
+
+            let mut detail = HoraDetail {
+                who,
+                target: int(1),
+                score_deltas,
+                pao,
+                han: yaku.iter().map(|y| y.han).sum(),
+                yaku,
+                ..Default::default()
+            };
+            if let Some(point_string) = values.get(3) {
+                detail.parse_point_string(&text(point_string));
+            }
+
+            detail
+        }
+
+        fn parse_point_string(&mut self, s: &str) {
+            const LIMITS: &[(&str, Limit)] = &[
+                ("満貫", Limit::Mangan),
+                ("跳満", Limit::Haneman),
+                ("倍満", Limit::Baiman),
+                ("三倍満", Limit::Sanbaiman),
+                ("役満", Limit::Yakuman),
+            ];
+
+            for &(name, limit) in LIMITS {
+                if let Some(rest) = s.strip_prefix(name) {
+                    self.limit = Some(limit);
+                    self.points = rest.to_owned();
+                    return;
+                }
+            }
+
+            // e.g. "30符2飜2000点"
+            let mut rest = s;
+            if let Some((fu, r)) = rest.split_once('符') {
+                if let Ok(fu) = fu.parse() {
+                    self.fu = fu;
+                    rest = r;
+                }
+            }
+            if let Some((han, r)) = rest.split_once('飜') {
+                if let Ok(han) = han.parse() {
+                    self.han = han;
+                    rest = r;
+                }
+            }
+            self.points = rest.to_owned();
+        }
+
+        /// The reverse of `from_values`.
+        pub(super) fn to_values(&self) -> Vec<Value> {
+            let mut values = vec![
+                self.who.into(),
+                self.target.into(),
+                self.pao.unwrap_or(self.who).into(),
+            ];
+
+            let point_string = if let Some(limit) = self.limit {
+                format!("{}{}", limit, self.points)
+            } else if self.fu > 0 {
+                format!("{}符{}飜{}", self.fu, self.han, self.points)
+            } else {
+                self.points.clone()
+            };
+            if !point_string.is_empty() || !self.yaku.is_empty() {
+                values.push(point_string.into());
+                values.extend(self.yaku.iter().map(|y| y.to_string().into()));
+            }
+
+            values
+        }
+    }
+
+    impl Yaku {
+        /// Parse a yaku string, e.g. "立直(1飜)" and "国士無双(役満)".
+        pub fn parse(s: &str) -> Option<Self> {
+            let (name, value) = s.strip_suffix(')')?.rsplit_once('(')?;
+            let han = if value == "役満" {
+                13
+            } else {
+                value.strip_suffix('飜')?.parse().ok()?
+            };
+
+            Some(Yaku {
+                name: name.to_owned(),
+                han,
+            })
+        }
+    }
+
+    impl fmt::Display for Yaku {
+        #[inline]
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            if self.han >= 13 {
+                write!(f, "{}(役満)", self.name)
+            } else {
+                write!(f, "{}({}飜)", self.name, self.han)
+            }
+        }
     }
 }
 
-pub use kyoku::Kyoku;
+pub use kyoku::{HoraDetail, Kyoku, Limit, RyukyokuKind, Yaku};
 
 /// A group of "配牌", "取" and "出", describing a player's
 /// gaming status and actions throughout a kyoku.
+///
+/// In sanma, the table of the absent 4th seat has a haipai of all
+/// `Pai::Unknown`, and empty takes and discards.
 #[derive(Debug, Clone)]
 pub struct ActionTable {
     pub haipai: [Pai; 13],
@@ -91,6 +539,9 @@ pub enum ActionItem {
 mod json_scheme {
     use super::*;
 
+    use serde::de::Error;
+    use serde::{Deserializer, Serializer};
+
     #[derive(Debug, Clone, Serialize, Deserialize)]
     #[serde(untagged)]
     pub(super) enum ResultItem {
@@ -108,22 +559,26 @@ mod json_scheme {
         #[serde(serialize_with = "Pai::serialize_slice_literal")]
         pub(super) ura_indicators: Vec<Pai>,
 
-        #[serde(serialize_with = "Pai::serialize_slice_literal")]
+        #[serde(serialize_with = "serialize_haipai")]
+        #[serde(deserialize_with = "deserialize_haipai")]
         pub(super) haipai_0: [Pai; 13],
         pub(super) takes_0: Vec<ActionItem>,
         pub(super) discards_0: Vec<ActionItem>,
 
-        #[serde(serialize_with = "Pai::serialize_slice_literal")]
+        #[serde(serialize_with = "serialize_haipai")]
+        #[serde(deserialize_with = "deserialize_haipai")]
         pub(super) haipai_1: [Pai; 13],
         pub(super) takes_1: Vec<ActionItem>,
         pub(super) discards_1: Vec<ActionItem>,
 
-        #[serde(serialize_with = "Pai::serialize_slice_literal")]
+        #[serde(serialize_with = "serialize_haipai")]
+        #[serde(deserialize_with = "deserialize_haipai")]
         pub(super) haipai_2: [Pai; 13],
         pub(super) takes_2: Vec<ActionItem>,
         pub(super) discards_2: Vec<ActionItem>,
 
-        #[serde(serialize_with = "Pai::serialize_slice_literal")]
+        #[serde(serialize_with = "serialize_haipai")]
+        #[serde(deserialize_with = "deserialize_haipai")]
         pub(super) haipai_3: [Pai; 13],
         pub(super) takes_3: Vec<ActionItem>,
         pub(super) discards_3: Vec<ActionItem>,
@@ -131,6 +586,38 @@ mod json_scheme {
         pub(super) results: Vec<ResultItem>,
     }
 
+    /// The haipai of the absent seat in sanma is an empty array.
+    fn serialize_haipai<S>(
+        haipai: &[Pai; 13],
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if haipai.iter().all(|&p| p == Pai::Unknown) {
+            Pai::serialize_slice_literal([], serializer)
+        } else {
+            Pai::serialize_slice_literal(haipai, serializer)
+        }
+    }
+
+    fn deserialize_haipai<'de, D>(deserializer: D) -> std::result::Result<[Pai; 13], D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let pais = Vec::<Pai>::deserialize(deserializer)?;
+
+        match pais.len() {
+            0 => Ok([Pai::Unknown; 13]),
+            13 => {
+                let mut haipai = [Pai::Unknown; 13];
+                haipai.copy_from_slice(&pais);
+                Ok(haipai)
+            }
+            n => Err(D::Error::invalid_length(n, &"13 or 0 pais")),
+        }
+    }
+
     #[derive(Debug, Clone, Default, Serialize, Deserialize)]
     #[serde(default)]
     pub(super) struct Rule {
@@ -174,10 +661,22 @@ mod json_scheme {
 pub use json_scheme::{Log as RawLog, PartialLog as RawPartialLog};
 
 impl RawLog {
+    #[inline]
+    pub fn names(&self) -> &[String; 4] {
+        &self.names
+    }
+
     #[inline]
     pub fn filter_kyokus(&mut self, kyoku_filter: &KyokuFilter) {
-        self.logs
-            .retain(|l| kyoku_filter.test(l.meta.kyoku_num, l.meta.honba))
+        kyoku_filter.retain(&mut self.logs, |l| (l.meta.kyoku_num, l.meta.honba));
+    }
+
+    /// Replaces the names and removes the dan, rate and sex of the players.
+    pub fn anonymize(&mut self, anonymizer: &Anonymizer) {
+        anonymizer.anonymize_names(&mut self.names);
+        self.dan = None;
+        self.rate = None;
+        self.sx = None;
     }
 
     /// Split one raw tenhou.net/6 log into many by kyokus.
@@ -186,7 +685,7 @@ impl RawLog {
 
         for kyoku in self.logs.chunks(1) {
             let kyoku_log = RawPartialLog {
-                parent: &self,
+                parent: self,
                 logs: kyoku,
             };
 
@@ -214,25 +713,47 @@ impl Log {
         Ok(Self::from(raw_log))
     }
 
+    /// Returns 3 for sanma and 4 otherwise.
+    #[inline]
+    pub fn num_players(&self) -> u8 {
+        self.rule.num_players()
+    }
+
     #[inline]
     pub fn filter_kyokus(&mut self, kyoku_filter: &KyokuFilter) {
-        self.kyokus
-            .retain(|l| kyoku_filter.test(l.meta.kyoku_num, l.meta.honba))
+        kyoku_filter.retain(&mut self.kyokus, |l| (l.meta.kyoku_num, l.meta.honba));
     }
 }
 
 impl From<RawLog> for Log {
     fn from(raw_log: RawLog) -> Self {
         let RawLog {
-            logs, names, rule, ..
+            logs,
+            names,
+            rule,
+            ratingc,
+            lobby,
+            dan,
+            rate,
+            sx,
         } = raw_log;
 
-        let game_length = if rule.disp.contains('東') {
-            GameLength::Tonpuu
-        } else {
-            GameLength::Hanchan
+        let mut players: [PlayerInfo; 4] = Default::default();
+        for (i, player) in players.iter_mut().enumerate() {
+            player.dan = dan.as_ref().and_then(|d| d.get(i)).cloned();
+            player.rate = rate.as_ref().and_then(|r| r.get(i)).copied();
+            player.sex = sx
+                .as_ref()
+                .and_then(|s| s.get(i))
+                .and_then(|s| Sex::from_tenhou_str(s));
+        }
+        let lobby = LobbyInfo {
+            lobby,
+            rating_class: ratingc,
         };
-        let has_aka = rule.aka + rule.aka51 + rule.aka52 + rule.aka53 > 0;
+
+        let rule = RuleSet::from_raw(&rule);
+        let is_sanma = rule.is_sanma;
 
         let kyokus = logs
             .into_iter()
@@ -264,50 +785,61 @@ impl From<RawLog> for Log {
                             discards: log.discards_3,
                         },
                     ],
-                    end_status: kyoku::EndStatus::Ryukyoku {
-                        score_deltas: [0; 4], // default
-                    },
+                    end_status: kyoku::EndStatus::default(),
                 };
 
-                if let Some(status) = log.results.get(0) {
-                    if let json_scheme::ResultItem::Status(status_text) = status {
-                        if status_text == "和了" {
-                            let hora_details = log.results[1..]
-                                .chunks_exact(2)
-                                .filter_map(|detail_tuple| {
-                                    if let (
-                                        json_scheme::ResultItem::ScoreDeltas(score_deltas),
-                                        json_scheme::ResultItem::HoraDetail(who_target_tuple),
-                                    ) = (&detail_tuple[0], &detail_tuple[1])
-                                    {
-                                        // TODO: it can actually fail, maybe impl TryFrom instead
-                                        let hora_detail = kyoku::HoraDetail {
-                                            score_deltas: *score_deltas,
-                                            who: who_target_tuple[0].as_u64().unwrap_or(0) as u8,
-                                            target: who_target_tuple[1].as_u64().unwrap_or(0) as u8,
-                                        };
-                                        Some(hora_detail)
-                                    } else {
-                                        None
-                                    }
-                                })
-                                .collect();
-
-                            item.end_status = kyoku::EndStatus::Hora {
-                                details: hora_details,
-                            };
-                        } else {
-                            let score_deltas =
-                                if let Some(json_scheme::ResultItem::ScoreDeltas(dts)) =
-                                    log.results.get(1)
+                if let Some(json_scheme::ResultItem::Status(status_text)) = log.results.first() {
+                    if status_text == "和了" {
+                        let hora_details = log.results[1..]
+                            .chunks_exact(2)
+                            .filter_map(|detail_tuple| {
+                                if let (
+                                    json_scheme::ResultItem::ScoreDeltas(score_deltas),
+                                    json_scheme::ResultItem::HoraDetail(who_target_tuple),
+                                ) = (&detail_tuple[0], &detail_tuple[1])
                                 {
-                                    *dts
+                                    Some(kyoku::HoraDetail::from_values(
+                                        *score_deltas,
+                                        who_target_tuple,
+                                    ))
                                 } else {
-                                    [0; 4]
-                                };
-
-                            item.end_status = kyoku::EndStatus::Ryukyoku { score_deltas };
+                                    None
+                                }
+                            })
+                            .collect();
+
+                        item.end_status = kyoku::EndStatus::Hora {
+                            details: hora_details,
+                        };
+                    } else {
+                        let score_deltas = if let Some(json_scheme::ResultItem::ScoreDeltas(dts)) =
+                            log.results.get(1)
+                        {
+                            *dts
+                        } else {
+                            [0; 4]
+                        };
+
+                        let kind = kyoku::RyukyokuKind::from_status_text(status_text);
+                        let mut tenpai = [false; 4];
+                        if kind == kyoku::RyukyokuKind::Exhaustive {
+                            let num_players = if is_sanma { 3 } else { 4 };
+                            match status_text.as_str() {
+                                "全員聴牌" => tenpai[..num_players].fill(true),
+                                "全員不聴" => (),
+                                _ => {
+                                    for (t, &d) in tenpai.iter_mut().zip(&score_deltas) {
+                                        *t = d > 0;
+                                    }
+                                }
+                            }
                         }
+
+                        item.end_status = kyoku::EndStatus::Ryukyoku {
+                            kind,
+                            score_deltas,
+                            tenpai,
+                        };
                     }
                 }
 
@@ -317,9 +849,124 @@ impl From<RawLog> for Log {
 
         Log {
             names,
-            game_length,
-            has_aka,
+            rule,
+            players,
+            lobby,
+            kyokus,
+        }
+    }
+}
+
+impl From<Log> for RawLog {
+    fn from(log: Log) -> Self {
+        let Log {
+            names,
+            rule,
+            players,
+            lobby,
             kyokus,
+        } = log;
+
+        let is_sanma = rule.is_sanma;
+        let rule = rule.to_raw();
+
+        let logs = kyokus
+            .into_iter()
+            .map(|kyoku| {
+                let results = match kyoku.end_status {
+                    kyoku::EndStatus::Hora { details } => {
+                        let mut results = vec![json_scheme::ResultItem::Status("和了".to_owned())];
+                        for detail in details {
+                            results.push(json_scheme::ResultItem::ScoreDeltas(detail.score_deltas));
+                            results.push(json_scheme::ResultItem::HoraDetail(detail.to_values()));
+                        }
+                        results
+                    }
+
+                    kyoku::EndStatus::Ryukyoku {
+                        kind,
+                        score_deltas,
+                        tenpai,
+                    } => {
+                        let num_players = if is_sanma { 3 } else { 4 };
+                        let status = match kind {
+                            kyoku::RyukyokuKind::Exhaustive
+                                if tenpai[..num_players].iter().all(|&t| t) =>
+                            {
+                                "全員聴牌".to_owned()
+                            }
+                            kyoku::RyukyokuKind::Exhaustive
+                                if !tenpai.contains(&true) && score_deltas == [0; 4] =>
+                            {
+                                "全員不聴".to_owned()
+                            }
+                            _ => kind.to_string(),
+                        };
+
+                        let mut results = vec![json_scheme::ResultItem::Status(status)];
+                        if !kind.is_abortive() {
+                            results.push(json_scheme::ResultItem::ScoreDeltas(score_deltas));
+                        }
+                        results
+                    }
+                };
+
+                let [t0, t1, t2, t3] = kyoku.action_tables;
+                json_scheme::Kyoku {
+                    meta: kyoku.meta,
+                    scoreboard: kyoku.scoreboard,
+                    dora_indicators: kyoku.dora_indicators,
+                    ura_indicators: kyoku.ura_indicators,
+
+                    haipai_0: t0.haipai,
+                    takes_0: t0.takes,
+                    discards_0: t0.discards,
+
+                    haipai_1: t1.haipai,
+                    takes_1: t1.takes,
+                    discards_1: t1.discards,
+
+                    haipai_2: t2.haipai,
+                    takes_2: t2.takes,
+                    discards_2: t2.discards,
+
+                    haipai_3: t3.haipai,
+                    takes_3: t3.takes,
+                    discards_3: t3.discards,
+
+                    results,
+                }
+            })
+            .collect();
+
+        // unknown ones, such as the absent seat in sanma, are left empty to
+        // keep the seats in place.
+        let dan = players.iter().any(|p| p.dan.is_some()).then(|| {
+            players
+                .iter()
+                .map(|p| p.dan.clone().unwrap_or_default())
+                .collect()
+        });
+        let rate = players
+            .iter()
+            .any(|p| p.rate.is_some())
+            .then(|| players.iter().map(|p| p.rate.unwrap_or_default()).collect());
+        let sx = players.iter().any(|p| p.sex.is_some()).then(|| {
+            players
+                .iter()
+                .map(|p| p.sex.map_or("", Sex::as_tenhou_str).to_owned())
+                .collect()
+        });
+
+        RawLog {
+            logs,
+            names,
+            rule,
+            ratingc: lobby.rating_class,
+            lobby: lobby.lobby,
+            dan,
+            rate,
+            sx,
         }
     }
 }
@@ -0,0 +1,322 @@
+use crate::board::{Board, BoardError};
+use crate::mjai::Event;
+use crate::score;
+use crate::Pai;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+#[error("invalid event at index {index}: {kind}")]
+pub struct ValidationError {
+    pub index: usize,
+    pub kind: ValidationErrorKind,
+}
+
+#[derive(Debug, Error)]
+pub enum ValidationErrorKind {
+    #[error(transparent)]
+    Board(#[from] BoardError),
+
+    #[error("actor {actor} cannot chi from {target}")]
+    ChiFromWrongSeat { actor: u8, target: u8 },
+
+    #[error("actor {actor} calls {pai} from {target}, but the last discard is {last:?}")]
+    NakiMismatch {
+        actor: u8,
+        target: u8,
+        pai: Pai,
+        last: Option<(u8, Pai)>,
+    },
+
+    #[error("actor {actor} tsumogiri {pai}, but the drawn pai is {drawn:?}")]
+    TsumogiriMismatch {
+        actor: u8,
+        pai: Pai,
+        drawn: Option<Pai>,
+    },
+
+    #[error("missing dora after {0} kan(s)")]
+    MissingDora(u8),
+
+    #[error("dora without a kan")]
+    UnexpectedDora,
+
+    #[error("actor {0} declares reach with an open hand")]
+    ReachWithOpenHand(u8),
+
+    #[error("actor {0} declares reach twice")]
+    DuplicateReach(u8),
+
+    #[error("actor {actor} declares reach with only {score} points")]
+    InsufficientScoreForReach { actor: u8, score: i32 },
+
+    #[error("reach of actor {0} is accepted without a declaration and discard")]
+    UnexpectedReachAccepted(u8),
+
+    #[error("score deltas {deltas:?} sum to {sum}, expected {expected}")]
+    DeltasMismatch {
+        deltas: [i32; 4],
+        sum: i32,
+        expected: i32,
+    },
+
+    #[error("actor {actor} wins with deltas {deltas:?}, but the hand is worth {expected:?}")]
+    HoraValueMismatch {
+        actor: u8,
+        deltas: [i32; 4],
+        expected: [i32; 4],
+    },
+
+    #[error("scores {actual:?} do not match the result of the last kyoku {expected:?}")]
+    ScoresMismatch {
+        actual: [i32; 4],
+        expected: [i32; 4],
+    },
+
+    #[error("kyotaku {actual} does not match the result of the last kyoku {expected}")]
+    KyotakuMismatch { actual: u8, expected: u8 },
+}
+
+pub type Result<T> = std::result::Result<T, ValidationError>;
+
+/// Replays the events and checks whether they describe a legal game. Stops
+/// at the first violation found.
+///
+/// Hands are not checked against the wall, so a log with fabricated tsumo can
+/// still pass as long as it is self-consistent.
+pub fn validate(events: &[Event]) -> Result<()> {
+    let mut validator = Validator::default();
+
+    for (index, event) in events.iter().enumerate() {
+        validator
+            .check(event)
+            .map_err(|kind| ValidationError { index, kind })?;
+    }
+
+    Ok(())
+}
+
+#[derive(Default)]
+struct Validator {
+    board: Board,
+
+    last_discard: Option<(u8, Pai)>,
+    drawn: [Option<Pai>; 4],
+    /// Kans whose dora has not been revealed yet.
+    pending_doras: u8,
+    /// Whether the next tsumo is from the dead wall.
+    is_rinshan: bool,
+    /// Reach declared but the declaration pai not discarded yet.
+    pending_reach: Option<u8>,
+    reach_discarded: [bool; 4],
+
+    /// Scores and kyotaku the next kyoku should start with, only known when
+    /// the last kyoku recorded its deltas.
+    expected_start: Option<([i32; 4], u8)>,
+}
+
+impl Validator {
+    fn check(&mut self, event: &Event) -> std::result::Result<(), ValidationErrorKind> {
+        let num_players = self.board.num_players;
+
+        match *event {
+            Event::StartKyoku {
+                scores, kyotaku, ..
+            } => {
+                if let Some((expected, expected_kyotaku)) = self.expected_start.take() {
+                    if scores != expected {
+                        return Err(ValidationErrorKind::ScoresMismatch {
+                            actual: scores,
+                            expected,
+                        });
+                    }
+                    if kyotaku != expected_kyotaku {
+                        return Err(ValidationErrorKind::KyotakuMismatch {
+                            actual: kyotaku,
+                            expected: expected_kyotaku,
+                        });
+                    }
+                }
+
+                self.last_discard = None;
+                self.drawn = [None; 4];
+                self.pending_doras = 0;
+                self.is_rinshan = false;
+                self.pending_reach = None;
+                self.reach_discarded = [false; 4];
+            }
+
+            Event::Tsumo { actor, pai } => {
+                if !self.is_rinshan && self.pending_doras > 0 {
+                    return Err(ValidationErrorKind::MissingDora(self.pending_doras));
+                }
+                self.is_rinshan = false;
+                self.drawn[actor as usize] = Some(pai);
+            }
+
+            Event::Dahai {
+                actor,
+                pai,
+                tsumogiri,
+            } => {
+                let drawn = self.drawn[actor as usize].take();
+                if tsumogiri && drawn != Some(pai) {
+                    return Err(ValidationErrorKind::TsumogiriMismatch { actor, pai, drawn });
+                }
+                if self.pending_reach == Some(actor) {
+                    self.pending_reach = None;
+                    self.reach_discarded[actor as usize] = true;
+                }
+                self.last_discard = Some((actor, pai));
+            }
+
+            Event::Chi {
+                actor, target, pai, ..
+            } => {
+                if num_players == 3 || target != (actor + num_players - 1) % num_players {
+                    return Err(ValidationErrorKind::ChiFromWrongSeat { actor, target });
+                }
+                self.check_naki(actor, target, pai)?;
+            }
+
+            Event::Pon {
+                actor, target, pai, ..
+            } => self.check_naki(actor, target, pai)?,
+
+            Event::Daiminkan {
+                actor, target, pai, ..
+            } => {
+                self.check_naki(actor, target, pai)?;
+                self.pending_doras += 1;
+                self.is_rinshan = true;
+            }
+
+            Event::Kakan { actor, .. } | Event::Ankan { actor, .. } => {
+                self.drawn[actor as usize] = None;
+                self.pending_doras += 1;
+                self.is_rinshan = true;
+            }
+
+            Event::Nukidora { actor, .. } => {
+                self.drawn[actor as usize] = None;
+                self.is_rinshan = true;
+            }
+
+            Event::Dora { .. } => {
+                if self.pending_doras == 0 {
+                    return Err(ValidationErrorKind::UnexpectedDora);
+                }
+                self.pending_doras -= 1;
+            }
+
+            Event::Reach { actor } => {
+                let player = &self.board.players[actor as usize];
+                if player.is_reach() {
+                    return Err(ValidationErrorKind::DuplicateReach(actor));
+                }
+                if player
+                    .fuuros
+                    .iter()
+                    .any(|f| !matches!(f, crate::board::Fuuro::Ankan { .. }))
+                {
+                    return Err(ValidationErrorKind::ReachWithOpenHand(actor));
+                }
+                let score = self.board.scores[actor as usize];
+                if score < 1000 {
+                    return Err(ValidationErrorKind::InsufficientScoreForReach { actor, score });
+                }
+                self.pending_reach = Some(actor);
+            }
+
+            Event::ReachAccepted { actor } => {
+                let player = &self.board.players[actor as usize];
+                if !self.reach_discarded[actor as usize] || player.reach_accepted {
+                    return Err(ValidationErrorKind::UnexpectedReachAccepted(actor));
+                }
+            }
+
+            Event::Hora {
+                actor,
+                deltas: Some(deltas),
+                ..
+            } => {
+                // kyotaku all go to the first winner in case of multiple
+                // ron.
+                let expected = self.board.kyotaku as i32 * 1000;
+                check_deltas_sum(deltas, expected)?;
+
+                // hands with unknown pais or under rules we do not know of
+                // cannot be valued, which is not an error by itself.
+                let value = self
+                    .board
+                    .hora_context(event)
+                    .and_then(|ctx| score::calculate(&ctx));
+                if let Some(value) = value {
+                    if value.deltas != deltas {
+                        return Err(ValidationErrorKind::HoraValueMismatch {
+                            actor,
+                            deltas,
+                            expected: value.deltas,
+                        });
+                    }
+                }
+            }
+
+            Event::Ryukyoku {
+                deltas: Some(deltas),
+                ..
+            } => check_deltas_sum(deltas, 0)?,
+
+            _ => (),
+        };
+
+        self.board.update(event)?;
+
+        match *event {
+            Event::Hora {
+                deltas: Some(_), ..
+            }
+            | Event::Ryukyoku {
+                deltas: Some(_), ..
+            } => {
+                self.expected_start = Some((self.board.scores, self.board.kyotaku));
+            }
+            _ => (),
+        };
+
+        Ok(())
+    }
+
+    fn check_naki(
+        &self,
+        actor: u8,
+        target: u8,
+        pai: Pai,
+    ) -> std::result::Result<(), ValidationErrorKind> {
+        if actor == target || self.last_discard != Some((target, pai)) {
+            return Err(ValidationErrorKind::NakiMismatch {
+                actor,
+                target,
+                pai,
+                last: self.last_discard,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[inline]
+fn check_deltas_sum(
+    deltas: [i32; 4],
+    expected: i32,
+) -> std::result::Result<(), ValidationErrorKind> {
+    let sum = deltas.iter().sum();
+    if sum != expected {
+        return Err(ValidationErrorKind::DeltasMismatch {
+            deltas,
+            sum,
+            expected,
+        });
+    }
+    Ok(())
+}
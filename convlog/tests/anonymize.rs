@@ -0,0 +1,67 @@
+use convlog::*;
+
+const RANKED_GAME: &str = include_str!("testdata/ranked_game.json");
+const SANMA: &str = include_str!("testdata/sanma.json");
+
+fn anonymized(data: &str, anonymizer: &Anonymizer) -> serde_json::Value {
+    let mut raw_log: tenhou::RawLog = serde_json::from_str(data).expect("failed to parse log");
+    raw_log.anonymize(anonymizer);
+    serde_json::to_value(&raw_log).expect("failed to serialize log")
+}
+
+#[test]
+fn test_anonymize_raw_log() {
+    let value = anonymized(RANKED_GAME, &Anonymizer::Seat);
+    assert_eq!(
+        value["name"],
+        serde_json::json!(["Aさん", "Bさん", "Cさん", "Dさん"])
+    );
+    for key in &["dan", "rate", "sx"] {
+        assert!(value.get(key).is_none(), "{} is not removed", key);
+    }
+
+    // the absent seat stays absent.
+    let value = anonymized(SANMA, &Anonymizer::Seat);
+    assert_eq!(value["name"][3], "");
+}
+
+#[test]
+#[cfg(feature = "anonymize-hash")]
+fn test_hash_alias() {
+    let salted = |salt: &str| Anonymizer::Hash {
+        salt: salt.to_owned(),
+    };
+
+    let alias = salted("salt").alias(0, "name");
+    assert!(alias.starts_with("Player-"));
+    assert_eq!(alias.len(), "Player-".len() + 8);
+
+    // the same name gets the same alias regardless of the seat, but not with
+    // another salt.
+    assert_eq!(salted("salt").alias(2, "name"), alias);
+    assert_ne!(salted("salt").alias(0, "other"), alias);
+    assert_ne!(salted("pepper").alias(0, "name"), alias);
+    assert_eq!(salted("salt").alias(0, ""), "");
+}
+
+#[test]
+#[cfg(feature = "anonymize-hash")]
+fn test_anonymize_events() {
+    use convlog::mjai::Event;
+
+    let log = tenhou::Log::from_json_str(RANKED_GAME).expect("failed to parse tenhou log");
+    let mut events = tenhou_to_mjai(&log).expect("failed to transform tenhou log");
+    let anonymizer = Anonymizer::Hash {
+        salt: "salt".to_owned(),
+    };
+    anonymizer.anonymize_events(&mut events);
+
+    match &events[0] {
+        Event::StartGame { names, .. } => {
+            for (seat, name) in names.iter().enumerate() {
+                assert_eq!(*name, anonymizer.alias(seat, &log.names[seat]));
+            }
+        }
+        ev => panic!("unexpected first event {:?}", ev),
+    }
+}
@@ -0,0 +1,104 @@
+mod testdata;
+
+use convlog::board::Board;
+use convlog::mjai::Event;
+use convlog::*;
+use testdata::TESTDATA;
+
+#[test]
+fn test_replay_testdata() {
+    TESTDATA.iter().for_each(|data| {
+        let tenhou_log = tenhou::Log::from_json_str(data).expect("failed to parse tenhou log");
+        let events = tenhou_to_mjai(&tenhou_log).expect("failed to transform tenhou log");
+
+        let mut board = Board::new();
+        let mut scores_after_kyoku = None;
+        let mut naki_count = 0;
+
+        for event in &events {
+            board.update(event).expect("failed to replay");
+
+            match *event {
+                Event::StartKyoku { scores, .. } => {
+                    // the scores tracked by the board must agree with the log.
+                    if let Some(expected) = scores_after_kyoku.take() {
+                        assert_eq!(scores, expected);
+                    }
+                    naki_count = 0;
+                }
+                Event::Chi { .. } | Event::Pon { .. } | Event::Daiminkan { .. } => {
+                    naki_count += 1;
+                }
+                Event::Ryukyoku {
+                    reason: Some(tenhou::RyukyokuKind::Exhaustive),
+                    tenpais: Some(tenpais),
+                    ..
+                } => {
+                    assert_eq!(board.tiles_left, 0);
+                    for (player, tenpai) in board.active_players().zip(&tenpais) {
+                        assert_eq!(!player.waits().is_empty(), *tenpai);
+                    }
+                }
+                Event::EndKyoku => {
+                    let called = board
+                        .active_players()
+                        .flat_map(|p| &p.kawa)
+                        .filter(|item| item.called)
+                        .count();
+                    assert_eq!(called, naki_count);
+
+                    for player in board.active_players() {
+                        if player.reach_accepted {
+                            assert!(player.reach_turn.is_some());
+                        }
+                    }
+
+                    scores_after_kyoku = Some(board.scores);
+                }
+                _ => (),
+            }
+        }
+    });
+}
+
+#[test]
+fn test_tenpai_at_ryukyoku() {
+    TESTDATA.iter().for_each(|data| {
+        let tenhou_log = tenhou::Log::from_json_str(data).expect("failed to parse tenhou log");
+        let events = tenhou_to_mjai(&tenhou_log).expect("failed to transform tenhou log");
+
+        let mut board = Board::new();
+        for event in &events {
+            if let Event::Ryukyoku {
+                tenpais: Some(tenpais),
+                ..
+            } = event
+            {
+                for (player, &tenpai) in board.active_players().zip(tenpais) {
+                    assert_eq!(!player.waits().is_empty(), tenpai);
+                }
+            }
+            board.update(event).expect("failed to replay");
+        }
+    });
+}
+
+#[test]
+fn test_board_at() {
+    let data = include_str!("testdata/ryukyoku.json");
+    let tenhou_log = tenhou::Log::from_json_str(data).expect("failed to parse tenhou log");
+    let events = tenhou_to_mjai(&tenhou_log).expect("failed to transform tenhou log");
+
+    let start = events
+        .iter()
+        .position(|ev| matches!(ev, Event::StartKyoku { .. }))
+        .expect("no start_kyoku found");
+    let board = Board::at(&events, start).expect("failed to replay");
+
+    assert_eq!(board.dora_markers.len(), 1);
+    assert_eq!(board.tiles_left, 70);
+    for player in board.active_players() {
+        assert_eq!(player.tehai.len(), 13);
+        assert!(player.kawa.is_empty());
+    }
+}
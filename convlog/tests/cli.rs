@@ -0,0 +1,55 @@
+use std::process::Command;
+
+const BIN: &str = env!("CARGO_BIN_EXE_convlog");
+const TESTDATA_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/testdata");
+
+fn run(args: &[&str]) -> (bool, String, String) {
+    let output = Command::new(BIN)
+        .args(args)
+        .current_dir(TESTDATA_DIR)
+        .output()
+        .expect("failed to run convlog");
+
+    (
+        output.status.success(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+#[test]
+fn test_subcommands() {
+    let (ok, stdout, _) = run(&["to-mjai", "ryukyoku.json"]);
+    assert!(ok);
+    assert!(stdout.lines().next().unwrap().contains("start_game"));
+
+    let (ok, stdout, _) = run(&["validate", "-f", "mjlog", "sanma.xml"]);
+    assert!(ok);
+    assert!(stdout.starts_with("ok"));
+
+    let (ok, stdout, _) = run(&["info", "-f", "majsoul", "majsoul/double_ron.json"]);
+    assert!(ok);
+    assert!(stdout.contains("kyokus:"));
+
+    let (ok, stdout, _) = run(&["filter", "-k", "last:1", "ranked_game.json"]);
+    assert!(ok);
+    let log = convlog::tenhou::Log::from_json_str(&stdout).expect("invalid filter output");
+    assert_eq!(log.kyokus.len(), 1);
+
+    let (ok, stdout, _) = run(&["anonymize", "--salt", "x", "ranked_game.json"]);
+    assert!(ok);
+    let log = convlog::tenhou::Log::from_json_str(&stdout).expect("invalid anonymize output");
+    assert!(log.names.iter().all(|name| name.starts_with("Player-")));
+    assert!(!stdout.contains("rate"));
+}
+
+#[test]
+fn test_errors() {
+    let (ok, _, stderr) = run(&["info", "no_such_file.json"]);
+    assert!(!ok);
+    assert!(stderr.contains("no_such_file.json"));
+
+    let (ok, _, stderr) = run(&["filter", "-k", "E1,X1", "ryukyoku.json"]);
+    assert!(!ok);
+    assert!(stderr.contains("position 3"));
+}
@@ -0,0 +1,72 @@
+use convlog::KyokuFilter;
+
+/// (kyoku, honba) of a hypothetical hanchan.
+const KYOKUS: &[(u8, u8)] = &[
+    (0, 0),
+    (1, 0),
+    (1, 1),
+    (2, 0),
+    (3, 0),
+    (4, 0),
+    (5, 0),
+    (5, 1),
+    (5, 2),
+    (6, 0),
+    (7, 0),
+];
+
+fn select(filter: &str) -> Vec<(u8, u8)> {
+    let filter: KyokuFilter = filter.parse().expect("failed to parse kyoku filter");
+    let mut kyokus = KYOKUS.to_vec();
+    filter.retain(&mut kyokus, |&k| k);
+    kyokus
+}
+
+#[test]
+fn test_exact() {
+    assert_eq!(select("E1,E4,S3.1"), [(0, 0), (3, 0)]);
+    assert_eq!(select("e2.1, S2.2"), [(1, 1), (5, 2)]);
+    assert_eq!(select(""), KYOKUS);
+}
+
+#[test]
+fn test_range_and_wildcard() {
+    assert_eq!(select("E4-S2"), [(3, 0), (4, 0), (5, 0), (5, 1), (5, 2)]);
+    assert_eq!(select("E4-S2.0"), [(3, 0), (4, 0), (5, 0)]);
+    assert_eq!(select("E2.*"), [(1, 0), (1, 1)]);
+    assert_eq!(select("S2.1-S2.*"), [(5, 1), (5, 2)]);
+}
+
+#[test]
+fn test_exclusion_and_last() {
+    assert_eq!(select("!S2.*,!E1-E4").len(), 3);
+    assert_eq!(select("last:3"), [(5, 2), (6, 0), (7, 0)]);
+    assert_eq!(select("last:3,!S4"), [(5, 2), (6, 0)]);
+    assert_eq!(select("E1,last:1"), [(0, 0), (7, 0)]);
+}
+
+#[test]
+fn test_index() {
+    assert_eq!(select("#2"), [(1, 1)]);
+    assert_eq!(select("#0,#8"), [(0, 0), (5, 2)]);
+    assert_eq!(select("E2.*,!#1"), [(1, 1)]);
+    assert_eq!(select("#20"), []);
+
+    let mut kyokus = KYOKUS.to_vec();
+    KyokuFilter::nth(3).retain(&mut kyokus, |&k| k);
+    assert_eq!(kyokus, [(2, 0)]);
+}
+
+#[test]
+fn test_parse_error_position() {
+    let pos = |s: &str| s.parse::<KyokuFilter>().unwrap_err().position();
+
+    assert_eq!(pos("E1,X2"), 3);
+    assert_eq!(pos("E1, S5"), 5);
+    assert_eq!(pos("E1,!S2.x"), 7);
+    assert_eq!(pos("E1,,E2"), 3);
+    assert_eq!(pos("last:x"), 5);
+    assert_eq!(pos("S1-E3"), 0);
+    assert_eq!(pos("E1-"), 3);
+    assert_eq!(pos("E1,#x"), 4);
+}
@@ -0,0 +1,82 @@
+use convlog::{TenhouLogRef, TenhouLogRefError};
+
+const ID: &str = "2019050417gm-0029-0000-4f2a8622";
+
+fn parse(s: &str) -> TenhouLogRef {
+    s.parse()
+        .unwrap_or_else(|err| panic!("failed to parse {:?}: {}", s, err))
+}
+
+#[test]
+fn test_parse() {
+    let cases = &[
+        (ID.to_owned(), None, None),
+        (
+            format!("https://tenhou.net/0/?log={}&tw=2&ts=3", ID),
+            Some(2),
+            Some(3),
+        ),
+        (
+            format!("http://tenhou.net/3/?log={}&tw=1", ID),
+            Some(1),
+            None,
+        ),
+        (format!("https://tenhou.net/5/?log={}", ID), None, None),
+        (format!("tenhou.net/6/?log={}&ts=0", ID), None, Some(0)),
+        (
+            format!("https://tenhou.net/0/?log={}#tw=3", ID),
+            Some(3),
+            None,
+        ),
+        (
+            format!("  https://tenhou.net/6?tw=0&log={}&foo  ", ID),
+            Some(0),
+            None,
+        ),
+    ];
+
+    for (s, actor, kyoku_index) in cases {
+        let log_ref = parse(s);
+        assert_eq!(log_ref.id, ID, "{}", s);
+        assert_eq!(log_ref.actor, *actor, "{}", s);
+        assert_eq!(log_ref.kyoku_index, *kyoku_index, "{}", s);
+        assert_eq!(log_ref.kyoku_filter().is_some(), kyoku_index.is_some());
+    }
+
+    let obfuscated = "2021010100gm-00a9-0000-x1a2b3c4d5e6f";
+    assert_eq!(parse(obfuscated).id, obfuscated);
+
+    let log_ref = parse(&format!("https://tenhou.net/3/?log={}&tw=2&ts=3", ID));
+    assert_eq!(parse(&log_ref.to_string()), log_ref);
+}
+
+#[test]
+fn test_parse_error() {
+    let err = |s: &str| s.parse::<TenhouLogRef>().unwrap_err();
+
+    assert!(matches!(err(""), TenhouLogRefError::Empty));
+    assert!(matches!(
+        err("2019050417-0029-0000-4f2a8622"),
+        TenhouLogRefError::InvalidId(_)
+    ));
+    assert!(matches!(
+        err("https://tenhou.net/4/?log=x"),
+        TenhouLogRefError::UnsupportedUrl(_)
+    ));
+    assert!(matches!(
+        err("https://example.com/0/?log=x"),
+        TenhouLogRefError::UnsupportedUrl(_)
+    ));
+    assert!(matches!(
+        err("https://tenhou.net/0/?tw=1"),
+        TenhouLogRefError::MissingId(_)
+    ));
+    assert!(matches!(
+        err(&format!("https://tenhou.net/0/?log={}&tw=4", ID)),
+        TenhouLogRefError::InvalidParam { name: "tw", .. }
+    ));
+    assert!(matches!(
+        err(&format!("https://tenhou.net/0/?log={}&ts=-1", ID)),
+        TenhouLogRefError::InvalidParam { name: "ts", .. }
+    ));
+}
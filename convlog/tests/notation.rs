@@ -0,0 +1,132 @@
+mod testdata;
+
+use convlog::board::{Board, Fuuro};
+use convlog::notation::{Pais, ParseError};
+use convlog::*;
+use testdata::TESTDATA;
+
+const TEHAI: [Pai; 13] = [
+    Pai::Man1,
+    Pai::Man2,
+    Pai::Man3,
+    Pai::Pin4,
+    Pai::AkaPin5,
+    Pai::Pin6,
+    Pai::Sou7,
+    Pai::Sou8,
+    Pai::Sou9,
+    Pai::East,
+    Pai::East,
+    Pai::South,
+    Pai::South,
+];
+
+#[test]
+fn test_parse_pais() {
+    let parsed: Pais = "123m406p789s1122z".parse().unwrap();
+    assert_eq!(parsed.0, TEHAI);
+
+    let parsed: Pais = "5m0s?7z".parse().unwrap();
+    assert_eq!(parsed.0, [Pai::Man5, Pai::AkaSou5, Pai::Unknown, Pai::Chun],);
+
+    let parsed: Pais = "".parse().unwrap();
+    assert!(parsed.is_empty());
+}
+
+#[test]
+fn test_parse_pais_error() {
+    assert!(matches!(
+        "123".parse::<Pais>(),
+        Err(ParseError::MissingSuit { pos: 0 })
+    ));
+    assert!(matches!(
+        "12m3x".parse::<Pais>(),
+        Err(ParseError::InvalidChar { pos: 4, found: 'x' })
+    ));
+    assert!(matches!(
+        "18z".parse::<Pais>(),
+        Err(ParseError::InvalidHonor { pos: 1, number: 8 })
+    ));
+    assert!(matches!(
+        "0z".parse::<Pais>(),
+        Err(ParseError::InvalidHonor { pos: 0, number: 0 })
+    ));
+}
+
+#[test]
+fn test_display_pais() {
+    let formatted = Pais(TEHAI.to_vec()).to_string();
+    assert_eq!(formatted, "123m406p789s1122z");
+
+    // the order is preserved.
+    let formatted = Pais(vec![
+        Pai::Man1,
+        Pai::Man2,
+        Pai::Pin1,
+        Pai::Man3,
+        Pai::Unknown,
+        Pai::Chun,
+    ])
+    .to_string();
+    assert_eq!(formatted, "12m1p3m?7z");
+}
+
+#[test]
+fn test_meld() {
+    for s in &[
+        "[3m]45m@3",
+        "[4m]35m@3",
+        "[5p]05p@1",
+        "[1z]111z@0",
+        "[5s]55s@2+0s",
+        "1111z",
+    ] {
+        let fuuro: Fuuro = s.parse().expect("failed to parse meld");
+        assert_eq!(fuuro.to_string(), *s);
+    }
+
+    assert!(matches!(
+        "[5p]05p@1".parse(),
+        Ok(Fuuro::Pon { target: 1, .. })
+    ));
+    assert!(matches!(
+        "[4m]35m@3".parse(),
+        Ok(Fuuro::Chi { target: 3, .. })
+    ));
+    assert!(matches!("1111z".parse(), Ok(Fuuro::Ankan { .. })));
+
+    for s in &[
+        "[3m]46m@3",
+        "[1z]23z@3",
+        "[5p]05p@4",
+        "[5p]05p",
+        "111z",
+        "[5p5p]5p@1",
+    ] {
+        assert!(s.parse::<Fuuro>().is_err(), "{} should be invalid", s);
+    }
+}
+
+#[test]
+fn test_round_trip_testdata() {
+    TESTDATA.iter().for_each(|data| {
+        let tenhou_log = tenhou::Log::from_json_str(data).expect("failed to parse tenhou log");
+        let events = tenhou_to_mjai(&tenhou_log).expect("failed to transform tenhou log");
+
+        let mut board = Board::new();
+        for event in &events {
+            board.update(event).expect("failed to replay");
+
+            for player in board.active_players() {
+                let tehai = Pais::from(player.tehai.as_slice());
+                assert_eq!(tehai.to_string().parse::<Pais>().unwrap(), tehai);
+
+                for fuuro in &player.fuuros {
+                    let formatted = fuuro.to_string();
+                    let parsed: Fuuro = formatted.parse().expect("failed to parse meld");
+                    assert_eq!(parsed.to_string(), formatted);
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,109 @@
+use convlog::*;
+
+fn pai(s: &str) -> Pai {
+    s.parse().expect("failed to parse mjai pai")
+}
+
+#[test]
+fn test_suit_and_number() {
+    assert_eq!(pai("3m").suit(), Some(Suit::Man));
+    assert_eq!(pai("5pr").suit(), Some(Suit::Pin));
+    assert_eq!(pai("9s").suit(), Some(Suit::Sou));
+    assert_eq!(pai("C").suit(), Some(Suit::Ji));
+    assert_eq!(Pai::Unknown.suit(), None);
+
+    assert_eq!(pai("3m").number(), Some(3));
+    assert_eq!(pai("5sr").number(), Some(5));
+    assert_eq!(pai("N").number(), Some(4));
+    assert_eq!(Pai::Unknown.number(), None);
+}
+
+#[test]
+fn test_kinds() {
+    for s in &["1m", "9m", "1p", "9s", "E", "P", "C"] {
+        assert!(pai(s).is_yaochuu(), "{} should be yaochuu", s);
+    }
+    for s in &["2m", "8p", "5sr", "?"] {
+        assert!(!pai(s).is_yaochuu(), "{} should not be yaochuu", s);
+    }
+
+    assert!(pai("9p").is_terminal());
+    assert!(!pai("S").is_terminal());
+    assert!(pai("S").is_honor());
+    assert!(!pai("1s").is_honor());
+
+    assert!(pai("5mr").is_aka());
+    assert_eq!(pai("5mr").deaka(), pai("5m"));
+    assert_eq!(pai("5pr").deaka(), pai("5p"));
+    assert_eq!(pai("7s").deaka(), pai("7s"));
+}
+
+#[test]
+fn test_sort_key() {
+    let mut pais: Vec<_> = ["?", "C", "6m", "5mr", "1p", "5m", "E", "9s", "4m"]
+        .iter()
+        .map(|s| pai(s))
+        .collect();
+    pais.sort_by_key(|p| p.sort_key());
+    let sorted: Vec<_> = pais.iter().map(|p| p.to_string()).collect();
+    assert_eq!(sorted, ["4m", "5m", "5mr", "6m", "1p", "9s", "E", "C", "?"]);
+}
+
+#[test]
+fn test_indicated_dora() {
+    let cases = &[
+        ("1m", "2m"),
+        ("5pr", "6p"),
+        ("9m", "1m"),
+        ("9p", "1p"),
+        ("9s", "1s"),
+        ("E", "S"),
+        ("N", "E"),
+        ("P", "F"),
+        ("C", "P"),
+    ];
+    for &(indicator, dora) in cases {
+        assert_eq!(pai(indicator).indicated_dora(), pai(dora), "{}", indicator);
+    }
+    assert_eq!(Pai::Unknown.indicated_dora(), Pai::Unknown);
+}
+
+#[test]
+fn test_id34() {
+    for id in 0..34 {
+        let p = Pai::from_id34(id).unwrap();
+        assert!(!p.is_aka());
+        assert_eq!(p.to_id34(), Some(id));
+    }
+    assert_eq!(Pai::from_id34(34), None);
+
+    assert_eq!(pai("1m").to_id34(), Some(0));
+    assert_eq!(pai("5sr").to_id34(), pai("5s").to_id34());
+    assert_eq!(pai("C").to_id34(), Some(33));
+    assert_eq!(Pai::Unknown.to_id34(), None);
+}
+
+#[test]
+fn test_tile_counts() {
+    let pais: Vec<_> = ["5m", "5mr", "5m", "E", "?"]
+        .iter()
+        .map(|s| pai(s))
+        .collect();
+    let mut counts = TileCounts::from(pais.as_slice());
+
+    assert_eq!(counts.get(pai("5m")), 3);
+    assert_eq!(counts.get(pai("5mr")), 3);
+    assert_eq!(counts.get(pai("E")), 1);
+    assert_eq!(counts.total(), 4);
+    assert_eq!(
+        counts.iter().collect::<Vec<_>>(),
+        [(pai("5m"), 3), (pai("E"), 1)],
+    );
+
+    assert!(counts.remove(pai("E")));
+    assert!(!counts.remove(pai("E")));
+    assert!(!counts.add(Pai::Unknown));
+    counts[0] += 2;
+    assert_eq!(counts.get(pai("1m")), 2);
+    assert_eq!(counts.total(), 5);
+}
@@ -12,3 +12,258 @@ fn test_parse_and_convert() {
         assert!(!mjai_log.is_empty());
     });
 }
+
+#[test]
+fn test_hora_detail() {
+    let data = include_str!("testdata/rinshan.json");
+    let tenhou_log = tenhou::Log::from_json_str(data).expect("failed to parse tenhou log");
+
+    let details = tenhou_log
+        .kyokus
+        .iter()
+        .find_map(|kyoku| match &kyoku.end_status {
+            tenhou::kyoku::EndStatus::Hora { details } => Some(details),
+            _ => None,
+        })
+        .expect("no hora found");
+    let detail = &details[0];
+
+    assert_eq!(detail.pao, None);
+    assert_eq!(detail.fu, 0);
+    assert_eq!(detail.han, 5);
+    assert_eq!(detail.limit, Some(tenhou::Limit::Mangan));
+    assert_eq!(detail.points, "2000-4000点");
+    assert_eq!(
+        detail.yaku[0],
+        tenhou::Yaku {
+            name: "立直".to_owned(),
+            han: 1,
+        }
+    );
+
+    let mjai_log = tenhou_to_mjai(&tenhou_log).expect("failed to transform tenhou log");
+    let hora = mjai_log
+        .iter()
+        .find(|ev| matches!(ev, mjai::Event::Hora { .. }))
+        .expect("no hora found");
+    if let mjai::Event::Hora {
+        fan, limit, yakus, ..
+    } = hora
+    {
+        assert_eq!(*fan, Some(5));
+        assert_eq!(*limit, Some(tenhou::Limit::Mangan));
+        assert_eq!(yakus.as_ref().map(Vec::len), Some(5));
+    }
+}
+
+#[test]
+fn test_ryukyoku_kind() {
+    let cases = [
+        (
+            include_str!("testdata/ryukyoku.json"),
+            tenhou::RyukyokuKind::Exhaustive,
+            [true, true, false, false],
+        ),
+        (
+            include_str!("testdata/kyushukyuhai.json"),
+            tenhou::RyukyokuKind::KyuushuKyuuhai,
+            [false; 4],
+        ),
+        (
+            include_str!("testdata/four_reach.json"),
+            tenhou::RyukyokuKind::SuuchaRiichi,
+            [false; 4],
+        ),
+    ];
+
+    for (data, expected_kind, expected_tenpai) in cases {
+        let tenhou_log = tenhou::Log::from_json_str(data).expect("failed to parse tenhou log");
+        let (kind, tenpai) = tenhou_log
+            .kyokus
+            .iter()
+            .find_map(|kyoku| match kyoku.end_status {
+                tenhou::kyoku::EndStatus::Ryukyoku { kind, tenpai, .. } => Some((kind, tenpai)),
+                _ => None,
+            })
+            .expect("no ryukyoku found");
+        assert_eq!(kind, expected_kind);
+        assert_eq!(tenpai, expected_tenpai);
+
+        let mjai_log = tenhou_to_mjai(&tenhou_log).expect("failed to transform tenhou log");
+        let ryukyoku = mjai_log
+            .iter()
+            .find(|ev| matches!(ev, mjai::Event::Ryukyoku { .. }))
+            .expect("no ryukyoku found");
+        if let mjai::Event::Ryukyoku {
+            reason, tenpais, ..
+        } = ryukyoku
+        {
+            assert_eq!(*reason, Some(expected_kind));
+            assert_eq!(
+                tenpais.is_some(),
+                expected_kind == tenhou::RyukyokuKind::Exhaustive
+            );
+        }
+    }
+}
+
+#[test]
+fn test_convert_iter() {
+    TESTDATA.iter().for_each(|data| {
+        let tenhou_log = tenhou::Log::from_json_str(data).expect("failed to parse tenhou log");
+        let expected = tenhou_to_mjai(&tenhou_log).expect("failed to transform tenhou log");
+
+        let kyokus = tenhou_to_mjai_iter(&tenhou_log);
+        assert_eq!(kyokus.len(), tenhou_log.kyokus.len());
+
+        let mut events = vec![kyokus.start_game()];
+        for kyoku_events in kyokus {
+            events.extend(kyoku_events.expect("failed to transform kyoku"));
+        }
+        events.push(mjai::Event::EndGame);
+        assert_eq!(events, expected);
+    });
+}
+
+#[test]
+fn test_convert_iter_broken_kyoku() {
+    let data = include_str!("testdata/ranked_game.json");
+    let mut tenhou_log = tenhou::Log::from_json_str(data).expect("failed to parse tenhou log");
+    tenhou_log.kyokus[1].dora_indicators.clear();
+
+    assert!(tenhou_to_mjai(&tenhou_log).is_err());
+
+    let results: Vec<_> = tenhou_to_mjai_iter(&tenhou_log).collect();
+    assert_eq!(results.len(), tenhou_log.kyokus.len());
+    for (idx, result) in results.iter().enumerate() {
+        assert_eq!(result.is_err(), idx == 1);
+    }
+}
+
+#[test]
+fn test_convert_lenient() {
+    let data = include_str!("testdata/ranked_game.json");
+    let mut tenhou_log = tenhou::Log::from_json_str(data).expect("failed to parse tenhou log");
+
+    let (events, diagnostics) = tenhou_to_mjai_lenient(&tenhou_log);
+    assert!(diagnostics.is_empty());
+    assert_eq!(
+        events,
+        tenhou_to_mjai(&tenhou_log).expect("failed to transform tenhou log")
+    );
+
+    // break the 2nd discard of actor 1 in the 1st kyoku.
+    tenhou_log.kyokus[0].action_tables[1].discards[1] = tenhou::ActionItem::Naki("x".to_owned());
+    tenhou_log.kyokus[2].dora_indicators.clear();
+
+    let (events, diagnostics) = tenhou_to_mjai_lenient(&tenhou_log);
+    assert_eq!(diagnostics.len(), 2);
+
+    let diagnostic = &diagnostics[0];
+    assert_eq!(diagnostic.kyoku_index, 0);
+    assert_eq!(diagnostic.actor, Some(1));
+    assert_eq!(
+        diagnostic.position,
+        Some(ActionPosition {
+            sequence: ActionSequence::Discards,
+            index: 1,
+        })
+    );
+    assert!(matches!(diagnostic.error, ConvertError::InvalidNaki(_)));
+    assert_eq!(diagnostic.resolution, Resolution::SkippedKyoku);
+
+    assert_eq!(diagnostics[1].kyoku_index, 2);
+    assert_eq!(diagnostics[1].resolution, Resolution::SkippedKyoku);
+
+    let kyoku_count = events
+        .iter()
+        .filter(|ev| matches!(ev, mjai::Event::StartKyoku { .. }))
+        .count();
+    assert_eq!(kyoku_count, tenhou_log.kyokus.len() - 2);
+}
+
+#[test]
+fn test_convert_lenient_patch_kan_dora() {
+    let data = include_str!("testdata/suukantsu_0.json");
+    let mut tenhou_log = tenhou::Log::from_json_str(data).expect("failed to parse tenhou log");
+    let kyoku = tenhou_log
+        .kyokus
+        .iter_mut()
+        .find(|k| k.dora_indicators.len() > 1)
+        .expect("no kan dora found");
+    kyoku.dora_indicators.truncate(1);
+
+    assert!(tenhou_to_mjai(&tenhou_log).is_err());
+
+    let (events, diagnostics) = tenhou_to_mjai_lenient(&tenhou_log);
+    assert!(!diagnostics.is_empty());
+    assert!(diagnostics
+        .iter()
+        .all(|d| d.resolution == Resolution::Patched));
+    assert!(events
+        .iter()
+        .any(|ev| matches!(ev, mjai::Event::StartKyoku { .. })));
+}
+
+#[test]
+fn test_player_info() {
+    let data = include_str!("testdata/suukantsu_1.json");
+    let tenhou_log = tenhou::Log::from_json_str(data).expect("failed to parse tenhou log");
+
+    assert_eq!(
+        tenhou_log.players[2],
+        tenhou::PlayerInfo {
+            dan: Some("６級".to_owned()),
+            rate: Some(1531.99),
+            sex: Some(tenhou::Sex::Female),
+        }
+    );
+    assert_eq!(tenhou_log.lobby.lobby, Some(0));
+    assert_eq!(tenhou_log.lobby.rating_class.as_deref(), Some("PF4"));
+
+    // kept when converted back.
+    let raw_log = tenhou::RawLog::from(tenhou_log.clone());
+    let back = tenhou::Log::from(raw_log);
+    assert_eq!(back.players, tenhou_log.players);
+    assert_eq!(back.lobby, tenhou_log.lobby);
+
+    // logs without profiles.
+    let data = include_str!("testdata/rinshan.json");
+    let tenhou_log = tenhou::Log::from_json_str(data).expect("failed to parse tenhou log");
+    assert_eq!(tenhou_log.players[0], tenhou::PlayerInfo::default());
+    assert_eq!(tenhou_log.lobby, tenhou::LobbyInfo::default());
+}
+
+#[test]
+fn test_rule_set() {
+    let rule = tenhou::RuleSet::from_disp("三鳳南喰赤速");
+    assert_eq!(rule.tier, Some(tenhou::Tier::Houou));
+    assert!(rule.is_sanma);
+    assert_eq!(rule.game_length, tenhou::GameLength::Hanchan);
+    assert!(rule.has_kuitan);
+    assert!(rule.has_aka());
+    assert!(rule.is_fast);
+    assert_eq!(rule.num_players(), 3);
+    assert_eq!(rule.to_string(), "三鳳南喰赤速");
+
+    let rule = tenhou::RuleSet::from_disp("般東");
+    assert_eq!(rule.tier, Some(tenhou::Tier::Ippan));
+    assert_eq!(rule.game_length, tenhou::GameLength::Tonpuu);
+    assert!(!rule.has_kuitan);
+    assert!(!rule.has_aka());
+    assert!(!rule.is_fast);
+    assert_eq!(rule.num_players(), 4);
+
+    // kept when converted back.
+    TESTDATA.iter().for_each(|data| {
+        let tenhou_log = tenhou::Log::from_json_str(data).expect("failed to parse tenhou log");
+        let raw_log = tenhou::RawLog::from(tenhou_log.clone());
+        let back = tenhou::Log::from(raw_log);
+        assert_eq!(back.rule, tenhou_log.rule);
+    });
+
+    // "aka" takes precedence over the disp, which lacks "赤" here.
+    let data = include_str!("testdata/four_reach.json");
+    let tenhou_log = tenhou::Log::from_json_str(data).expect("failed to parse tenhou log");
+    assert_eq!(tenhou_log.rule.aka_counts, [1; 3]);
+}
@@ -0,0 +1,154 @@
+use convlog::*;
+
+/// Records written from the tenhou.net/6 JSON of the same games.
+const TESTDATA: &[(&str, &str)] = &[
+    (
+        include_str!("testdata/majsoul/chankan.json"),
+        include_str!("testdata/chankan.json"),
+    ),
+    (
+        include_str!("testdata/majsoul/complex_nakis_0.json"),
+        include_str!("testdata/complex_nakis_0.json"),
+    ),
+    (
+        include_str!("testdata/majsoul/complex_nakis_1.json"),
+        include_str!("testdata/complex_nakis_1.json"),
+    ),
+    (
+        include_str!("testdata/majsoul/double_ron.json"),
+        include_str!("testdata/double_ron.json"),
+    ),
+    (
+        include_str!("testdata/majsoul/four_reach.json"),
+        include_str!("testdata/four_reach.json"),
+    ),
+    (
+        include_str!("testdata/majsoul/kyushukyuhai.json"),
+        include_str!("testdata/kyushukyuhai.json"),
+    ),
+    (
+        include_str!("testdata/majsoul/ranked_game.json"),
+        include_str!("testdata/ranked_game.json"),
+    ),
+    (
+        include_str!("testdata/majsoul/rinshan.json"),
+        include_str!("testdata/rinshan.json"),
+    ),
+    (
+        include_str!("testdata/majsoul/ryukyoku.json"),
+        include_str!("testdata/ryukyoku.json"),
+    ),
+    (
+        include_str!("testdata/majsoul/sanma.json"),
+        include_str!("testdata/sanma.json"),
+    ),
+    (
+        include_str!("testdata/majsoul/suukantsu_0.json"),
+        include_str!("testdata/suukantsu_0.json"),
+    ),
+    (
+        include_str!("testdata/majsoul/suukantsu_1.json"),
+        include_str!("testdata/suukantsu_1.json"),
+    ),
+];
+
+#[test]
+fn test_parse_majsoul() {
+    TESTDATA.iter().for_each(|(majsoul_json, tenhou_json)| {
+        let log = majsoul::parse_str(majsoul_json).expect("failed to parse majsoul record");
+        let tenhou_log =
+            tenhou::Log::from_json_str(tenhou_json).expect("failed to parse tenhou log");
+
+        assert_eq!(log.names, tenhou_log.names);
+        assert_eq!(log.rule.has_aka(), tenhou_log.rule.has_aka());
+        assert_eq!(log.rule.is_sanma, tenhou_log.rule.is_sanma);
+
+        let mjai_log = majsoul::parse_str_to_mjai(majsoul_json).expect("failed to transform");
+        let mut expected = tenhou_to_mjai(&tenhou_log).expect("failed to transform tenhou log");
+
+        // yaku are not decoded from majsoul records.
+        expected.iter_mut().for_each(|ev| {
+            if let mjai::Event::Hora {
+                pao,
+                fu,
+                fan,
+                limit,
+                yakus,
+                ..
+            } = ev
+            {
+                *pao = None;
+                *fu = None;
+                *fan = None;
+                *limit = None;
+                *yakus = None;
+            }
+        });
+
+        assert_eq!(mjai_log, expected);
+    });
+}
+
+#[test]
+fn test_ron_on_ankan() {
+    // kokushi robs the ankan of seat 2, after a discard of seat 1.
+    let record = r#"{
+        "head": {"config": {"category": 2, "mode": {"mode": 2, "detail_rule": {}}}},
+        "actions": [
+            {"name": "RecordNewRound", "data": {"chang": 0, "ju": 0, "ben": 0, "liqibang": 0,
+                "scores": [25000, 25000, 25000, 25000], "doras": ["5p"],
+                "tiles0": ["2m", "3m", "4m", "5m", "6m", "7m", "8m", "2p", "3p", "4p", "5p", "6p", "7p", "8p"],
+                "tiles1": ["2s", "3s", "4s", "5s", "6s", "7s", "8s", "2p", "3p", "4p", "5p", "6p", "7p"],
+                "tiles2": ["1m", "1m", "1m", "2s", "3s", "4s", "5s", "6s", "7s", "8s", "6p", "7p", "8p"],
+                "tiles3": ["9m", "1p", "9p", "1s", "9s", "1z", "2z", "3z", "4z", "5z", "6z", "7z", "7z"]}},
+            {"name": "RecordDiscardTile", "data": {"seat": 0, "tile": "8p"}},
+            {"name": "RecordDealTile", "data": {"seat": 1, "tile": "8s"}},
+            {"name": "RecordDiscardTile", "data": {"seat": 1, "tile": "8s", "moqie": true}},
+            {"name": "RecordDealTile", "data": {"seat": 2, "tile": "1m"}},
+            {"name": "RecordAnGangAddGang", "data": {"seat": 2, "type": 3, "tiles": "1m"}},
+            {"name": "RecordHule", "data": {"hules": [{"seat": 3, "zimo": false}],
+                "delta_scores": [0, 0, -32000, 32000]}}
+        ]
+    }"#;
+
+    let log = majsoul::parse_str(record).expect("failed to parse majsoul record");
+    match &log.kyokus[0].end_status {
+        tenhou::kyoku::EndStatus::Hora { details } => {
+            assert_eq!((details[0].who, details[0].target), (3, 2));
+        }
+        status => panic!("unexpected end status {:?}", status),
+    }
+}
+
+#[test]
+fn test_zero_fields_left_out() {
+    // seat 0, east 1 and chi are all zero, which protobuf leaves out in JSON.
+    let record = r#"{
+        "head": {"config": {"category": 2, "mode": {"mode": 1, "detail_rule": {}}},
+            "accounts": [{"nickname": "A"}, {"seat": 1, "nickname": "B"},
+                {"seat": 2, "nickname": "C"}, {"seat": 3, "nickname": "D"}]},
+        "actions": [
+            {"name": "RecordNewRound", "data": {
+                "scores": [25000, 25000, 25000, 25000], "doras": ["5p"],
+                "tiles0": ["3m", "3m", "4m", "5m", "6m", "7m", "8m", "2p", "3p", "4p", "5p", "6p", "7p", "8p"],
+                "tiles1": ["4m", "5m", "4s", "5s", "6s", "7s", "8s", "2p", "3p", "4p", "5p", "6p", "7p"],
+                "tiles2": ["1m", "1m", "1m", "2s", "3s", "4s", "5s", "6s", "7s", "8s", "6p", "7p", "8p"],
+                "tiles3": ["9m", "1p", "9p", "1s", "9s", "1z", "2z", "3z", "4z", "5z", "6z", "7z", "7z"]}},
+            {"name": "RecordDiscardTile", "data": {"tile": "3m"}},
+            {"name": "RecordChiPengGang", "data": {"seat": 1, "tiles": ["3m", "4m", "5m"], "froms": [0, 1, 1]}},
+            {"name": "RecordDiscardTile", "data": {"seat": 1, "tile": "4s"}},
+            {"name": "RecordLiuJu", "data": {"type": 1}}
+        ]
+    }"#;
+
+    let log = majsoul::parse_str(record).expect("failed to parse majsoul record");
+    assert_eq!(log.names[0], "A");
+    assert_eq!(log.rule.game_length, tenhou::GameLength::Tonpuu);
+    assert_eq!(log.kyokus[0].meta.kyoku_num, 0);
+
+    let table = &log.kyokus[0].action_tables[1];
+    assert!(matches!(
+        &table.takes[0],
+        tenhou::ActionItem::Naki(naki) if naki == "c131415"
+    ));
+}
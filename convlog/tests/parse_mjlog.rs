@@ -0,0 +1,207 @@
+use convlog::*;
+
+use std::io::prelude::*;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+/// mjlogs written from the tenhou.net/6 JSON of the same games, which are not
+/// the ones Tenhou serves, so the meld codes are also checked by hand in
+/// `test_mjlog_melds`.
+const TESTDATA: &[(&str, &str)] = &[
+    (
+        include_str!("testdata/chankan.xml"),
+        include_str!("testdata/chankan.json"),
+    ),
+    (
+        include_str!("testdata/complex_nakis_0.xml"),
+        include_str!("testdata/complex_nakis_0.json"),
+    ),
+    (
+        include_str!("testdata/complex_nakis_1.xml"),
+        include_str!("testdata/complex_nakis_1.json"),
+    ),
+    (
+        include_str!("testdata/four_reach.xml"),
+        include_str!("testdata/four_reach.json"),
+    ),
+    (
+        include_str!("testdata/kyushukyuhai.xml"),
+        include_str!("testdata/kyushukyuhai.json"),
+    ),
+    (
+        include_str!("testdata/double_ron.xml"),
+        include_str!("testdata/double_ron.json"),
+    ),
+    (
+        include_str!("testdata/ranked_game.xml"),
+        include_str!("testdata/ranked_game.json"),
+    ),
+    (
+        include_str!("testdata/rinshan.xml"),
+        include_str!("testdata/rinshan.json"),
+    ),
+    (
+        include_str!("testdata/ryukyoku.xml"),
+        include_str!("testdata/ryukyoku.json"),
+    ),
+    (
+        include_str!("testdata/sanma.xml"),
+        include_str!("testdata/sanma.json"),
+    ),
+    (
+        include_str!("testdata/suukantsu_0.xml"),
+        include_str!("testdata/suukantsu_0.json"),
+    ),
+    (
+        include_str!("testdata/suukantsu_1.xml"),
+        include_str!("testdata/suukantsu_1.json"),
+    ),
+];
+
+#[test]
+fn test_parse_mjlog() {
+    TESTDATA.iter().for_each(|(xml, json)| {
+        let mjlog = mjlog::parse_str(xml).expect("failed to parse mjlog");
+        let tenhou_log = tenhou::Log::from_json_str(json).expect("failed to parse tenhou log");
+
+        assert_eq!(mjlog.names, tenhou_log.names);
+        assert_eq!(mjlog.rule, tenhou_log.rule);
+
+        let mjai_log = tenhou_to_mjai(&mjlog).expect("failed to transform mjlog");
+        let expected = tenhou_to_mjai(&tenhou_log).expect("failed to transform tenhou log");
+
+        assert_eq!(mjai_log, expected);
+    });
+}
+
+#[test]
+fn test_parse_gzipped_mjlog() {
+    let (xml, _) = TESTDATA[0];
+
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(xml.as_bytes()).unwrap();
+    let gzipped = encoder.finish().unwrap();
+
+    let plain = mjlog::parse_str(xml).expect("failed to parse mjlog");
+    let decompressed = mjlog::parse_bytes(&gzipped).expect("failed to parse gzipped mjlog");
+
+    assert_eq!(
+        tenhou_to_mjai(&plain).unwrap(),
+        tenhou_to_mjai(&decompressed).unwrap()
+    );
+}
+
+#[test]
+fn test_mjlog_player_info() {
+    let mjlog =
+        mjlog::parse_str(include_str!("testdata/sanma.xml")).expect("failed to parse mjlog");
+
+    assert_eq!(mjlog.players[0].dan.as_deref(), Some("新人"));
+    assert_eq!(mjlog.players[0].rate, Some(1500.0));
+    assert_eq!(mjlog.players[0].sex, Some(tenhou::Sex::Male));
+    // the absent seat.
+    assert_eq!(mjlog.players[3], tenhou::PlayerInfo::default());
+    assert_eq!(mjlog.lobby.lobby, Some(0));
+}
+
+#[test]
+fn test_mjlog_rule() {
+    let mjlog =
+        mjlog::parse_str(include_str!("testdata/chankan.xml")).expect("failed to parse mjlog");
+    assert_eq!(mjlog.rule.tier, Some(tenhou::Tier::Joukyuu));
+    assert_eq!(mjlog.rule.game_length, tenhou::GameLength::Tonpuu);
+    assert!(mjlog.rule.is_fast);
+    assert_eq!(mjlog.rule.to_string(), "上東喰赤速");
+
+    let mjlog = mjlog::parse_str(include_str!("testdata/complex_nakis_1.xml"))
+        .expect("failed to parse mjlog");
+    assert_eq!(mjlog.rule.tier, Some(tenhou::Tier::Houou));
+    assert_eq!(mjlog.rule.to_string(), "鳳南喰赤");
+}
+
+/// The naki strings among `items`.
+fn nakis(items: &[tenhou::ActionItem]) -> Vec<&str> {
+    items
+        .iter()
+        .filter_map(|item| match item {
+            tenhou::ActionItem::Naki(naki) => Some(naki.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_mjlog_melds() {
+    // The meld codes are put together by hand from the bit fields of mjlog,
+    // where the lowest 2 bits are the seat of the target relative to the
+    // actor, which is seat 1 here.
+    let xml = concat!(
+        r#"<mjloggm ver="2.3"><GO type="193" lobby="0"/>"#,
+        r#"<UN n0="A" n1="B" n2="C" n3="D" dan="0,0,0,0" rate="1500.00,1500.00,1500.00,1500.00" sx="M,M,M,M"/>"#,
+        r#"<TAIKYOKU oya="0"/>"#,
+        r#"<INIT seed="0,0,0,1,2,80" ten="250,250,250,250" oya="0" hai0="8,9,24,36,48,53,72,76,81,82,92,112,120" hai1="4,32,33,37,54,56,64,108,113,116,117,128,132" hai2="10,17,16,25,57,77,89,88,100,109,110,118,124" hai3="5,20,38,44,45,49,60,61,84,93,94,96,125"/>"#,
+        // chi aka 5m with 34m: chi, 345m is run 2, called 2, offsets 0 0 0,
+        // relative 3
+        r#"<N who="1" m="8199"/>"#,
+        // chi 7p with 68p: run 12, called 1, offsets 1 2 3, relative 3
+        r#"<N who="1" m="38351"/>"#,
+        // pon 2m from toimen: pon, pai 1 * 3 + called 1, unused 2, relative 2
+        r#"<N who="1" m="2122"/>"#,
+        // kakan of it with the unused one
+        r#"<N who="1" m="2130"/>"#,
+        // pon aka 5s from kamicha: pai 22 * 3 + called 0, unused 3, relative 3
+        r#"<N who="1" m="33899"/>"#,
+        // daiminkan 7s from shimocha: id 96, relative 1
+        r#"<N who="1" m="24577"/>"#,
+        // ankan 1z: id 108
+        r#"<N who="1" m="27648"/>"#,
+        r#"<RYUUKYOKU ba="0,0" sc="250,0,250,0,250,0,250,0"/></mjloggm>"#,
+    );
+    let log = mjlog::parse_str(xml).expect("failed to parse mjlog");
+    assert_eq!(log.num_players(), 4);
+
+    let table = &log.kyokus[0].action_tables[1];
+    assert_eq!(
+        nakis(&table.takes),
+        ["c511314", "c272628", "12p1212", "p533535", "373737m37"],
+    );
+    assert_eq!(nakis(&table.discards), ["12k121212", "414141a41"]);
+}
+
+#[test]
+fn test_mjlog_sanma_melds() {
+    // The meld codes are put together by hand from the bit fields of mjlog,
+    // where the lowest 2 bits are the seat of the target relative to the
+    // actor, which is seat 1 here, counting around the 3 seats.
+    let xml = concat!(
+        r#"<mjloggm ver="2.3"><GO type="17" lobby="0"/>"#,
+        r#"<UN n0="A" n1="B" n2="C" n3="" dan="0,0,0,0" rate="1500.00,1500.00,1500.00,1500.00" sx="M,M,M,M"/>"#,
+        r#"<TAIKYOKU oya="0"/>"#,
+        r#"<INIT seed="0,0,0,1,2,53" ten="350,350,350,0" oya="0" hai0="0,32,33,36,37,80,81,84,108,109,112,116,124" hai1="1,40,44,48,60,64,68,89,92,96,120,125,126" hai2="34,41,54,56,57,76,77,82,100,101,113,128,132" hai3=""/>"#,
+        // pon 5z from kamicha: pon, pai 31 * 3 + called 0, unused 3, relative 2
+        r#"<N who="1" m="47722"/>"#,
+        // pon 1p from shimocha: pon, pai 9 * 3 + called 2, unused 0, relative 1
+        r#"<N who="1" m="14857"/>"#,
+        // kakan of it with the unused one
+        r#"<N who="1" m="14865"/>"#,
+        // pon aka 5p from kamicha: pai 13 * 3 + called 0, unused 3, relative 2
+        r#"<N who="1" m="20074"/>"#,
+        // daiminkan 1s from kamicha: id 72, relative 2
+        r#"<N who="1" m="18434"/>"#,
+        // ankan 9s: id 104
+        r#"<N who="1" m="26624"/>"#,
+        // nukidora: id 120 with the nukidora flag
+        r#"<N who="1" m="30752"/>"#,
+        r#"<RYUUKYOKU ba="0,0" sc="350,0,350,0,350,0"/></mjloggm>"#,
+    );
+    let log = mjlog::parse_str(xml).expect("failed to parse mjlog");
+    assert_eq!(log.num_players(), 3);
+
+    let table = &log.kyokus[0].action_tables[1];
+    assert_eq!(
+        nakis(&table.takes),
+        ["p454545", "2121p21", "p522525", "m31313131"],
+    );
+    assert_eq!(nakis(&table.discards), ["2121k2121", "393939a39", "f44"]);
+}
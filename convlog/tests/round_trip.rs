@@ -0,0 +1,90 @@
+mod testdata;
+
+use convlog::*;
+use testdata::TESTDATA;
+
+use serde_json::{self as json, json};
+
+#[test]
+fn test_round_trip() {
+    TESTDATA.iter().for_each(|data| {
+        let tenhou_log = tenhou::Log::from_json_str(data).expect("failed to parse tenhou log");
+        let mjai_log = tenhou_to_mjai(&tenhou_log).expect("failed to transform tenhou log");
+
+        let raw_log = mjai_to_tenhou(&mjai_log).expect("failed to transform mjai log");
+        let json_string = json::to_string(&raw_log).expect("failed to serialize tenhou log");
+
+        let tenhou_log_again =
+            tenhou::Log::from_json_str(&json_string).expect("failed to parse tenhou log again");
+        let mjai_log_again =
+            tenhou_to_mjai(&tenhou_log_again).expect("failed to transform tenhou log again");
+
+        assert_eq!(mjai_log, mjai_log_again);
+    });
+}
+
+/// Reverses a kyoku that ends right away in an exhaustive draw where nobody
+/// pays, without telling who is tenpai, and returns the tenpai flags.
+fn exhaustive_draw_tenpai(tehais: [[&str; 13]; 4]) -> [bool; 4] {
+    let events = [
+        json!({
+            "type": "start_game",
+            "kyoku_first": 0,
+            "aka_flag": true,
+            "names": ["A", "B", "C", "D"],
+        }),
+        json!({
+            "type": "start_kyoku",
+            "bakaze": "E",
+            "dora_marker": "9m",
+            "kyoku": 1,
+            "honba": 0,
+            "kyotaku": 0,
+            "oya": 0,
+            "scores": [25000, 25000, 25000, 25000],
+            "tehais": tehais,
+        }),
+        json!({"type": "ryukyoku", "deltas": [0, 0, 0, 0]}),
+        json!({"type": "end_kyoku"}),
+        json!({"type": "end_game"}),
+    ];
+    let events: Vec<mjai::Event> = events
+        .iter()
+        .map(|ev| json::from_value(ev.clone()).expect("invalid event"))
+        .collect();
+
+    let raw_log = mjai_to_tenhou(&events).expect("failed to transform mjai log");
+    let log = tenhou::Log::from(raw_log);
+    match log.kyokus[0].end_status {
+        tenhou::kyoku::EndStatus::Ryukyoku { tenpai, .. } => tenpai,
+        ref status => panic!("unexpected end status {:?}", status),
+    }
+}
+
+#[test]
+fn test_all_tenpai_ryukyoku() {
+    let tenpai = exhaustive_draw_tenpai([
+        [
+            "1m", "2m", "3m", "4m", "5m", "6m", "7m", "8m", "9m", "E", "E", "E", "S",
+        ],
+        [
+            "1p", "2p", "3p", "4p", "5p", "6p", "7p", "8p", "9p", "S", "S", "S", "W",
+        ],
+        [
+            "1s", "2s", "3s", "4s", "5s", "6s", "7s", "8s", "9s", "W", "W", "W", "N",
+        ],
+        [
+            "2m", "3m", "4m", "2p", "3p", "4p", "2s", "3s", "4s", "P", "P", "P", "N",
+        ],
+    ]);
+    assert_eq!(tenpai, [true; 4]);
+}
+
+#[test]
+fn test_all_noten_ryukyoku() {
+    let noten = [
+        "1m", "4m", "7m", "1p", "4p", "7p", "1s", "4s", "7s", "E", "S", "W", "N",
+    ];
+    let tenpai = exhaustive_draw_tenpai([noten; 4]);
+    assert_eq!(tenpai, [false; 4]);
+}
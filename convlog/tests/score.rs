@@ -0,0 +1,172 @@
+mod testdata;
+
+use convlog::board::Board;
+use convlog::mjai::Event;
+use convlog::notation::Pais;
+use convlog::score::{self, HoraContext};
+use convlog::tenhou::Limit;
+use convlog::*;
+use testdata::TESTDATA;
+
+#[test]
+fn test_testdata_horas() {
+    let mut num_horas = 0;
+
+    TESTDATA.iter().for_each(|data| {
+        let tenhou_log = tenhou::Log::from_json_str(data).expect("failed to parse tenhou log");
+        let events = tenhou_to_mjai(&tenhou_log).expect("failed to transform tenhou log");
+
+        let mut board = Board::new();
+        for event in &events {
+            if let Event::Hora {
+                deltas: Some(deltas),
+                fu,
+                fan,
+                limit,
+                ref yakus,
+                ..
+            } = *event
+            {
+                let ctx = board.hora_context(event).expect("not a hora");
+                let value = score::calculate(&ctx)
+                    .unwrap_or_else(|| panic!("failed to value hora {:?}", ctx));
+
+                let mut expected_yaku = yakus.clone().unwrap_or_default();
+                let mut actual_yaku: Vec<_> =
+                    value.yaku.iter().map(|y| (y.name.clone(), y.han)).collect();
+                expected_yaku.sort();
+                actual_yaku.sort();
+                assert_eq!(actual_yaku, expected_yaku, "{:?}", ctx);
+
+                assert_eq!(value.deltas, deltas, "{:?}", ctx);
+                assert_eq!(value.limit, limit, "{:?}", ctx);
+                if limit.is_none() {
+                    assert_eq!(Some(value.fu), fu, "{:?}", ctx);
+                    assert_eq!(Some(value.han), fan, "{:?}", ctx);
+                }
+
+                num_horas += 1;
+            }
+
+            board.update(event).expect("failed to replay");
+        }
+    });
+
+    assert_eq!(num_horas, 14);
+}
+
+fn ron(tehai: &str, winning_pai: &str) -> HoraContext {
+    HoraContext {
+        num_players: 4,
+        actor: 1,
+        target: 0,
+        oya: 0,
+        bakaze: Pai::East,
+        tehai: tehai.parse::<Pais>().unwrap().0,
+        winning_pai: winning_pai.parse::<Pais>().unwrap()[0],
+        ..HoraContext::default()
+    }
+}
+
+fn yaku_names(ctx: &HoraContext) -> Vec<String> {
+    score::calculate(ctx)
+        .expect("failed to value hora")
+        .yaku
+        .into_iter()
+        .map(|y| y.name)
+        .collect()
+}
+
+#[test]
+fn test_pinfu_and_fu() {
+    let ctx = ron("23m45699p345789s", "1m");
+    let value = score::calculate(&ctx).unwrap();
+    assert_eq!(yaku_names(&ctx), ["平和"]);
+    assert_eq!((value.han, value.fu), (1, 30));
+    assert_eq!(value.deltas, [-1000, 1000, 0, 0]);
+
+    // kanchan is not pinfu, which leaves no yaku.
+    let ctx = ron("13m45699p345789s", "2m");
+    assert_eq!(score::calculate(&ctx), None);
+
+    let ctx = HoraContext {
+        target: 1,
+        ..ron("23m45699p345789s", "1m")
+    };
+    let value = score::calculate(&ctx).unwrap();
+    assert_eq!(yaku_names(&ctx), ["門前清自摸和", "平和"]);
+    assert_eq!((value.han, value.fu), (2, 20));
+    assert_eq!(value.deltas, [-700, 1500, -400, -400]);
+}
+
+#[test]
+fn test_yakuman() {
+    let ctx = ron("19m19p19s1234567z", "1m");
+    let value = score::calculate(&ctx).unwrap();
+    assert_eq!(yaku_names(&ctx), ["国士無双１３面"]);
+    assert_eq!(value.limit, Some(Limit::Yakuman));
+    assert_eq!(value.deltas, [-32000, 32000, 0, 0]);
+
+    let ctx = ron("111222333m555p9s", "9s");
+    assert_eq!(yaku_names(&ctx), ["四暗刻単騎"]);
+
+    // shanpon by ron is not suuankou.
+    let ctx = ron("111222333m55p99s", "9s");
+    assert!(!yaku_names(&ctx).contains(&"四暗刻".to_owned()));
+
+    let ctx = ron("1112345678999m", "5m");
+    assert_eq!(yaku_names(&ctx), ["純正九蓮宝燈"]);
+}
+
+#[test]
+fn test_chiitoitsu() {
+    let ctx = ron("2233m4466p88s667z", "7z");
+    let value = score::calculate(&ctx).unwrap();
+    assert_eq!(yaku_names(&ctx), ["七対子"]);
+    assert_eq!((value.han, value.fu), (2, 25));
+    assert_eq!(value.deltas, [-1600, 1600, 0, 0]);
+
+    // ryanpeikou is preferred over chiitoitsu.
+    let ctx = ron("223344m556677p5s", "5s");
+    let names = yaku_names(&ctx);
+    assert!(names.contains(&"二盃口".to_owned()));
+    assert!(!names.contains(&"七対子".to_owned()));
+}
+
+#[test]
+fn test_sanma_tsumo_loss() {
+    // The dealer pays 1300 and the other non-dealer 700, i.e. the share of
+    // the absent player is lost.
+    let ctx = HoraContext {
+        num_players: 3,
+        target: 1,
+        dora_markers: vec!["8s".parse::<Pais>().unwrap()[0]],
+        ..ron("23m45699p345789s", "1m")
+    };
+    let value = score::calculate(&ctx).unwrap();
+    assert_eq!((value.han, value.fu), (3, 20));
+    assert_eq!(value.deltas, [-1300, 2000, -700, 0]);
+
+    let ctx = HoraContext { honba: 2, ..ctx };
+    let value = score::calculate(&ctx).unwrap();
+    assert_eq!(value.deltas, [-1500, 2400, -900, 0]);
+
+    let ctx = HoraContext { target: 0, ..ctx };
+    let value = score::calculate(&ctx).unwrap();
+    assert_eq!((value.han, value.fu), (2, 30));
+    assert_eq!(value.deltas, [-2400, 2400, 0, 0]);
+}
+
+#[test]
+fn test_sanma_oya_tsumo() {
+    let ctx = HoraContext {
+        num_players: 3,
+        actor: 0,
+        target: 0,
+        honba: 1,
+        ..ron("23m45699p345789s", "1m")
+    };
+    let value = score::calculate(&ctx).unwrap();
+    assert_eq!((value.han, value.fu), (2, 20));
+    assert_eq!(value.deltas, [1600, -800, -800, 0]);
+}
@@ -0,0 +1,94 @@
+use convlog::notation::Pais;
+use convlog::shanten::{self, UkeirePai};
+use convlog::*;
+
+fn counts(s: &str) -> TileCounts {
+    let pais: Pais = s.parse().expect("failed to parse pais");
+    TileCounts::from(&*pais)
+}
+
+#[test]
+fn test_shanten() {
+    let cases = &[
+        // agari
+        ("123m456p789s11222z", 0, -1),
+        ("11223344556677z", 0, -1),
+        ("19m19p19s12345677z", 0, -1),
+        ("11222z", 3, -1),
+        // tenpai
+        ("123m456p789s1122z", 0, 0),
+        ("1112345678999m", 0, 0),
+        ("1122334455667z", 0, 0),
+        ("19m19p19s1234567z", 0, 0),
+        ("1z", 4, 0),
+        // iishanten and more
+        ("123m456p79s12z", 1, 1),
+        ("123m406p1357s117z", 0, 1),
+        ("2468m2468p2468s1z", 0, 4),
+        ("147m258p369s1234z", 0, 6),
+    ];
+
+    for &(hand, num_fuuros, expected) in cases {
+        assert_eq!(
+            shanten::shanten(&counts(hand), num_fuuros),
+            expected,
+            "{} with {} fuuros",
+            hand,
+            num_fuuros,
+        );
+    }
+
+    // chiitoitsu and kokushi only apply to a closed hand.
+    assert_eq!(shanten::chiitoitsu_shanten(&counts("1122334455667z")), 0);
+    assert_eq!(shanten::kokushi_shanten(&counts("19m19p19s1234567z")), 0);
+    // four of a kind is not two pairs.
+    assert_eq!(shanten::chiitoitsu_shanten(&counts("1111223344556z")), 2);
+}
+
+#[test]
+fn test_waits() {
+    let cases = &[
+        ("1112345678999m", 0, "123456789m"),
+        ("123m456p789s1122z", 0, "12z"),
+        ("23m456p789s11z", 1, "14m"),
+        ("19m19p19s1234567z", 0, "19m19p19s1234567z"),
+        ("1z", 4, "1z"),
+        ("123m456p789s1357z", 0, ""),
+    ];
+
+    for &(hand, num_fuuros, expected) in cases {
+        assert_eq!(
+            shanten::waits(&counts(hand), num_fuuros),
+            expected.parse::<Pais>().unwrap().0,
+            "{}",
+            hand,
+        );
+    }
+}
+
+#[test]
+fn test_ukeire() {
+    let ukeire = shanten::ukeire(&counts("23m456p789s11z"), 1, &counts("1m4m4m4m"));
+    assert_eq!(ukeire.shanten, 0);
+    assert_eq!(
+        ukeire.pais,
+        [
+            UkeirePai {
+                pai: Pai::Man1,
+                left: 3,
+            },
+            UkeirePai {
+                pai: Pai::Man4,
+                left: 1,
+            },
+        ],
+    );
+    assert_eq!(ukeire.total, 4);
+
+    let candidates =
+        shanten::discard_candidates(&counts("123m456p789s11227z"), 0, &TileCounts::new());
+    assert_eq!(candidates[0].pai, Pai::Chun);
+    assert_eq!(candidates[0].ukeire.shanten, 0);
+    assert_eq!(candidates[0].ukeire.total, 4);
+    assert_eq!(candidates.last().unwrap().ukeire.shanten, 1);
+}
@@ -8,6 +8,7 @@ pub const TESTDATA: &[&str] = &[
     include_str!("ranked_game.json"),
     include_str!("rinshan.json"),
     include_str!("ryukyoku.json"),
+    include_str!("sanma.json"),
     include_str!("suukantsu_0.json"),
     include_str!("suukantsu_1.json"),
 ];
@@ -0,0 +1,247 @@
+mod testdata;
+
+use convlog::board::BoardError;
+use convlog::mjai::Event;
+use convlog::*;
+use testdata::TESTDATA;
+
+fn load(data: &str) -> Vec<Event> {
+    let tenhou_log = tenhou::Log::from_json_str(data).expect("failed to parse tenhou log");
+    tenhou_to_mjai(&tenhou_log).expect("failed to transform tenhou log")
+}
+
+#[test]
+fn test_validate_testdata() {
+    TESTDATA.iter().for_each(|data| {
+        validate(&load(data)).expect("testdata must be valid");
+    });
+}
+
+#[test]
+fn test_validate_errors() {
+    let events = load(include_str!("testdata/complex_nakis_1.json"));
+
+    // discard a pai that is not in the tehai.
+    let mut broken = events.clone();
+    let idx = broken
+        .iter()
+        .position(|ev| matches!(ev, Event::Dahai { .. }))
+        .unwrap();
+    let mut board = Board::new();
+    for ev in &broken[..idx] {
+        board.update(ev).unwrap();
+    }
+    if let Event::Dahai {
+        actor,
+        pai,
+        tsumogiri,
+    } = &mut broken[idx]
+    {
+        let tehai = &board.players[*actor as usize].tehai;
+        *pai = (0..34)
+            .filter_map(Pai::from_id34)
+            .find(|p| !tehai.contains(p))
+            .unwrap();
+        *tsumogiri = false;
+    }
+    let err = validate(&broken).unwrap_err();
+    assert_eq!(err.index, idx);
+    assert!(matches!(
+        err.kind,
+        ValidationErrorKind::Board(BoardError::PaiNotInTehai { .. })
+    ));
+
+    // chi from a seat other than kamicha.
+    let mut broken = events.clone();
+    let idx = broken
+        .iter()
+        .position(|ev| matches!(ev, Event::Chi { .. }))
+        .expect("no chi found");
+    if let Event::Chi { actor, target, .. } = &mut broken[idx] {
+        *target = (*actor + 2) % 4;
+    }
+    let err = validate(&broken).unwrap_err();
+    assert_eq!(err.index, idx);
+    assert!(matches!(
+        err.kind,
+        ValidationErrorKind::ChiFromWrongSeat { .. }
+    ));
+
+    // scores that do not add up.
+    let mut broken = events;
+    let idx = broken
+        .iter()
+        .position(|ev| matches!(ev, Event::Hora { .. }))
+        .expect("no hora found");
+    if let Event::Hora {
+        deltas: Some(deltas),
+        ..
+    } = &mut broken[idx]
+    {
+        deltas[0] += 100;
+    }
+    let err = validate(&broken).unwrap_err();
+    assert_eq!(err.index, idx);
+    assert!(matches!(
+        err.kind,
+        ValidationErrorKind::DeltasMismatch { .. }
+    ));
+}
+
+#[test]
+fn test_validate_missing_dora() {
+    let mut events = load(include_str!("testdata/suukantsu_0.json"));
+    let idx = events
+        .iter()
+        .position(|ev| matches!(ev, Event::Dora { .. }))
+        .expect("no dora found");
+    events.remove(idx);
+
+    let err = validate(&events).unwrap_err();
+    assert!(matches!(err.kind, ValidationErrorKind::MissingDora(_)));
+}
+
+#[test]
+fn test_validate_hora_value() {
+    let mut events = load(include_str!("testdata/rinshan.json"));
+    let idx = events
+        .iter()
+        .position(|ev| matches!(ev, Event::Hora { .. }))
+        .expect("no hora found");
+
+    // still sums up, but the winner gets more than the hand is worth.
+    if let Event::Hora {
+        actor,
+        deltas: Some(deltas),
+        ..
+    } = &mut events[idx]
+    {
+        let payer = (*actor as usize + 1) % 4;
+        deltas[*actor as usize] += 1000;
+        deltas[payer] -= 1000;
+    }
+    let err = validate(&events).unwrap_err();
+    assert_eq!(err.index, idx);
+    assert!(matches!(
+        err.kind,
+        ValidationErrorKind::HoraValueMismatch { .. }
+    ));
+}
+
+#[test]
+fn test_validate_reach_errors() {
+    let events = load(include_str!("testdata/four_reach.json"));
+
+    // reach with less than 1000 points.
+    let (mut broken, idx) = cut_to_kyoku_of(&events, |ev| matches!(ev, Event::Reach { .. }));
+    let actor = match broken[idx] {
+        Event::Reach { actor } => actor,
+        _ => unreachable!(),
+    };
+    if let Event::StartKyoku { scores, .. } = &mut broken[1] {
+        scores[actor as usize] = 900;
+    }
+    let err = validate(&broken).unwrap_err();
+    assert_eq!(err.index, idx);
+    assert!(matches!(
+        err.kind,
+        ValidationErrorKind::InsufficientScoreForReach { score: 900, .. }
+    ));
+
+    // reach again after the reach is accepted.
+    let mut broken = events.clone();
+    let idx = broken
+        .iter()
+        .position(|ev| matches!(ev, Event::ReachAccepted { .. }))
+        .expect("no reach_accepted found");
+    if let Event::ReachAccepted { actor } = broken[idx] {
+        broken.insert(idx + 1, Event::Reach { actor });
+    }
+    let err = validate(&broken).unwrap_err();
+    assert_eq!(err.index, idx + 1);
+    assert!(matches!(err.kind, ValidationErrorKind::DuplicateReach(_)));
+
+    // reach_accepted of a player who has not declared reach.
+    let mut broken = events;
+    let idx = broken
+        .iter()
+        .position(|ev| matches!(ev, Event::Dahai { .. }))
+        .unwrap();
+    if let Event::Dahai { actor, .. } = broken[idx] {
+        broken.insert(idx + 1, Event::ReachAccepted { actor });
+    }
+    let err = validate(&broken).unwrap_err();
+    assert_eq!(err.index, idx + 1);
+    assert!(matches!(
+        err.kind,
+        ValidationErrorKind::UnexpectedReachAccepted(_)
+    ));
+
+    // reach right after a pon.
+    let mut broken = load(include_str!("testdata/complex_nakis_1.json"));
+    let idx = broken
+        .iter()
+        .position(|ev| matches!(ev, Event::Pon { .. }))
+        .expect("no pon found");
+    if let Event::Pon { actor, .. } = broken[idx] {
+        broken.insert(idx + 1, Event::Reach { actor });
+    }
+    let err = validate(&broken).unwrap_err();
+    assert_eq!(err.index, idx + 1);
+    assert!(matches!(
+        err.kind,
+        ValidationErrorKind::ReachWithOpenHand(_)
+    ));
+}
+
+#[test]
+fn test_validate_start_mismatch() {
+    let events = load(include_str!("testdata/ranked_game.json"));
+    let idx = events
+        .iter()
+        .enumerate()
+        .filter(|(_, ev)| matches!(ev, Event::StartKyoku { .. }))
+        .nth(1)
+        .expect("no second kyoku found")
+        .0;
+
+    // points moved between two players out of nowhere.
+    let mut broken = events.clone();
+    if let Event::StartKyoku { scores, .. } = &mut broken[idx] {
+        scores[0] += 1000;
+        scores[1] -= 1000;
+    }
+    let err = validate(&broken).unwrap_err();
+    assert_eq!(err.index, idx);
+    assert!(matches!(
+        err.kind,
+        ValidationErrorKind::ScoresMismatch { .. }
+    ));
+
+    // a kyotaku out of nowhere.
+    let mut broken = events;
+    if let Event::StartKyoku { kyotaku, .. } = &mut broken[idx] {
+        *kyotaku += 1;
+    }
+    let err = validate(&broken).unwrap_err();
+    assert_eq!(err.index, idx);
+    assert!(matches!(
+        err.kind,
+        ValidationErrorKind::KyotakuMismatch { .. }
+    ));
+}
+
+/// Cuts the game down to `start_game` and the kyokus from the one of the first
+/// event that matches `f`, so that its scores are not checked against the
+/// kyoku before. Returns the index of the event in the cut game as well.
+fn cut_to_kyoku_of(events: &[Event], f: impl Fn(&Event) -> bool) -> (Vec<Event>, usize) {
+    let idx = events.iter().position(f).expect("no such event found");
+    let start = events[..idx]
+        .iter()
+        .rposition(|ev| matches!(ev, Event::StartKyoku { .. }))
+        .expect("no start_kyoku found");
+
+    let mut cut = vec![events[0].clone()];
+    cut.extend_from_slice(&events[start..]);
+    (cut, idx - start + 1)
+}
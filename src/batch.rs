@@ -0,0 +1,231 @@
+use crate::cache::{review_with, ReviewCache};
+use crate::engine::ReviewEngine;
+use crate::log;
+use crate::metadata::{game_length_of, players_of, Metadata};
+use crate::render::{GameSummary, IndexView, View};
+use crate::review::convert_lenient;
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use convlog::{tenhou, Anonymizer, TenhouLogRef};
+
+/// What `batch_review` shares among the games.
+pub struct BatchOptions<'a, E> {
+    /// Cloned for each job of every game, so that a game never goes on with
+    /// an engine left behind by a failed one.
+    pub engine: E,
+    pub jobs: usize,
+    pub cache: Option<&'a ReviewCache>,
+    pub pt: Option<&'a [i32]>,
+    pub actor: Option<u8>,
+    pub player_name: Option<&'a str>,
+    pub kyokus: Option<&'a str>,
+    pub anonymizer: Option<&'a Anonymizer>,
+    /// Whether the engine supports sanma, which akochan does not.
+    pub sanma: bool,
+    pub full: bool,
+    pub verbose: bool,
+    pub version: &'a str,
+}
+
+/// Reviews every log in `tenhou_ids_file`, going on with the rest when one
+/// fails, and writes the reports and the index page into `out_dir_name`.
+/// The logs are fetched by their IDs with `fetch`, which usually downloads
+/// them. Returns the path of the index page.
+pub fn batch_review<E, F>(
+    out_dir_name: &Path,
+    tenhou_ids_file: &Path,
+    mut fetch: F,
+    opts: &BatchOptions<'_, E>,
+) -> Result<PathBuf>
+where
+    E: ReviewEngine + Clone + Send,
+    F: FnMut(&str) -> Result<tenhou::RawLog>,
+{
+    fs::create_dir_all(out_dir_name)
+        .with_context(|| format!("failed to create {:?}", out_dir_name))?;
+
+    log!("tenhou_ids_file: {:?}", tenhou_ids_file);
+
+    let mut games = vec![];
+    for line in BufReader::new(File::open(tenhou_ids_file)?).lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let position = games.len() + 1;
+        let summary =
+            review_game(out_dir_name, position, line, &mut fetch, opts).unwrap_or_else(|err| {
+                log!("warning: failed to review {:?}: {:#}", line, err);
+                if opts.anonymizer.is_some() {
+                    // the error may tell the log ID.
+                    GameSummary::failed(None, &anyhow!("failed to review, see the log for details"))
+                } else {
+                    let tenhou_id = line.split_whitespace().next().unwrap_or(line);
+                    GameSummary::failed(Some(tenhou_id), &err)
+                }
+            });
+        games.push(summary);
+    }
+
+    let index_path = out_dir_name.join("index.html");
+    let view = IndexView::new(&games, opts.version);
+    let mut index_file = File::create(&index_path)
+        .with_context(|| format!("failed to create index page {:?}", index_path))?;
+    view.render(&mut index_file)
+        .context("failed to render index page")?;
+
+    log!(
+        "{} of {} games reviewed",
+        games.iter().filter(|g| g.error.is_none()).count(),
+        games.len(),
+    );
+    Ok(index_path)
+}
+
+/// Reviews a game of a line in the file of `batch_review`, which is a log ID
+/// or url, optionally followed by the actor. `position` counts the games from
+/// 1, which names the report instead of the log ID when anonymizing.
+fn review_game<E, F>(
+    out_dir_name: &Path,
+    position: usize,
+    line: &str,
+    fetch: &mut F,
+    opts: &BatchOptions<'_, E>,
+) -> Result<GameSummary>
+where
+    E: ReviewEngine + Clone + Send,
+    F: FnMut(&str) -> Result<tenhou::RawLog>,
+{
+    let mut columns = line.split_whitespace();
+    let log_ref = columns
+        .next()
+        .unwrap_or_default()
+        .parse::<TenhouLogRef>()
+        .context("failed to parse tenhou log ID")?;
+    let column_actor = columns
+        .next()
+        .map(|a| a.parse::<u8>())
+        .transpose()
+        .with_context(|| format!("failed to parse actor in {:?}", line))?;
+    let tenhou_id = log_ref.id.as_str();
+
+    let begin_parse_log = chrono::Local::now();
+    let mut raw_log = fetch(tenhou_id)?;
+
+    // get actor, from the column, "&tw=" in the url, the name and --actor in
+    // order
+    let actor = column_actor
+        .or(log_ref.actor)
+        .or_else(|| {
+            let name = opts.player_name?;
+            raw_log
+                .names()
+                .iter()
+                .position(|n| n == name)
+                .map(|i| i as u8)
+        })
+        .or(opts.actor)
+        .context("no actor is specified or found by --player-name")?;
+
+    // filter kyokus, where --kyokus takes precedence over "&ts=" in the url
+    let filter = match opts.kyokus {
+        Some(s) => Some(s.parse().context("failed to parse kyoku filter")?),
+        None => log_ref.kyoku_filter(),
+    };
+    if let Some(filter) = filter {
+        raw_log.filter_kyokus(&filter);
+    }
+    if let Some(anonymizer) = opts.anonymizer {
+        raw_log.anonymize(anonymizer);
+    }
+
+    let cloned_raw_log = raw_log.clone();
+    let mut splited_raw_logs = cloned_raw_log.split_by_kyoku();
+    let mut log = tenhou::Log::from(raw_log);
+    if actor >= log.num_players() {
+        return Err(anyhow!("actor {} does not exist in a sanma log", actor));
+    }
+    if log.num_players() == 3 && !opts.sanma {
+        return Err(anyhow!(
+            "{} does not support sanma, review it with --engine pipe instead",
+            opts.engine.name(),
+        ));
+    }
+
+    let begin_convert_log = chrono::Local::now();
+    log!("converting to mjai events...");
+    let (events, skipped_kyokus) = convert_lenient(&log);
+    if events.len() <= 2 {
+        return Err(anyhow!("failed to convert any kyoku into mjai format"));
+    }
+    for &idx in skipped_kyokus.iter().rev() {
+        log.kyokus.remove(idx);
+        splited_raw_logs.remove(idx);
+    }
+
+    log!(
+        "reviewing {} as {} ...",
+        tenhou_id,
+        log.names[actor as usize]
+    );
+    let begin_review = chrono::Local::now();
+    let mut engines = vec![opts.engine.clone(); opts.jobs];
+    let review_result = review_with(
+        &mut engines,
+        opts.cache,
+        &events,
+        actor,
+        opts.full,
+        opts.verbose,
+    )
+    .context("failed to review log")?;
+
+    // the log ID links to the real names.
+    let shown_tenhou_id = Some(tenhou_id).filter(|_| opts.anonymizer.is_none());
+    let now = chrono::Local::now();
+    let meta = Metadata {
+        engine: opts.engine.name(),
+        pt: opts.pt,
+        game_length: &game_length_of(&log),
+        parse_time: (begin_convert_log - begin_parse_log).to_std()?,
+        convert_time: (begin_review - begin_convert_log).to_std()?,
+        review_time: (now - begin_review).to_std()?,
+        tenhou_id: shown_tenhou_id,
+        players: players_of(&log),
+        lobby: &log.lobby,
+        total_reviewed: review_result.total_reviewed,
+        total_entries: review_result.total_entries,
+        version: opts.version,
+    };
+    let view = View::new(
+        &review_result.kyokus,
+        actor,
+        log.num_players(),
+        &meta,
+        Some(splited_raw_logs),
+    );
+
+    let report = match shown_tenhou_id {
+        Some(tenhou_id) => format!("{}&tw={}.html", tenhou_id, actor),
+        None => format!("game-{}.html", position),
+    };
+    let report_path = out_dir_name.join(&report);
+    let mut out = File::create(&report_path)
+        .with_context(|| format!("failed to create HTML report file {:?}", report_path))?;
+    view.render(&mut out)
+        .context("failed to render HTML report")?;
+
+    Ok(GameSummary::new(
+        shown_tenhou_id,
+        &log.names[actor as usize],
+        &report,
+        &review_result,
+    ))
+}
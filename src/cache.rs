@@ -0,0 +1,331 @@
+use crate::engine::ReviewEngine;
+use crate::log;
+use crate::review::{kyoku_ranges, review_kyokus, review_parallel, KyokuReview, Review};
+
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use convlog::mjai::Event;
+use serde_json::{self as json, Value};
+use sha2::{Digest, Sha256};
+
+/// Bumped whenever `KyokuReview` or how it is reviewed changes, so that the
+/// old entries are never served.
+const CACHE_VERSION: &str = "2";
+
+/// Reviews of kyokus stored on disk, so that a kyoku reviewed before with the
+/// same engine, tactics and options is not reviewed again.
+///
+/// Each kyoku is stored in `{dir}/{key[..2]}/{key}.json`, where the key is
+/// the SHA-256 of the engine, the target actor, the options, and the events
+/// of the game up to the end of the kyoku, as the review of a kyoku may depend
+/// on all of them, see `review_kyokus`.
+pub struct ReviewCache {
+    dir: PathBuf,
+    /// What else the review depends on, see `engine_id`.
+    engine_id: String,
+}
+
+/// Entries removed by `prune`.
+#[derive(Debug, Default)]
+pub struct PruneStats {
+    pub removed: usize,
+    pub removed_bytes: u64,
+    pub kept: usize,
+}
+
+impl ReviewCache {
+    #[inline]
+    pub fn new(dir: &Path, engine_id: &str) -> Self {
+        Self {
+            dir: dir.to_owned(),
+            engine_id: engine_id.to_owned(),
+        }
+    }
+
+    /// Reviews the kyokus not in the cache only, and puts them into the
+    /// cache. The result is the same as `review_parallel`.
+    pub fn review<E>(
+        &self,
+        engines: &mut [E],
+        events: &[Event],
+        target_actor: u8,
+        full: bool,
+        verbose: bool,
+    ) -> Result<Review>
+    where
+        E: ReviewEngine + Send,
+    {
+        let kyoku_ranges = kyoku_ranges(events);
+        if kyoku_ranges.is_empty() {
+            return review_parallel(engines, events, target_actor, full, verbose);
+        }
+
+        let keys_result: Result<Vec<_>> = kyoku_ranges
+            .iter()
+            .map(|range| self.key(target_actor, full, &events[..range.end]))
+            .collect();
+        let keys = match keys_result {
+            Ok(keys) => keys,
+            Err(err) => {
+                log!("warning: reviewing without cache: {:#}", err);
+                return review_parallel(engines, events, target_actor, full, verbose);
+            }
+        };
+        let mut kyokus: Vec<_> = keys.iter().map(|key| self.get(key)).collect();
+
+        let missing: Vec<_> = (0..kyokus.len()).filter(|&i| kyokus[i].is_none()).collect();
+        log!(
+            "{} of {} kyokus found in cache",
+            kyokus.len() - missing.len(),
+            kyokus.len(),
+        );
+
+        if !missing.is_empty() {
+            let reviewed = review_kyokus(engines, events, &missing, target_actor, full, verbose)?;
+            for (&i, kyoku) in missing.iter().zip(reviewed) {
+                if let Err(err) = self.put(&keys[i], &kyoku) {
+                    log!("warning: failed to write cache: {:#}", err);
+                }
+                kyokus[i] = Some(kyoku);
+            }
+        }
+
+        let kyokus: Vec<_> = kyokus
+            .into_iter()
+            .collect::<Option<_>>()
+            .context("invalid state: some kyokus are not reviewed")?;
+        Ok(Review {
+            target_actor,
+            total_reviewed: kyokus.iter().map(|k| k.total_reviewed).sum(),
+            total_entries: kyokus.iter().map(|k| k.entries.len()).sum(),
+            kyokus,
+        })
+    }
+
+    /// `events` are those of the game up to the end of the kyoku.
+    fn key(&self, target_actor: u8, full: bool, events: &[Event]) -> Result<String> {
+        let mut hasher = Sha256::new()
+            .chain_update(CACHE_VERSION)
+            .chain_update([0])
+            .chain_update(&self.engine_id)
+            .chain_update([0, target_actor, full as u8]);
+        for event in events {
+            let line = json::to_string(event).context("failed to serialize event for cache key")?;
+            hasher.update(line);
+            hasher.update([b'\n']);
+        }
+        Ok(to_hex(&hasher.finalize()))
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(&key[..2]).join(format!("{}.json", key))
+    }
+
+    /// Returns `None` if it is not in the cache or broken.
+    fn get(&self, key: &str) -> Option<KyokuReview> {
+        let path = self.path(key);
+        let file = File::open(&path).ok()?;
+
+        match json::from_reader(BufReader::new(file)) {
+            Ok(kyoku) => {
+                // mark it as used for prune, which is fine to fail, e.g. in a
+                // read-only cache.
+                if let Ok(file) = File::options().append(true).open(&path) {
+                    let _ = file.set_modified(SystemTime::now());
+                }
+                Some(kyoku)
+            }
+            Err(err) => {
+                log!("warning: ignoring broken cache {:?}: {}", path, err);
+                None
+            }
+        }
+    }
+
+    fn put(&self, key: &str, kyoku: &KyokuReview) -> Result<()> {
+        let path = self.path(key);
+        let dir = path.parent().unwrap_or(&self.dir);
+        fs::create_dir_all(dir).with_context(|| format!("failed to create {:?}", dir))?;
+
+        // write to a temp file first, so that a half written one is never read.
+        let tmp_path = path.with_extension("json.tmp");
+        let mut tmp = File::create(&tmp_path)
+            .with_context(|| format!("failed to create cache file {:?}", tmp_path))?;
+        json::to_writer(&mut tmp, kyoku).context("failed to serialize")?;
+        tmp.flush()?;
+        drop(tmp);
+
+        fs::rename(&tmp_path, &path)
+            .with_context(|| format!("failed to rename {:?} to {:?}", tmp_path, path))
+    }
+}
+
+/// Reviews with `cache` if there is one, otherwise just like
+/// `review_parallel`.
+pub fn review_with<E>(
+    engines: &mut [E],
+    cache: Option<&ReviewCache>,
+    events: &[Event],
+    target_actor: u8,
+    full: bool,
+    verbose: bool,
+) -> Result<Review>
+where
+    E: ReviewEngine + Send,
+{
+    match cache {
+        Some(cache) => cache.review(engines, events, target_actor, full, verbose),
+        None => review_parallel(engines, events, target_actor, full, verbose),
+    }
+}
+
+/// Removes the entries of the cache in `dir` not used for `older_than`, or all
+/// of them if it is `None`. Anything else in `dir` is left alone, in case it
+/// is not a cache directory at all.
+pub fn prune(dir: &Path, older_than: Option<Duration>) -> Result<PruneStats> {
+    let mut stats = PruneStats::default();
+    if !dir.exists() {
+        return Ok(stats);
+    }
+
+    let now = SystemTime::now();
+    for sub_dir in read_dir(dir)? {
+        if !sub_dir.is_dir() || !file_name_matches(&sub_dir, |name| is_hex(name, 2)) {
+            continue;
+        }
+
+        for path in read_dir(&sub_dir)? {
+            if !file_name_matches(&path, is_entry_name) {
+                continue;
+            }
+            let metadata = fs::symlink_metadata(&path)
+                .with_context(|| format!("failed to stat {:?}", path))?;
+            if !metadata.is_file() {
+                continue;
+            }
+
+            let is_expired = match older_than {
+                Some(older_than) => metadata
+                    .modified()
+                    .ok()
+                    .and_then(|t| now.duration_since(t).ok())
+                    .is_none_or(|age| age >= older_than),
+                None => true,
+            };
+
+            if is_expired {
+                fs::remove_file(&path).with_context(|| format!("failed to remove {:?}", path))?;
+                stats.removed += 1;
+                stats.removed_bytes += metadata.len();
+            } else {
+                stats.kept += 1;
+            }
+        }
+
+        // fails if it is not empty, which is fine.
+        let _ = fs::remove_dir(&sub_dir);
+    }
+
+    Ok(stats)
+}
+
+/// Returns the identity of an engine to key the cache by, from `name`, the
+/// SHA-256 of the contents of `files`, e.g. the executable, and `config`,
+/// whose object keys are sorted so that the same config always gets the same
+/// identity.
+pub fn engine_id(name: &str, files: &[&Path], config: Option<&Value>) -> Result<String> {
+    let mut id = name.to_owned();
+
+    for path in files {
+        let mut file = File::open(path).with_context(|| format!("failed to open {:?}", path))?;
+        let mut hasher = Sha256::new();
+        let mut buf = [0; 64 * 1024];
+        loop {
+            let n = file
+                .read(&mut buf)
+                .with_context(|| format!("failed to read {:?}", path))?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+        }
+        let _ = write!(id, " {}", to_hex(&hasher.finalize()));
+    }
+
+    if let Some(config) = config {
+        id.push(' ');
+        write_canonical_json(&mut id, config);
+    }
+
+    Ok(id)
+}
+
+fn write_canonical_json(s: &mut String, value: &Value) {
+    match value {
+        Value::Array(arr) => {
+            s.push('[');
+            for (i, v) in arr.iter().enumerate() {
+                if i > 0 {
+                    s.push(',');
+                }
+                write_canonical_json(s, v);
+            }
+            s.push(']');
+        }
+        Value::Object(obj) => {
+            let mut entries: Vec<_> = obj.iter().collect();
+            entries.sort_by_key(|(k, _)| *k);
+
+            s.push('{');
+            for (i, (k, v)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    s.push(',');
+                }
+                s.push_str(&Value::from(k.as_str()).to_string());
+                s.push(':');
+                write_canonical_json(s, v);
+            }
+            s.push('}');
+        }
+        _ => s.push_str(&value.to_string()),
+    }
+}
+
+fn read_dir(dir: &Path) -> Result<Vec<PathBuf>> {
+    fs::read_dir(dir)
+        .with_context(|| format!("failed to read {:?}", dir))?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<std::io::Result<_>>()
+        .with_context(|| format!("failed to read {:?}", dir))
+}
+
+fn file_name_matches(path: &Path, f: impl FnOnce(&str) -> bool) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(f)
+}
+
+/// Whether `name` is of an entry `put` writes, i.e. "{key}.json", or
+/// "{key}.json.tmp" left by an interrupted one.
+fn is_entry_name(name: &str) -> bool {
+    name.strip_suffix(".json")
+        .or_else(|| name.strip_suffix(".json.tmp"))
+        .is_some_and(|key| is_hex(key, 64))
+}
+
+fn is_hex(s: &str, len: usize) -> bool {
+    s.len() == len && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut s, b| {
+        let _ = write!(s, "{:02x}", b);
+        s
+    })
+}
@@ -0,0 +1,244 @@
+use crate::log;
+use crate::review::DetailedAction;
+
+use std::ffi::{OsStr, OsString};
+use std::io::prelude::*;
+use std::io::{BufReader, Lines};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use anyhow::{anyhow, Context, Result};
+use convlog::mjai::Event;
+use serde_json as json;
+
+/// Something that tells the best moves of the target actor in a game, like
+/// akochan.
+///
+/// The review loop feeds every event of the game in order, and asks for the
+/// candidates right after each event at which the target actor may act, that
+/// is its own tsumo, and others' dahai or kakan.
+pub trait ReviewEngine {
+    /// The name shown in logs and the report.
+    fn name(&self) -> &str;
+
+    /// Prepares to review a new game from the perspective of `target_actor`.
+    fn start(&mut self, target_actor: u8) -> Result<()>;
+
+    fn feed(&mut self, event: &Event) -> Result<()>;
+
+    /// Returns the candidate actions for the last fed event, with the best
+    /// one first.
+    fn candidates(&mut self) -> Result<Vec<DetailedAction>>;
+
+    /// Ends the game started by `start`.
+    fn finish(&mut self) -> Result<()>;
+
+    /// Gives up the game started by `start` after an error, if any, so that
+    /// the engine can be started again.
+    fn abort(&mut self);
+}
+
+/// An engine running as a child process that speaks the protocol of
+/// `akochan pipe_detailed`: mjai events in JSON, one per line, are written to
+/// its stdin, and it answers a JSON array of `DetailedAction` in one line on
+/// its stdout for each event at which the target actor may act. The target
+/// actor is passed as the last argument.
+///
+/// The command can be anything speaking the protocol, such as another bot or
+/// a remote akochan behind ssh.
+pub struct PipeEngine {
+    name: String,
+    program: PathBuf,
+    args: Vec<OsString>,
+    dir: Option<PathBuf>,
+    verbose: bool,
+
+    process: Option<Process>,
+}
+
+struct Process {
+    child: Child,
+    stdin: ChildStdin,
+    stdout_lines: Lines<BufReader<ChildStdout>>,
+}
+
+impl PipeEngine {
+    pub fn new<I, S>(name: &str, program: &Path, args: I, verbose: bool) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        Self {
+            name: name.to_owned(),
+            program: program.to_owned(),
+            args: args.into_iter().map(|a| a.as_ref().to_owned()).collect(),
+            dir: None,
+            verbose,
+            process: None,
+        }
+    }
+
+    /// Runs akochan in `akochan_dir` with `tactics_config`.
+    pub fn akochan(
+        akochan_exe: &Path,
+        akochan_dir: &Path,
+        tactics_config: &Path,
+        verbose: bool,
+    ) -> Self {
+        let args = ["pipe_detailed".as_ref(), tactics_config.as_os_str()];
+        Self::new("akochan", akochan_exe, args, verbose).current_dir(akochan_dir)
+    }
+
+    #[inline]
+    pub fn current_dir(mut self, dir: &Path) -> Self {
+        self.dir = Some(dir.to_owned());
+        self
+    }
+
+    fn process(&mut self) -> Result<&mut Process> {
+        let name = &self.name;
+        self.process
+            .as_mut()
+            .with_context(|| format!("{} is not started", name))
+    }
+}
+
+impl ReviewEngine for PipeEngine {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn start(&mut self, target_actor: u8) -> Result<()> {
+        if self.process.is_some() {
+            return Err(anyhow!("{} is already started", self.name));
+        }
+
+        let target_actor_string = target_actor.to_string();
+        let args: Vec<&OsStr> = self
+            .args
+            .iter()
+            .map(|a| a.as_os_str())
+            .chain(Some(target_actor_string.as_ref()))
+            .collect();
+
+        if self.verbose {
+            if let Some(dir) = &self.dir {
+                log!("$ cd {:?}", dir);
+            }
+            log!(
+                "$ {:?}{}",
+                self.program,
+                args.iter()
+                    .fold("".to_owned(), |acc, p| format!("{} {:?}", acc, p))
+            );
+        }
+
+        let mut command = Command::new(&self.program);
+        command
+            .args(&args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit());
+        if let Some(dir) = &self.dir {
+            command.current_dir(dir);
+        }
+        let mut child = command
+            .spawn()
+            .with_context(|| format!("failed to spawn {}", self.name))?;
+
+        let stdin = child
+            .stdin
+            .take()
+            .with_context(|| format!("failed to get stdin of {}", self.name))?;
+        let stdout = child
+            .stdout
+            .take()
+            .with_context(|| format!("failed to get stdout of {}", self.name))?;
+
+        self.process = Some(Process {
+            child,
+            stdin,
+            stdout_lines: BufReader::new(stdout).lines(),
+        });
+        Ok(())
+    }
+
+    fn feed(&mut self, event: &Event) -> Result<()> {
+        let to_write = json::to_string(event).context("failed to serialize")?;
+        if self.verbose {
+            log!("> {}", to_write);
+        }
+
+        let name = self.name.clone();
+        writeln!(self.process()?.stdin, "{}", to_write)
+            .with_context(|| format!("failed to write to {}", name))
+    }
+
+    fn candidates(&mut self) -> Result<Vec<DetailedAction>> {
+        let name = self.name.clone();
+
+        // be careful, this may block.
+        let line = self
+            .process()?
+            .stdout_lines
+            .next()
+            .with_context(|| format!("failed to read from {}: unexpected EOF", name))?
+            .with_context(|| format!("failed to read from {}", name))?;
+        if self.verbose {
+            log!("< {}", line.trim());
+        }
+
+        json::from_str(&line).with_context(|| format!("failed to parse JSON output of {}", name))
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let Process {
+            mut child, stdin, ..
+        } = self
+            .process
+            .take()
+            .with_context(|| format!("{} is not started", self.name))?;
+
+        // close stdin so that it knows the game has ended.
+        drop(stdin);
+
+        let ecode = child.wait()?;
+        if !ecode.success() {
+            if let Some(code) = ecode.code() {
+                return Err(anyhow!("non-zero exit code: {}", code));
+            } else {
+                return Err(anyhow!("non-zero exit code: Process terminated by signal"));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn abort(&mut self) {
+        if let Some(mut process) = self.process.take() {
+            let _ = process.child.kill();
+            let _ = process.child.wait();
+        }
+    }
+}
+
+/// Clones the command only, without the running process, if any.
+impl Clone for PipeEngine {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            program: self.program.clone(),
+            args: self.args.clone(),
+            dir: self.dir.clone(),
+            verbose: self.verbose,
+            process: None,
+        }
+    }
+}
+
+impl Drop for PipeEngine {
+    fn drop(&mut self) {
+        // do not leave the child running when the review failed halfway.
+        self.abort();
+    }
+}
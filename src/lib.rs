@@ -0,0 +1,11 @@
+pub mod batch;
+pub mod cache;
+pub mod download;
+pub mod engine;
+mod log;
+pub mod metadata;
+pub mod render;
+pub mod review;
+pub mod state;
+pub mod tactics;
+pub mod tehai;
@@ -1,31 +1,27 @@
-mod download;
-mod log;
-mod metadata;
-mod render;
-mod review;
-mod state;
-mod tactics;
-mod tehai;
-
-use download::download_tenhou_log;
-use metadata::Metadata;
-use render::View;
-use review::review;
-use tactics::TacticsJson;
+use akochan_reviewer::batch::{batch_review, BatchOptions};
+use akochan_reviewer::cache::{self, review_with, ReviewCache};
+use akochan_reviewer::download::download_tenhou_log;
+use akochan_reviewer::engine::{PipeEngine, ReviewEngine};
+use akochan_reviewer::log;
+use akochan_reviewer::metadata::{game_length_of, players_of, Metadata};
+use akochan_reviewer::render::View;
+use akochan_reviewer::review::convert_lenient;
+use akochan_reviewer::tactics::TacticsJson;
 
 use std::env;
-use std::ffi::OsString;
+use std::ffi::{OsStr, OsString};
 use std::fs;
 use std::fs::File;
 use std::io;
 use std::io::prelude::*;
 use std::io::BufReader;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use clap::value_t;
-use clap::{App, Arg};
-use convlog::tenhou;
+use clap::{App, Arg, SubCommand};
+use convlog::{majsoul, mjlog, tenhou, Anonymizer, TenhouLogRef};
 use dunce::canonicalize;
 use serde_json as json;
 use tee::TeeReader;
@@ -74,7 +70,8 @@ fn main() -> Result<()> {
                 })
                 .help(
                     "Specify the actor to review. \
-                    It is the number after \"&tw=\" in tenhou's log url",
+                    It is the number after \"&tw=\" in tenhou's log url, \
+                    which is used by default if --tenhou-id is a url with it",
                 ),
         )
         .arg(
@@ -85,7 +82,10 @@ fn main() -> Result<()> {
                 .value_name("ARRAY")
                 .help(
                     "Specify kyokus to review. If ARRAY is empty, review all kyokus. \
-                    Format: \"E1,E4,S3.1\"",
+                    Format: \"E1,E4,S3.1\". Also supports ranges \"E3-S2\", \
+                    any honba \"E2.*\", exclusions \"!S4\", the last N kyokus \"last:3\" \
+                    and the N-th kyoku of the log counting from 0 \"#3\". \
+                    Defaults to the kyoku after \"&ts=\" if --tenhou-id is a url with it",
                 ),
         )
         .arg(
@@ -95,10 +95,24 @@ fn main() -> Result<()> {
                 .takes_value(true)
                 .value_name("FILE")
                 .help(
-                    "Specify a tenhou.net/6 format log file to review. \
+                    "Specify a log file to review, in the format specified by --in-format. \
                     If FILE is \"-\" or empty, read from stdin",
                 ),
         )
+        .arg(
+            Arg::with_name("in-format")
+                .long("in-format")
+                .takes_value(true)
+                .value_name("FORMAT")
+                .possible_values(&["tenhou", "mjlog", "majsoul"])
+                .conflicts_with("tenhou-id")
+                .help(
+                    "Specify the format of --in-file. \
+                    \"tenhou\" for tenhou.net/6 JSON, \"mjlog\" for Tenhou's mjlog XML, \
+                    which can be gzipped, \"majsoul\" for Mahjong Soul record decoded into JSON. \
+                    Default value \"tenhou\"",
+                ),
+        )
         .arg(
             Arg::with_name("out-file")
                 .short("o")
@@ -109,7 +123,7 @@ fn main() -> Result<()> {
                     "Specify the output file for generated HTML report. \
                     If FILE is \"-\", write to stdout; \
                     if FILE is empty, write to \"{tenhou_id}&tw={actor}.html\" \
-                    if --tenhou-id is specified, otherwise \"report.html\"",
+                    if --tenhou-id is specified without --anonymize, otherwise \"report.html\"",
                 ),
         )
         .arg(
@@ -119,8 +133,9 @@ fn main() -> Result<()> {
                 .takes_value(true)
                 .value_name("ID")
                 .help(
-                    "Specify a Tenhou log ID to review, overriding --in-file. \
-                    Example: \"2019050417gm-0029-0000-4f2a8622\"",
+                    "Specify a Tenhou log ID or log url to review, overriding --in-file. \
+                    Example: \"2019050417gm-0029-0000-4f2a8622\" or \
+                    \"https://tenhou.net/0/?log=2019050417gm-0029-0000-4f2a8622&tw=2&ts=3\"",
                 ),
         )
         .arg(
@@ -130,7 +145,7 @@ fn main() -> Result<()> {
                 .value_name("FILE")
                 .help(
                     "Save the downloaded tenhou.net/6 format log to FILE \
-                    when --tenhou-id is specified. \
+                    when --tenhou-id is specified, which is anonymized with --anonymize. \
                     If FILE is \"-\", write to stdout",
                 ),
         )
@@ -150,10 +165,30 @@ fn main() -> Result<()> {
                 .takes_value(true)
                 .value_name("FILE")
                 .help(
-                    "Specify a file of Tenhou log ID list to convert to mjai format, \
-                    implying --no-review.",
+                    "Specify a file of Tenhou log ID or url list to convert to mjai format, \
+                    implying --no-review unless --batch-review is specified.",
+                ),
+        )
+        .arg(
+            Arg::with_name("batch-review")
+                .long("batch-review")
+                .requires("tenhou-ids-file")
+                .help(
+                    "Review every log in --tenhou-ids-file, writing a report for each \
+                    and \"index.html\" with the agreement rate and EV loss of all of them \
+                    to --out-dir. A line of the file can be followed by the actor, \
+                    otherwise the actor is the one after \"&tw=\" in the url, \
+                    the one named --player-name, or --actor. With --anonymize, \
+                    the reports are named by the position in the file instead of the log ID",
                 ),
         )
+        .arg(
+            Arg::with_name("player-name")
+                .long("player-name")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("Specify the name of the player to review in --batch-review"),
+        )
         .arg(
             Arg::with_name("out-dir")
                 .long("out-dir")
@@ -169,6 +204,20 @@ fn main() -> Result<()> {
                 .long("without-viewer")
                 .help("Do not include log viewer in the generated HTML report"),
         )
+        .arg(Arg::with_name("anonymize").long("anonymize").help(
+            "Replace player names with \"Aさん\" to \"Dさん\" by seat, \
+                    and remove their dan, rate, sex and the tenhou log ID from the report",
+        ))
+        .arg(
+            Arg::with_name("anonymize-salt")
+                .long("anonymize-salt")
+                .takes_value(true)
+                .value_name("SALT")
+                .help(
+                    "Replace player names with a hash salted by SALT instead, \
+                    which stays the same across logs. Implies --anonymize",
+                ),
+        )
         .arg(
             Arg::with_name("no-open")
                 .long("no-open")
@@ -184,6 +233,45 @@ fn main() -> Result<()> {
                 .long("json")
                 .help("Output review result in JSON instead of HTML"),
         )
+        .arg(
+            Arg::with_name("engine")
+                .long("engine")
+                .takes_value(true)
+                .value_name("ENGINE")
+                .possible_values(&["akochan", "pipe"])
+                .help(
+                    "Specify the engine to review with. \
+                    \"akochan\" for akochan specified by --akochan-exe, \
+                    \"pipe\" for the command specified by --engine-cmd. \
+                    Default value \"akochan\"",
+                ),
+        )
+        .arg(
+            Arg::with_name("engine-cmd")
+                .long("engine-cmd")
+                .takes_value(true)
+                .value_name("CMD")
+                .required_if("engine", "pipe")
+                .help(
+                    "Specify the command of --engine pipe, split by whitespace. \
+                    It must speak the protocol of \"akochan pipe_detailed\", \
+                    taking the actor to review as the last argument. \
+                    --tactics-config, --pt and --use-ranking-exp cannot be used with it. \
+                    Example: \"ssh host ./system.exe pipe_detailed tactics.json\"",
+                ),
+        )
+        .arg(
+            Arg::with_name("engine-id")
+                .long("engine-id")
+                .takes_value(true)
+                .value_name("ID")
+                .help(
+                    "Specify the identity of --engine pipe to key the review cache by, \
+                    which must be changed whenever the engine changes, \
+                    such as its program, model or config files. \
+                    The cache is not used for --engine pipe without it",
+                ),
+        )
         .arg(
             Arg::with_name("akochan-dir")
                 .short("d")
@@ -251,28 +339,100 @@ fn main() -> Result<()> {
                     This will override --pt and \"jun_pt\" in --tactics-config.",
                 ),
         )
+        .arg(
+            Arg::with_name("jobs")
+                .short("j")
+                .long("jobs")
+                .takes_value(true)
+                .value_name("N")
+                .validator(|v| {
+                    let num: usize = v
+                        .parse()
+                        .map_err(|err| format!("N must be a number: {}", err))?;
+
+                    if num == 0 {
+                        Err("N must be at least 1".to_owned())
+                    } else {
+                        Ok(())
+                    }
+                })
+                .help(
+                    "Review with N engine processes at the same time, \
+                    each of which reviews a part of the kyokus. \
+                    Default value 1",
+                ),
+        )
         .arg(
             Arg::with_name("full")
                 .short("f")
                 .long("full")
                 .help("Analyze every move, not only the different ones."),
         )
+        .arg(
+            Arg::with_name("cache-dir")
+                .long("cache-dir")
+                .takes_value(true)
+                .value_name("DIR")
+                .help(
+                    "Specify the directory of the review cache, \
+                    where the reviewed kyokus are kept to be reused as long as \
+                    the kyoku, the actor, the engine and the tactics are the same. \
+                    Default value is \"akochan-reviewer\" in the cache directory of the user, \
+                    such as \"~/.cache\" on Linux",
+                ),
+        )
+        .arg(Arg::with_name("no-cache").long("no-cache").help(
+            "Neither read nor write the review cache. \
+                    The cache tells akochan apart by its files and tactics, \
+                    but --engine pipe only by --engine-cmd and --engine-id, \
+                    so it is not used for --engine pipe without --engine-id",
+        ))
         .arg(
             Arg::with_name("verbose")
                 .short("v")
                 .long("verbose")
                 .help("Use verbose output"),
         )
+        .subcommand(
+            SubCommand::with_name("prune-cache")
+                .about("Remove entries from the review cache in --cache-dir")
+                .arg(
+                    Arg::with_name("older-than")
+                        .long("older-than")
+                        .takes_value(true)
+                        .value_name("DAYS")
+                        .validator(|v| {
+                            v.parse::<u64>()
+                                .map(|_| ())
+                                .map_err(|err| format!("DAYS must be a number: {}", err))
+                        })
+                        .help(
+                            "Only remove the entries not used for DAYS days. \
+                            If not specified, remove all",
+                        ),
+                ),
+        )
         .get_matches();
 
     // load options
     let arg_in_file = matches.value_of_os("in-file");
+    let arg_in_format = matches.value_of("in-format");
     let arg_out_file = matches.value_of_os("out-file");
-    let arg_tenhou_id = matches.value_of("tenhou-id");
+    let arg_tenhou_ref = matches
+        .value_of("tenhou-id")
+        .map(|s| s.parse::<TenhouLogRef>())
+        .transpose()
+        .context("failed to parse tenhou log ID")?;
+    let arg_tenhou_id = arg_tenhou_ref.as_ref().map(|r| r.id.as_str());
     let arg_tenhou_out = matches.value_of_os("tenhou-out");
     let arg_mjai_out = matches.value_of_os("mjai-out");
     let arg_tenhou_ids_file = matches.value_of_os("tenhou-ids-file");
     let arg_out_dir = matches.value_of_os("out-dir");
+    let arg_batch_review = matches.is_present("batch-review");
+    let arg_player_name = matches.value_of("player-name");
+    let arg_engine = matches.value_of("engine").unwrap_or("akochan");
+    let arg_engine_cmd = matches.value_of("engine-cmd");
+    let arg_engine_id = matches.value_of("engine-id");
     let arg_akochan_exe = matches.value_of_os("akochan-exe");
     let arg_akochan_dir = matches.value_of_os("akochan-dir");
     let arg_tactics_config = matches.value_of_os("tactics-config");
@@ -281,18 +441,176 @@ fn main() -> Result<()> {
     let arg_kyokus = matches.value_of("kyokus");
     let arg_use_ranking_exp = matches.is_present("use-ranking-exp");
     let arg_without_reviewer = matches.is_present("without-reviewer");
+    let arg_anonymize_salt = matches.value_of("anonymize-salt");
+    let arg_anonymize = matches.is_present("anonymize") || arg_anonymize_salt.is_some();
+    let anonymizer = match arg_anonymize_salt {
+        Some(salt) => Anonymizer::Hash {
+            salt: salt.to_owned(),
+        },
+        None => Anonymizer::Seat,
+    };
     let arg_no_open = matches.is_present("no-open");
     let arg_no_review = matches.is_present("no-review");
     let arg_json = matches.is_present("json");
+    let arg_jobs = value_t!(matches, "jobs", usize).unwrap_or(1);
     let arg_full = matches.is_present("full");
     let arg_verbose = matches.is_present("verbose");
+    let arg_no_cache = matches.is_present("no-cache");
+    let cache_dir = matches
+        .value_of_os("cache-dir")
+        .map(PathBuf::from)
+        .or_else(|| Some(dirs::cache_dir()?.join(PKG_NAME)));
+
+    // the pipe engine takes its tactics in --engine-cmd, if any.
+    if arg_engine == "pipe" {
+        if let Some(flag) = ["tactics-config", "pt", "use-ranking-exp"]
+            .iter()
+            .find(|&&f| matches.is_present(f))
+        {
+            return Err(anyhow!(
+                "--{} is for akochan only and cannot be used with --engine pipe",
+                flag
+            ));
+        }
+    } else if arg_engine_id.is_some() {
+        return Err(anyhow!(
+            "--engine-id is for --engine pipe only, akochan is told apart by its files"
+        ));
+    }
+
+    if let Some(sub_matches) = matches.subcommand_matches("prune-cache") {
+        let older_than = value_t!(sub_matches, "older-than", u64)
+            .ok()
+            .map(|days| Duration::from_secs(days * 24 * 60 * 60));
+        let cache_dir = cache_dir
+            .as_ref()
+            .context("no cache directory is found, specify --cache-dir")?;
+        let stats = cache::prune(cache_dir, older_than).context("failed to prune cache")?;
+        log!(
+            "removed {} entries ({} bytes), kept {}",
+            stats.removed,
+            stats.removed_bytes,
+            stats.kept,
+        );
+        return Ok(());
+    }
+
+    // set up the engine. the temp tactics config must be cleaned up after
+    // the review.
+    let new_engine = || -> Result<EngineSetup> {
+        // opt-in pt
+        let pt_opt: Option<Vec<i32>> = if arg_use_ranking_exp {
+            Some(vec![-1, -2, -3, -4])
+        } else {
+            arg_pt.map(|pt| pt.split(',').map(|p| p.parse::<i32>().unwrap()).collect())
+        };
+
+        match arg_engine {
+            "pipe" => {
+                let mut cmd = arg_engine_cmd
+                    .context("--engine-cmd is required for --engine pipe")?
+                    .split_whitespace();
+                let program = Path::new(cmd.next().context("--engine-cmd is empty")?);
+                let name = program
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "pipe".to_owned());
+
+                let engine = PipeEngine::new(&name, program, cmd, arg_verbose);
+                Ok(EngineSetup {
+                    engine,
+                    pt: None,
+                    tmp_tactics_path: None,
+                    id_name: arg_engine_id
+                        .map(|id| format!("pipe {} {}", arg_engine_cmd.unwrap_or_default(), id)),
+                    id_files: Some(program.to_owned())
+                        .filter(|p| p.is_file())
+                        .into_iter()
+                        .collect(),
+                    id_config: None,
+                })
+            }
+            _ => akochan_engine(
+                arg_akochan_exe,
+                arg_akochan_dir,
+                arg_tactics_config,
+                pt_opt,
+                arg_verbose,
+            ),
+        }
+    };
+    let new_cache = |setup: &EngineSetup| -> Result<Option<ReviewCache>> {
+        if arg_no_cache {
+            return Ok(None);
+        }
+        let cache_dir = match &cache_dir {
+            Some(dir) => dir,
+            None => {
+                log!("warning: no cache directory is found, reviewing without cache");
+                return Ok(None);
+            }
+        };
+        if setup.id_name.is_none() {
+            log!("warning: --engine pipe has no --engine-id, reviewing without cache");
+            return Ok(None);
+        }
+        if arg_verbose {
+            log!("cache_dir: {:?}", cache_dir);
+        }
+        let cache = setup
+            .cache(cache_dir)
+            .context("failed to identify the engine for cache")?;
+        Ok(Some(cache))
+    };
 
     if let Some(tenhou_ids_file) = arg_tenhou_ids_file {
         let out_dir_name = arg_out_dir
             .map(PathBuf::from)
             .unwrap_or_else(|| PathBuf::from("."));
 
-        return batch_download(&out_dir_name, Path::new(tenhou_ids_file));
+        if !arg_batch_review {
+            return batch_download(&out_dir_name, Path::new(tenhou_ids_file));
+        }
+
+        let setup = new_engine()?;
+        let cache = new_cache(&setup)?;
+        let version = format!("v{} ({})", PKG_VERSION, GIT_HASH);
+        let opts = BatchOptions {
+            engine: setup.engine.clone(),
+            jobs: arg_jobs,
+            cache: cache.as_ref(),
+            pt: setup.pt.as_deref(),
+            actor: arg_actor.ok(),
+            player_name: arg_player_name,
+            kyokus: arg_kyokus,
+            anonymizer: Some(&anonymizer).filter(|_| arg_anonymize),
+            sanma: arg_engine != "akochan",
+            full: arg_full,
+            verbose: arg_verbose,
+            version: &version,
+        };
+        let fetch = |tenhou_id: &str| -> Result<tenhou::RawLog> {
+            log!("downloading tenhou log {} ...", tenhou_id);
+            let log_stream = download_tenhou_log(tenhou_id)
+                .with_context(|| format!("failed to download tenhou log ID={:?}", tenhou_id))?;
+            json::from_reader(log_stream).context("failed to parse tenhou log")
+        };
+        let index_result = batch_review(&out_dir_name, Path::new(tenhou_ids_file), fetch, &opts);
+
+        // clean up
+        if let Some(tmp_path) = &setup.tmp_tactics_path {
+            fs::remove_file(tmp_path)
+                .with_context(|| format!("failed to clean up temp file {:?}", tmp_path))?;
+        }
+
+        let index_path = index_result?;
+        if !arg_no_open {
+            opener::open(&index_path)
+                .with_context(|| format!("failed to open index page {:?}", index_path))?;
+        }
+
+        log!("done");
+        return Ok(());
     }
 
     // get log reader, can be from a file, from stdin, or from HTTP stream
@@ -301,8 +619,9 @@ fn main() -> Result<()> {
             let log_stream = download_tenhou_log(tenhou_id)
                 .with_context(|| format!("failed to download tenhou log ID={:?}", tenhou_id))?;
 
-            // handle --tenhou-out
-            if let Some(tenhou_out) = arg_tenhou_out {
+            // handle --tenhou-out, which is written after parsing instead
+            // when anonymizing.
+            if let Some(tenhou_out) = arg_tenhou_out.filter(|_| !arg_anonymize) {
                 if tenhou_out != "-" {
                     let tenhou_out_file = File::create(tenhou_out).with_context(|| {
                         format!("failed to create download out file {:?}", tenhou_out)
@@ -333,12 +652,32 @@ fn main() -> Result<()> {
     let begin_parse_log = chrono::Local::now();
     log!("parsing tenhou log...");
     let raw_log = {
-        let mut l: tenhou::RawLog =
-            json::from_reader(log_reader).context("failed to parse tenhou log")?;
+        let mut l: tenhou::RawLog = match arg_in_format {
+            Some("mjlog") => {
+                let log = mjlog::parse_reader(log_reader).context("failed to parse mjlog")?;
+                tenhou::RawLog::from(log)
+            }
+            Some("majsoul") => {
+                let log = majsoul::parse_reader(log_reader)
+                    .context("failed to parse Mahjong Soul record")?;
+                tenhou::RawLog::from(log)
+            }
+            _ => json::from_reader(log_reader).context("failed to parse tenhou log")?,
+        };
 
-        // filter kyokus
-        if let Some(s) = arg_kyokus {
-            let filter = s.parse().context("failed to parse kyoku filter")?;
+        if arg_anonymize {
+            l.anonymize(&anonymizer);
+            if let Some(tenhou_out) = arg_tenhou_out.filter(|_| arg_tenhou_id.is_some()) {
+                write_raw_log(&l, tenhou_out)?;
+            }
+        }
+
+        // filter kyokus, where --kyokus takes precedence over "&ts=" in the url
+        let filter = match arg_kyokus {
+            Some(s) => Some(s.parse().context("failed to parse kyoku filter")?),
+            None => arg_tenhou_ref.as_ref().and_then(TenhouLogRef::kyoku_filter),
+        };
+        if let Some(filter) = filter {
             l.filter_kyokus(&filter);
         }
 
@@ -350,7 +689,7 @@ fn main() -> Result<()> {
     // See https://manishearth.github.io/blog/2017/04/13/prolonging-temporaries-in-rust/
     // for the technique of extending the lifetime of temp var here.
     let cloned_raw_log;
-    let splited_raw_logs = if !arg_without_reviewer {
+    let mut splited_raw_logs = if !arg_without_reviewer {
         cloned_raw_log = raw_log.clone();
         Some(cloned_raw_log.split_by_kyoku())
     } else {
@@ -359,13 +698,24 @@ fn main() -> Result<()> {
 
     // convert from RawLog to Log.
     // it moves raw_log.
-    let log = tenhou::Log::from(raw_log);
+    let mut log = tenhou::Log::from(raw_log);
 
     // convert from tenhou::Log to Vec<mjai::Event>
     let begin_convert_log = chrono::Local::now();
     log!("converting to mjai events...");
-    let events =
-        convlog::tenhou_to_mjai(&log).context("failed to convert tenhou log into mjai format")?;
+    let (events, skipped_kyokus) = convert_lenient(&log);
+    if events.len() <= 2 {
+        return Err(anyhow!("failed to convert any kyoku into mjai format"));
+    }
+
+    // drop the skipped kyokus so that the splited logs still line up with the
+    // events.
+    for &idx in skipped_kyokus.iter().rev() {
+        log.kyokus.remove(idx);
+        if let Some(logs) = &mut splited_raw_logs {
+            logs.remove(idx);
+        }
+    }
 
     // handle --mjai-out
     if let Some(mjai_out) = arg_mjai_out {
@@ -388,96 +738,35 @@ fn main() -> Result<()> {
         return Ok(());
     }
 
-    // get actor
-    let actor = arg_actor.unwrap_or_else(|e| e.exit());
-
-    // get paths
-    let akochan_exe = {
-        let path = arg_akochan_exe.map(PathBuf::from).unwrap_or_else(|| {
-            let mut path = if let Ok(current_dir) = env::current_dir() {
-                current_dir
-            } else {
-                PathBuf::from(".")
-            };
-
-            path.push("akochan");
-            path.push("system.exe");
-
-            path
-        });
-
-        canonicalize(&path)
-            .with_context(|| format!("failed to canonicalize akochan_exe path {:?}", path))?
-    };
-    let akochan_dir = {
-        let path = arg_akochan_dir.map(PathBuf::from).unwrap_or_else(|| {
-            let mut dir = akochan_exe.clone();
-            dir.pop();
-            dir
-        });
-
-        canonicalize(&path)
-            .with_context(|| format!("failed to canonicalize akochan_dir path {:?}", path))?
-    };
-    let (tactics_file_path, tactics) = {
-        let path = arg_tactics_config
-            .map(PathBuf::from)
-            .unwrap_or_else(|| "tactics.json".into());
-
-        let canon_path = canonicalize(&path)
-            .with_context(|| format!("failed to canonicalize tactics_config path {:?}", path))?;
-
-        // load tactics_config for metadata
-        let tactics_file = File::open(&canon_path)
-            .with_context(|| format!("failed to open tactics_config {:?}", canon_path))?;
-        let tactics_file_reader = BufReader::new(tactics_file);
-
-        let mut tactics_json: TacticsJson = json::from_reader(tactics_file_reader)
-            .with_context(|| format!("failed to parse tactics_config {:?}", canon_path))?;
-
-        // opt-in pt
-        let pt_opt = if arg_use_ranking_exp {
-            Some(vec![-1, -2, -3, -4])
-        } else if let Some(pt) = arg_pt {
-            Some(pt.split(',').map(|p| p.parse::<i32>().unwrap()).collect())
-        } else {
-            None
-        };
+    // get actor, falling back to "&tw=" in the url
+    let actor = arg_actor.unwrap_or_else(|e| {
+        arg_tenhou_ref
+            .as_ref()
+            .and_then(|r| r.actor)
+            .unwrap_or_else(|| e.exit())
+    });
+    if actor >= log.num_players() {
+        return Err(anyhow!("actor {} does not exist in a sanma log", actor));
+    }
+    if log.num_players() == 3 && arg_engine == "akochan" {
+        return Err(anyhow!(
+            "akochan does not support sanma, review it with --engine pipe instead"
+        ));
+    }
 
-        if let Some(pt) = pt_opt {
-            tactics_json
-                .tactics
-                .jun_pt
-                .iter_mut()
-                .zip(pt)
-                .for_each(|(o, n)| *o = n);
-
-            let mut tmp = NamedTempFile::new().context("failed to create temp file")?;
-            json::to_writer(&mut tmp, &tactics_json).context("failed to write to temp file")?;
-
-            let tmp_path = tmp
-                .into_temp_path()
-                .keep()
-                .context("failed to keep temp file")?;
-            let canon_tmp_path = canonicalize(&tmp_path)
-                .with_context(|| format!("failed to canonicalize temp file path {:?}", tmp_path))?;
-
-            (canon_tmp_path, tactics_json.tactics)
-        } else {
-            (canon_path, tactics_json.tactics)
-        }
-    };
+    let setup = new_engine()?;
+    let cache = new_cache(&setup)?;
 
     log!("players: {:?}", log.names);
     log!("target: {}", log.names[actor as usize]);
     log!("start review, this may take serval minutes...");
 
-    // do the review
+    // do the review, with an engine process for each job
     let begin_review = chrono::Local::now();
-    let review_result = review(
-        &akochan_exe,
-        &akochan_dir,
-        &tactics_file_path,
+    let mut engines = vec![setup.engine.clone(); arg_jobs];
+    let review_result = review_with(
+        &mut engines,
+        cache.as_ref(),
         &events,
         actor,
         arg_full,
@@ -486,9 +775,9 @@ fn main() -> Result<()> {
     .context("failed to review log")?;
 
     // clean up
-    if arg_pt.is_some() {
-        fs::remove_file(&tactics_file_path)
-            .with_context(|| format!("failed to clean up temp file {:?}", tactics_file_path))?;
+    if let Some(tmp_path) = &setup.tmp_tactics_path {
+        fs::remove_file(tmp_path)
+            .with_context(|| format!("failed to clean up temp file {:?}", tmp_path))?;
     }
 
     // determine whether the file can be opened after writing
@@ -501,7 +790,8 @@ fn main() -> Result<()> {
             }
         }
         _ => {
-            if let Some(tenhou_id) = arg_tenhou_id {
+            // the log ID links to the real names.
+            if let Some(tenhou_id) = arg_tenhou_id.filter(|_| !arg_anonymize) {
                 Some(OsString::from(format!("{}&tw={}.html", tenhou_id, actor)))
             } else {
                 Some(OsString::from("report.html"))
@@ -523,19 +813,29 @@ fn main() -> Result<()> {
     let convert_time = (begin_review - begin_convert_log).to_std()?;
     let review_time = (now - begin_review).to_std()?;
     let meta = Metadata {
-        pt: &tactics.jun_pt,
-        game_length: &log.game_length.to_string(),
+        engine: setup.engine.name(),
+        pt: setup.pt.as_deref(),
+        game_length: &game_length_of(&log),
         parse_time,
         convert_time,
         review_time,
-        tenhou_id: arg_tenhou_id,
+        // the log ID leads to the original log with real names.
+        tenhou_id: arg_tenhou_id.filter(|_| !arg_anonymize),
+        players: players_of(&log),
+        lobby: &log.lobby,
         total_reviewed: review_result.total_reviewed,
         total_entries: review_result.total_entries,
         version: &format!("v{} ({})", PKG_VERSION, GIT_HASH),
     };
 
     // render the HTML report page or JSON
-    let view = View::new(&review_result.kyokus, actor, &meta, splited_raw_logs);
+    let view = View::new(
+        &review_result.kyokus,
+        actor,
+        log.num_players(),
+        &meta,
+        splited_raw_logs,
+    );
     if arg_json {
         log!("writing output...");
         json::to_writer(&mut out, &view).context("failed to write JSON result")?;
@@ -561,14 +861,183 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// An engine to review with, along with what it depends on.
+struct EngineSetup {
+    engine: PipeEngine,
+    pt: Option<Vec<i32>>,
+    /// The temp tactics config written for the pt override, which must be
+    /// cleaned up after the review.
+    tmp_tactics_path: Option<PathBuf>,
+
+    /// What the cache is keyed by, see `cache::engine_id`. The name is `None`
+    /// if the engine cannot be told apart, which is reviewed without cache.
+    id_name: Option<String>,
+    id_files: Vec<PathBuf>,
+    id_config: Option<json::Value>,
+}
+
+impl EngineSetup {
+    fn cache(&self, dir: &Path) -> Result<ReviewCache> {
+        let files: Vec<_> = self.id_files.iter().map(PathBuf::as_path).collect();
+        let id_name = self
+            .id_name
+            .as_deref()
+            .context("the engine cannot be told apart")?;
+        let engine_id = cache::engine_id(id_name, &files, self.id_config.as_ref())?;
+        Ok(ReviewCache::new(dir, &engine_id))
+    }
+}
+
+/// Sets up akochan with the tactics in use.
+fn akochan_engine(
+    arg_akochan_exe: Option<&OsStr>,
+    arg_akochan_dir: Option<&OsStr>,
+    arg_tactics_config: Option<&OsStr>,
+    pt_opt: Option<Vec<i32>>,
+    verbose: bool,
+) -> Result<EngineSetup> {
+    // get paths
+    let akochan_exe = {
+        let path = arg_akochan_exe.map(PathBuf::from).unwrap_or_else(|| {
+            let mut path = if let Ok(current_dir) = env::current_dir() {
+                current_dir
+            } else {
+                PathBuf::from(".")
+            };
+
+            path.push("akochan");
+            path.push("system.exe");
+
+            path
+        });
+
+        canonicalize(&path)
+            .with_context(|| format!("failed to canonicalize akochan_exe path {:?}", path))?
+    };
+    let akochan_dir = {
+        let path = arg_akochan_dir.map(PathBuf::from).unwrap_or_else(|| {
+            let mut dir = akochan_exe.clone();
+            dir.pop();
+            dir
+        });
+
+        canonicalize(&path)
+            .with_context(|| format!("failed to canonicalize akochan_dir path {:?}", path))?
+    };
+
+    let path = arg_tactics_config
+        .map(PathBuf::from)
+        .unwrap_or_else(|| "tactics.json".into());
+
+    let canon_path = canonicalize(&path)
+        .with_context(|| format!("failed to canonicalize tactics_config path {:?}", path))?;
+
+    // load tactics_config for metadata
+    let tactics_file = File::open(&canon_path)
+        .with_context(|| format!("failed to open tactics_config {:?}", canon_path))?;
+    let tactics_file_reader = BufReader::new(tactics_file);
+
+    let mut tactics_json: TacticsJson = json::from_reader(tactics_file_reader)
+        .with_context(|| format!("failed to parse tactics_config {:?}", canon_path))?;
+
+    // akochan loads the AI from the library in its directory, and the
+    // parameters of the AI from "params" in it.
+    let mut id_files = vec![akochan_exe.clone()];
+    for lib in &["libai.so", "ai.dll", "libai.dylib"] {
+        let lib_path = akochan_dir.join(lib);
+        if lib_path.is_file() {
+            id_files.push(lib_path);
+        }
+    }
+    let params_dir = akochan_dir.join("params");
+    if params_dir.is_dir() {
+        files_under(&params_dir, &mut id_files)?;
+    }
+
+    let (tactics_path, tmp_tactics_path) = if let Some(pt) = pt_opt {
+        tactics_json
+            .tactics
+            .jun_pt
+            .iter_mut()
+            .zip(pt)
+            .for_each(|(o, n)| *o = n);
+
+        let mut tmp = NamedTempFile::new().context("failed to create temp file")?;
+        json::to_writer(&mut tmp, &tactics_json).context("failed to write to temp file")?;
+
+        let tmp_path = tmp
+            .into_temp_path()
+            .keep()
+            .context("failed to keep temp file")?;
+        let canon_tmp_path = canonicalize(&tmp_path)
+            .with_context(|| format!("failed to canonicalize temp file path {:?}", tmp_path))?;
+
+        (canon_tmp_path.clone(), Some(canon_tmp_path))
+    } else {
+        (canon_path, None)
+    };
+
+    Ok(EngineSetup {
+        engine: PipeEngine::akochan(&akochan_exe, &akochan_dir, &tactics_path, verbose),
+        pt: Some(tactics_json.tactics.jun_pt.to_vec()),
+        tmp_tactics_path,
+        id_name: Some("akochan".to_owned()),
+        id_files,
+        id_config: Some(json::to_value(&tactics_json).context("failed to serialize tactics")?),
+    })
+}
+
+/// Collects the files under `dir` recursively, sorted by path.
+fn files_under(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let mut paths = fs::read_dir(dir)
+        .with_context(|| format!("failed to read {:?}", dir))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()
+        .with_context(|| format!("failed to read {:?}", dir))?;
+    paths.sort();
+
+    for path in paths {
+        if path.is_dir() {
+            files_under(&path, files)?;
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Writes the log in tenhou.net/6 format to `out`, or stdout if it is "-".
+fn write_raw_log(raw_log: &tenhou::RawLog, out: &OsStr) -> Result<()> {
+    let w: Box<dyn Write> = if out != "-" {
+        let file = File::create(out)
+            .with_context(|| format!("failed to create download out file {:?}", out))?;
+        Box::new(file)
+    } else {
+        Box::new(io::stdout())
+    };
+    json::to_writer(w, raw_log)
+        .with_context(|| format!("failed to write to download out file {:?}", out))
+}
+
 fn batch_download(out_dir_name: &Path, tenhou_ids_file: &Path) -> Result<()> {
-    fs::create_dir_all(&out_dir_name)
+    fs::create_dir_all(out_dir_name)
         .with_context(|| format!("failed to create {:?}", out_dir_name))?;
 
     log!("tenhou_ids_file: {:?}", tenhou_ids_file);
 
     for line in BufReader::new(File::open(tenhou_ids_file)?).lines() {
-        let tenhou_id = line?;
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        // the actor may follow for --batch-review.
+        let tenhou_id = line
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .parse::<TenhouLogRef>()
+            .with_context(|| format!("failed to parse tenhou log ID {:?}", line))?
+            .id;
 
         log!("downloading tenhou log {} ...", tenhou_id);
         let log_stream = download_tenhou_log(&tenhou_id)
@@ -580,8 +1049,7 @@ fn batch_download(out_dir_name: &Path, tenhou_ids_file: &Path) -> Result<()> {
         let log = tenhou::Log::from(raw_log);
 
         log!("converting to mjai events...");
-        let events = convlog::tenhou_to_mjai(&log)
-            .context("failed to convert tenhou log into mjai format")?;
+        let (events, _) = convert_lenient(&log);
 
         let mjai_out = {
             let mut p = out_dir_name.to_owned();
@@ -1,12 +1,18 @@
 use std::time::Duration;
 
+use convlog::tenhou::{self, LobbyInfo};
 use serde::Serialize;
 
 #[derive(Serialize)]
 pub struct Metadata<'a> {
-    pub pt: &'a [i32; 4],
+    pub engine: &'a str,
+    /// None if the engine does not take pt.
+    pub pt: Option<&'a [i32]>,
     pub game_length: &'a str,
     pub tenhou_id: Option<&'a str>,
+    /// By seat, without the absent one in sanma.
+    pub players: Vec<Player<'a>>,
+    pub lobby: &'a LobbyInfo,
 
     #[serde(with = "humantime_serde")]
     pub parse_time: Duration,
@@ -20,3 +26,33 @@ pub struct Metadata<'a> {
 
     pub version: &'a str,
 }
+
+#[derive(Serialize)]
+pub struct Player<'a> {
+    pub name: &'a str,
+    pub dan: Option<&'a str>,
+    pub rate: Option<f64>,
+}
+
+/// The game length shown in the report, prefixed by "三麻" in sanma.
+pub fn game_length_of(log: &tenhou::Log) -> String {
+    if log.rule.is_sanma {
+        format!("三麻{}", log.rule.game_length)
+    } else {
+        log.rule.game_length.to_string()
+    }
+}
+
+/// Players by seat, without the absent one in sanma.
+pub fn players_of(log: &tenhou::Log) -> Vec<Player<'_>> {
+    log.names
+        .iter()
+        .zip(&log.players)
+        .take(log.num_players() as usize)
+        .map(|(name, player)| Player {
+            name,
+            dan: player.dan.as_deref(),
+            rate: player.rate,
+        })
+        .collect()
+}
@@ -1,5 +1,5 @@
 use crate::metadata::Metadata;
-use crate::review::KyokuReview;
+use crate::review::{KyokuReview, Review};
 
 use std::collections::HashMap;
 use std::convert::TryFrom;
@@ -21,6 +21,7 @@ static TEMPLATES: Lazy<Tera> = Lazy::new(|| {
         ("macros.html", include_str!("../templates/macros.html")),
         ("pai.svg", include_str!("../assets/pai.svg")),
         ("report.html", include_str!("../templates/report.html")),
+        ("index.html", include_str!("../templates/index.html")),
     ])
     .expect("failed to parse template");
 
@@ -72,6 +73,7 @@ where
 {
     kyokus: &'a [KyokuReview],
     target_actor: u8,
+    num_players: u8,
     #[serde(skip_serializing_if = "Option::is_none")]
     splited_logs: Option<L>,
     metadata: &'a Metadata<'a>,
@@ -85,12 +87,14 @@ where
     pub fn new(
         kyoku_reviews: &'a [KyokuReview],
         target_actor: u8,
+        num_players: u8,
         metadata: &'a Metadata<'a>,
         splited_logs: Option<L>,
     ) -> Self {
         Self {
             kyokus: kyoku_reviews,
             target_actor,
+            num_players,
             splited_logs,
             metadata,
         }
@@ -100,7 +104,7 @@ where
     where
         W: Write,
     {
-        let ctx = tera::Context::from_serialize(&self)?;
+        let ctx = tera::Context::from_serialize(self)?;
         let result =
             TEMPLATES.render("report.html", &ctx).with_context(|| {
                 match json::to_string(&self) {
@@ -108,7 +112,102 @@ where
                     Err(err) => format!("even serializations failed: {}", err),
                 }
             })?;
-        w.write_all(&result.as_bytes())?;
+        w.write_all(result.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+/// A line of the index page of a batch review.
+#[derive(Debug, Clone, Serialize)]
+pub struct GameSummary {
+    /// `None` if it is not to be shown, such as when anonymizing.
+    pub tenhou_id: Option<String>,
+    pub actor: Option<u8>,
+    pub name: Option<String>,
+    /// Path of the report, relative to the index page.
+    pub report: Option<String>,
+
+    pub total_reviewed: usize,
+    pub total_entries: usize,
+    pub agreement_rate: Option<f64>,
+    pub ev_loss: f64,
+
+    /// Set if the game failed to review.
+    pub error: Option<String>,
+}
+
+impl GameSummary {
+    pub fn new(tenhou_id: Option<&str>, name: &str, report: &str, review: &Review) -> Self {
+        Self {
+            tenhou_id: tenhou_id.map(ToOwned::to_owned),
+            actor: Some(review.target_actor),
+            name: Some(name.to_owned()),
+            report: Some(report.to_owned()),
+            total_reviewed: review.total_reviewed,
+            total_entries: review.total_entries,
+            agreement_rate: review.agreement_rate(),
+            ev_loss: review.ev_loss(),
+            error: None,
+        }
+    }
+
+    pub fn failed(tenhou_id: Option<&str>, error: &anyhow::Error) -> Self {
+        Self {
+            tenhou_id: tenhou_id.map(ToOwned::to_owned),
+            actor: None,
+            name: None,
+            report: None,
+            total_reviewed: 0,
+            total_entries: 0,
+            agreement_rate: None,
+            ev_loss: 0.,
+            error: Some(format!("{:#}", error)),
+        }
+    }
+}
+
+/// The index page of a batch review, linking to the report of each game.
+#[derive(Serialize)]
+pub struct IndexView<'a> {
+    games: &'a [GameSummary],
+    total_games: usize,
+    total_reviewed: usize,
+    total_entries: usize,
+    agreement_rate: Option<f64>,
+    ev_loss: f64,
+    version: &'a str,
+}
+
+impl<'a> IndexView<'a> {
+    pub fn new(games: &'a [GameSummary], version: &'a str) -> Self {
+        let reviewed = games.iter().filter(|g| g.error.is_none());
+        let total_reviewed = reviewed.clone().map(|g| g.total_reviewed).sum();
+        let total_entries = reviewed.clone().map(|g| g.total_entries).sum();
+        let agreement_rate = if total_reviewed > 0 {
+            Some(1. - total_entries as f64 / total_reviewed as f64)
+        } else {
+            None
+        };
+
+        Self {
+            games,
+            total_games: reviewed.clone().count(),
+            total_reviewed,
+            total_entries,
+            agreement_rate,
+            ev_loss: reviewed.map(|g| g.ev_loss).sum(),
+            version,
+        }
+    }
+
+    pub fn render<W>(&self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let ctx = tera::Context::from_serialize(self)?;
+        let result = TEMPLATES.render("index.html", &ctx)?;
+        w.write_all(result.as_bytes())?;
 
         Ok(())
     }
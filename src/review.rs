@@ -1,37 +1,62 @@
+use crate::engine::ReviewEngine;
 use crate::log;
 use crate::state::State;
 
-use std::io::prelude::*;
-use std::io::BufReader;
-use std::path::Path;
-use std::process::{Command, Stdio};
+use std::ops::Range;
+use std::thread;
 
 use anyhow::anyhow;
 use anyhow::{Context, Result};
 use convlog::mjai::Event;
-use convlog::Pai;
+use convlog::score::{self, HandValue};
+use convlog::shanten::Ukeire;
+use convlog::{tenhou, Board, Pai, Resolution};
 use serde::{Deserialize, Serialize};
-use serde_json as json;
 
 pub struct Review {
+    pub target_actor: u8,
     pub total_reviewed: usize,
     pub total_entries: usize,
     pub kyokus: Vec<KyokuReview>,
 }
 
-#[derive(Debug, Clone, Default, Serialize)]
+impl Review {
+    /// The ratio of the reviewed moves that agree with the engine, or `None`
+    /// if nothing is reviewed.
+    pub fn agreement_rate(&self) -> Option<f64> {
+        if self.total_reviewed == 0 {
+            return None;
+        }
+        Some(1. - self.total_entries as f64 / self.total_reviewed as f64)
+    }
+
+    /// The sum of the EV loss of all the entries, skipping those the engine
+    /// does not evaluate the actual move of.
+    pub fn ev_loss(&self) -> f64 {
+        self.kyokus
+            .iter()
+            .flat_map(|k| &k.entries)
+            .filter_map(Entry::ev_loss)
+            .sum()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct KyokuReview {
     pub kyoku: u8, // in tenhou.net/6 format, counts from 0
     pub honba: u8,
     pub end_status: Vec<Event>, // must be either multiple Horas or one Ryukyoku
 
+    pub total_reviewed: usize,
+
     pub entries: Vec<Entry>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Entry {
     pub junme: u8,
     pub actor: u8,
+    #[serde(deserialize_with = "Pai::deserialize_mjai_str")]
     pub pai: Pai,
     pub is_kakan: bool, // for chankan
     pub state: State,
@@ -39,9 +64,64 @@ pub struct Entry {
     pub expected: Vec<Event>, // at most 2 events
     pub actual: Vec<Event>,   // at most 2 events
 
+    /// Shanten and ukeire after each move, if it leaves a tehai waiting.
+    pub expected_ukeire: Option<Ukeire>,
+    pub actual_ukeire: Option<Ukeire>,
+
     pub details: Vec<DetailedAction>,
 }
 
+impl Entry {
+    /// How much `pt_exp_total` the actual move loses to the best one, or
+    /// `None` if the engine does not evaluate the actual move.
+    pub fn ev_loss(&self) -> Option<f64> {
+        let best = self.details.first()?.review.pt_exp_total?;
+        let actual = self
+            .details
+            .iter()
+            .find(|d| is_same_move(&self.actual, &d.moves))?
+            .review
+            .pt_exp_total?;
+        Some((best - actual).max(0.))
+    }
+}
+
+/// Returns true if `moves` of a candidate are exactly the `actual` move,
+/// event by event. Unlike `compare_action`, nothing innocent is taken as the
+/// same, while a dahai only has to discard the same pai, and a hora or
+/// ryukyoku does not have to carry the fields akochan never sends.
+fn is_same_move(actual: &[Event], moves: &[Event]) -> bool {
+    actual.len() == moves.len()
+        && actual.iter().zip(moves).all(|(a, m)| match (a, m) {
+            (
+                Event::Dahai {
+                    actor: a_actor,
+                    pai: a_pai,
+                    ..
+                },
+                Event::Dahai {
+                    actor: m_actor,
+                    pai: m_pai,
+                    ..
+                },
+            ) => a_actor == m_actor && a_pai == m_pai,
+            (
+                Event::Hora {
+                    actor: a_actor,
+                    target: a_target,
+                    ..
+                },
+                Event::Hora {
+                    actor: m_actor,
+                    target: m_target,
+                    ..
+                },
+            ) => a_actor == m_actor && a_target == m_target,
+            (Event::Ryukyoku { .. }, Event::Ryukyoku { .. }) => true,
+            _ => a == m,
+        })
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Stat {
     total_houjuu_hai_prob_now: Option<f64>,
@@ -56,54 +136,62 @@ pub struct DetailedAction {
     pub review: Stat,
 }
 
-pub fn review(
-    akochan_exe: &Path,
-    akochan_dir: &Path,
-    tactics_config: &Path,
+pub fn review<E>(
+    engine: &mut E,
     events: &[Event],
     target_actor: u8,
     full: bool,
     verbose: bool,
-) -> Result<Review> {
-    let mut kyoku_reviews = vec![];
-
-    let target_actor_string = target_actor.to_string();
-    let args = &[
-        "pipe_detailed".as_ref(),
-        tactics_config,
-        target_actor_string.as_ref(),
-    ];
+) -> Result<Review>
+where
+    E: ReviewEngine + ?Sized,
+{
+    review_some(engine, events, &|_| true, target_actor, full, verbose)
+}
 
-    if verbose {
-        log!("$ cd {:?}", akochan_dir);
-        log!(
-            "$ {:?}{}",
-            akochan_exe,
-            args.iter()
-                .fold("".to_owned(), |acc, p| format!("{} {:?}", acc, p))
-        );
+/// Reviews the kyokus for which `to_review` returns true with their index
+/// among the kyokus of `events`, only replaying the others to the engine.
+fn review_some<E>(
+    engine: &mut E,
+    events: &[Event],
+    to_review: &dyn Fn(usize) -> bool,
+    target_actor: u8,
+    full: bool,
+    verbose: bool,
+) -> Result<Review>
+where
+    E: ReviewEngine + ?Sized,
+{
+    engine
+        .start(target_actor)
+        .with_context(|| format!("failed to start {}", engine.name()))?;
+
+    // the engine must not be left in the game when the review fails halfway,
+    // or it cannot review another one.
+    match review_events(engine, events, to_review, target_actor, full, verbose) {
+        Ok(review) => {
+            engine.finish()?;
+            Ok(review)
+        }
+        Err(err) => {
+            engine.abort();
+            Err(err)
+        }
     }
+}
 
-    let mut akochan = Command::new(akochan_exe)
-        .args(args)
-        .current_dir(Path::new(akochan_dir))
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::inherit())
-        .spawn()
-        .context("failed to spawn akochan")?;
-
-    let stdin = akochan
-        .stdin
-        .as_mut()
-        .context("failed to get stdin of akochan")?;
-    let mut stdout_lines = BufReader::new(
-        akochan
-            .stdout
-            .as_mut()
-            .context("failed to get stdout of akochan")?,
-    )
-    .lines();
+fn review_events<E>(
+    engine: &mut E,
+    events: &[Event],
+    to_review: &dyn Fn(usize) -> bool,
+    target_actor: u8,
+    full: bool,
+    verbose: bool,
+) -> Result<Review>
+where
+    E: ReviewEngine + ?Sized,
+{
+    let mut kyoku_reviews = vec![];
 
     let events_len = events.len();
     let mut total_entries = 0;
@@ -111,20 +199,44 @@ pub fn review(
 
     let mut kyoku_review = KyokuReview::default();
     let mut state = State::new(target_actor);
+    let mut board = Board::new();
+    // whether the board follows the events, which only decorates the review
+    // with ukeire and hand values, so the review goes on without them for the
+    // rest of the kyoku when it does not.
+    let mut board_synced = true;
     let mut junme = 0;
     let mut entries = vec![];
     let mut is_reached = false;
+    let mut kyoku_index = 0;
 
     for (i, event) in events.iter().enumerate() {
-        let to_write = json::to_string(event).unwrap();
-        writeln!(stdin, "{}", to_write).context("failed to write to akochan")?;
-        if verbose {
-            log!("> {}", to_write);
-        }
+        engine.feed(event)?;
+        let replaying = !to_review(kyoku_index);
+
+        // the board is needed as it was before the hora to value it.
+        let hand_value = board
+            .hora_context(event)
+            .filter(|_| board_synced)
+            .and_then(|ctx| score::calculate(&ctx));
 
         // upate the state
         state.update(event).context("failed to update state")?;
 
+        // start_kyoku sets up the whole position again.
+        if let Event::StartKyoku { .. } = event {
+            board_synced = true;
+        }
+        if board_synced {
+            if let Err(err) = board.update(event) {
+                log!(
+                    "warning: failed to update board, reviewing the rest of the kyoku \
+                    without ukeire and hand values: {}",
+                    err,
+                );
+                board_synced = false;
+            }
+        }
+
         // this match does two things:
         // 1. setting board metadata like bakaze, kyoku, honba, junme
         // 2. decide whether or not this event is a valid timing when we can review
@@ -147,14 +259,24 @@ pub fn review(
                 kyoku_review.entries = entries.clone();
                 entries.clear();
 
-                kyoku_reviews.push(kyoku_review.clone());
+                if !replaying {
+                    kyoku_reviews.push(kyoku_review.clone());
+                }
                 kyoku_review = KyokuReview::default();
+                kyoku_index += 1;
 
                 junme = 0;
                 continue;
             }
 
-            Event::Hora { .. } | Event::Ryukyoku { .. } => {
+            Event::Hora { .. } => {
+                kyoku_review
+                    .end_status
+                    .push(with_hand_value(event, hand_value));
+                continue;
+            }
+
+            Event::Ryukyoku { .. } => {
                 kyoku_review.end_status.push(event.clone());
                 continue;
             }
@@ -193,7 +315,8 @@ pub fn review(
         };
 
         log!(
-            "reviewing kyoku={} honba={} junme={} ({:.2}%)",
+            "{} kyoku={} honba={} junme={} ({:.2}%)",
+            if replaying { "replaying" } else { "reviewing" },
             kyoku_review.kyoku,
             kyoku_review.honba,
             junme,
@@ -207,18 +330,12 @@ pub fn review(
             ));
         }
 
-        // be careful, stdout_lines.next() may block.
-        let line = stdout_lines
-            .next()
-            .context("failed to read from akochan: unexpected EOF")?
-            .context("failed to read from akochan")?;
-        if verbose {
-            log!("< {}", line.trim());
+        // the engine answers the replayed ones as well, which must be read.
+        let actions = engine.candidates()?;
+        if replaying {
+            continue;
         }
 
-        let actions: Vec<DetailedAction> =
-            json::from_str(&line).context("failed to parse JSON output of akochan")?;
-
         if actions.is_empty() || actions.iter().any(|a| a.moves.is_empty()) {
             log!("WARNING: actions or some moves in actions is empty");
             continue;
@@ -228,6 +345,12 @@ pub fn review(
         // 1. it is not our turn and there is no chance to naki
         // 2. our state is reached and there is no chance to ankan
         // 3. 九種九牌
+        // 4. we declared nukidora, which is not a choice the engine can make
+        if let Some(&Event::Nukidora { actor, .. }) = events.get(i + 1) {
+            if actor == target_actor {
+                continue;
+            }
+        }
         if actions.len() == 1 {
             if is_reached {
                 continue;
@@ -240,9 +363,10 @@ pub fn review(
         let expected_action = &actions[0].moves; // best move
         let actual_action = next_action_for_compare(&events[(i + 1)..]);
 
-        let is_equal = compare_action(&actual_action, expected_action, target_actor)
+        let is_equal = compare_action(actual_action, expected_action, target_actor)
             .context("invalid state in event")?;
         total_reviewed += 1;
+        kyoku_review.total_reviewed += 1;
 
         if is_equal && !full {
             continue;
@@ -264,6 +388,16 @@ pub fn review(
             }
         };
 
+        let (expected_ukeire, actual_ukeire) = if board_synced {
+            let visible = board.visible_pais();
+            (
+                state.ukeire_after(expected_action, &visible),
+                state.ukeire_after(&actual_action_vec, &visible),
+            )
+        } else {
+            (None, None)
+        };
+
         let entry = Entry {
             junme,
             actor,
@@ -272,10 +406,35 @@ pub fn review(
             state: state.clone(),
             expected: expected_action.to_vec(),
             actual: actual_action_vec,
+            expected_ukeire,
+            actual_ukeire,
             details: actions,
         };
         if verbose {
-            log!("{:?}", entry);
+            let fuuros: Vec<_> = entry.state.fuuros.iter().map(|f| f.to_string()).collect();
+            log!(
+                "tehai={} fuuros=[{}] pai={} expected={:?} actual={:?}",
+                entry.state.tehai,
+                fuuros.join(" "),
+                entry.pai,
+                entry.expected,
+                entry.actual,
+            );
+            for (name, ukeire) in &[
+                ("expected", &entry.expected_ukeire),
+                ("actual", &entry.actual_ukeire),
+            ] {
+                if let Some(ukeire) = ukeire {
+                    let pais: Vec<_> = ukeire.pais.iter().map(|p| p.pai).collect();
+                    log!(
+                        "{}: shanten={} ukeire={} ({})",
+                        name,
+                        ukeire.shanten,
+                        ukeire.total,
+                        convlog::notation::display(&pais),
+                    );
+                }
+            }
         }
         entries.push(entry);
 
@@ -283,22 +442,182 @@ pub fn review(
         log!("review entry created (total {})", total_entries);
     }
 
-    let ecode = akochan.wait()?;
-    if !ecode.success() {
-        if let Some(code) = ecode.code() {
-            return Err(anyhow!("non-zero exit code: {}", code));
-        } else {
-            return Err(anyhow!("non-zero exit code: Process terminated by signal"));
-        }
-    }
-
     Ok(Review {
+        target_actor,
         total_reviewed,
         total_entries,
         kyokus: kyoku_reviews,
     })
 }
 
+/// Reviews with one engine per job at the same time, giving the same result
+/// as `review` with any one of them.
+///
+/// The kyokus are split into a run of kyokus for each job, see
+/// `review_kyokus`.
+pub fn review_parallel<E>(
+    engines: &mut [E],
+    events: &[Event],
+    target_actor: u8,
+    full: bool,
+    verbose: bool,
+) -> Result<Review>
+where
+    E: ReviewEngine + Send,
+{
+    let num_kyokus = kyoku_ranges(events).len();
+    if engines.len().min(num_kyokus) <= 1 {
+        let engine = engines.first_mut().context("no engine to review with")?;
+        return review(engine, events, target_actor, full, verbose);
+    }
+
+    let all: Vec<_> = (0..num_kyokus).collect();
+    let kyokus = review_kyokus(engines, events, &all, target_actor, full, verbose)?;
+    Ok(Review {
+        target_actor,
+        total_reviewed: kyokus.iter().map(|k| k.total_reviewed).sum(),
+        total_entries: kyokus.iter().map(|k| k.entries.len()).sum(),
+        kyokus,
+    })
+}
+
+/// Reviews the kyokus of `events` at `kyokus`, which are indices among them in
+/// ascending order, with one engine per job at the same time. Returns their
+/// reviews in the same order.
+///
+/// The kyokus are split into a run of kyokus for each job. Each engine replays
+/// the whole game before its run, as its answers may depend on any of it, and
+/// then reviews its run, so the result is the same as reviewing all of them
+/// with one engine. The engine still answers every replayed decision, so a job
+/// of later kyokus takes longer.
+pub(crate) fn review_kyokus<E>(
+    engines: &mut [E],
+    events: &[Event],
+    kyokus: &[usize],
+    target_actor: u8,
+    full: bool,
+    verbose: bool,
+) -> Result<Vec<KyokuReview>>
+where
+    E: ReviewEngine + Send,
+{
+    let kyoku_ranges = kyoku_ranges(events);
+    let suffix = match kyoku_ranges.last() {
+        Some(last) if !kyokus.is_empty() => &events[last.end..],
+        _ => return Ok(vec![]),
+    };
+    let jobs = engines.len().min(kyokus.len());
+    if jobs == 0 {
+        return Err(anyhow!("no engine to review with"));
+    }
+
+    let runs: Vec<_> = kyokus.chunks(kyokus.len().div_ceil(jobs)).collect();
+    let job_events: Vec<Vec<Event>> = runs
+        .iter()
+        .map(|run| {
+            let end = kyoku_ranges[run[run.len() - 1]].end;
+            let mut job_events = events[..end].to_vec();
+            job_events.extend_from_slice(suffix);
+            job_events
+        })
+        .collect();
+
+    let results: Vec<Result<Review>> = thread::scope(|s| {
+        let handles: Vec<_> = engines
+            .iter_mut()
+            .zip(runs.iter().zip(&job_events))
+            .map(|(engine, (run, job_events))| {
+                s.spawn(move || {
+                    let to_review = |k| run.contains(&k);
+                    review_some(engine, job_events, &to_review, target_actor, full, verbose)
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|h| {
+                h.join()
+                    .unwrap_or_else(|_| Err(anyhow!("review job panicked")))
+            })
+            .collect()
+    });
+
+    let mut reviewed = Vec::with_capacity(kyokus.len());
+    for (job, (result, run)) in results.into_iter().zip(&runs).enumerate() {
+        let review = result.with_context(|| format!("review job {} failed", job))?;
+        if review.kyokus.len() != run.len() {
+            return Err(anyhow!(
+                "review job {} reviewed {} kyokus, expected {}",
+                job,
+                review.kyokus.len(),
+                run.len(),
+            ));
+        }
+        reviewed.extend(review.kyokus);
+    }
+    Ok(reviewed)
+}
+
+/// Converts the log into mjai events to review leniently, warning about every
+/// diagnostic. Returns the events and the indices of the skipped kyokus.
+pub fn convert_lenient(log: &tenhou::Log) -> (Vec<Event>, Vec<usize>) {
+    let (events, diagnostics) = convlog::tenhou_to_mjai_lenient(log);
+
+    let mut skipped = vec![];
+    for diagnostic in &diagnostics {
+        log!("warning: {}", diagnostic);
+        if diagnostic.resolution == Resolution::SkippedKyoku {
+            skipped.push(diagnostic.kyoku_index);
+        }
+    }
+
+    (events, skipped)
+}
+
+/// Returns the ranges of events from `start_kyoku` to `end_kyoku` inclusive.
+pub(crate) fn kyoku_ranges(events: &[Event]) -> Vec<Range<usize>> {
+    let mut ranges = vec![];
+    let mut start = None;
+    for (i, event) in events.iter().enumerate() {
+        match event {
+            Event::StartKyoku { .. } => start = Some(i),
+            Event::EndKyoku => {
+                if let Some(start) = start.take() {
+                    ranges.push(start..i + 1);
+                }
+            }
+            _ => (),
+        }
+    }
+    ranges
+}
+
+/// Fills the yaku, fu, han and limit of a hora with the computed ones when
+/// the log does not carry them.
+fn with_hand_value(event: &Event, value: Option<HandValue>) -> Event {
+    let mut event = event.clone();
+    if let (
+        Event::Hora {
+            fu,
+            fan,
+            limit,
+            yakus,
+            ..
+        },
+        Some(value),
+    ) = (&mut event, value)
+    {
+        if yakus.is_none() {
+            *yakus = Some(value.yaku.into_iter().map(|y| (y.name, y.han)).collect());
+            *fu = Some(value.fu);
+            *fan = Some(value.han);
+            *limit = value.limit;
+        }
+    }
+    event
+}
+
 fn next_action_for_compare(events: &[Event]) -> &[Event] {
     match events[0] {
         Event::Dora { .. } => next_action_for_compare(&events[1..]),
@@ -317,10 +636,7 @@ fn next_action_exact(rough_action: &[Event], target_actor: u8) -> Vec<Event> {
         Event::Hora { .. } => vec![rough_action
             .iter()
             .take(3)
-            .find(|&a| match *a {
-                Event::Hora { actor, .. } if actor == target_actor => true,
-                _ => false,
-            })
+            .find(|&a| matches!(*a, Event::Hora { actor, .. } if actor == target_actor))
             .cloned()
             .unwrap_or(Event::None)],
 
@@ -1,18 +1,21 @@
 use crate::tehai::Tehai;
 
-use anyhow::anyhow;
 use anyhow::{Context, Result};
-use convlog::mjai::{Consumed2, Consumed3, Consumed4, Event};
-use convlog::Pai;
-use serde::Serialize;
+use convlog::board::Fuuro;
+use convlog::mjai::{Consumed3, Event};
+use convlog::shanten::{self, Ukeire};
+use convlog::{Pai, TileCounts};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Default, Serialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct State {
     #[serde(skip)]
     actor: u8,
 
     pub tehai: Tehai,
     pub fuuros: Vec<Fuuro>,
+    /// Number of nukidora (kita) declared, only in sanma.
+    pub nukidora: u8,
 }
 
 impl State {
@@ -24,6 +27,44 @@ impl State {
         }
     }
 
+    /// Returns the shanten and ukeire of the tehai after the actor makes
+    /// `moves`, or `None` if the moves do not end up waiting with a 3n+1
+    /// tehai, e.g. hora. `visible` is the pais visible outside of the tehai
+    /// before the moves, to which the pais the moves reveal are added.
+    pub fn ukeire_after(&self, moves: &[Event], visible: &TileCounts) -> Option<Ukeire> {
+        let mut counts = TileCounts::from(self.tehai.view());
+        let mut visible = *visible;
+        let mut num_fuuros = self.fuuros.len();
+
+        for event in moves {
+            match *event {
+                Event::Dahai { pai, .. } | Event::Kakan { pai, .. } => {
+                    counts.remove(pai);
+                    visible.add(pai);
+                }
+                Event::Chi { consumed, .. } | Event::Pon { consumed, .. } => {
+                    reveal_all(&mut counts, &mut visible, &consumed.0);
+                    num_fuuros += 1;
+                }
+                Event::Daiminkan { consumed, .. } => {
+                    reveal_all(&mut counts, &mut visible, &consumed.0);
+                    num_fuuros += 1;
+                }
+                Event::Ankan { consumed, .. } => {
+                    reveal_all(&mut counts, &mut visible, &consumed.0);
+                    num_fuuros += 1;
+                }
+                Event::Reach { .. } | Event::None => (),
+                _ => return None,
+            }
+        }
+
+        if counts.total() % 3 != 1 {
+            return None;
+        }
+        Some(shanten::ukeire(&counts, num_fuuros, &visible))
+    }
+
     /// Argument `event` must be either a
     ///
     /// * StartKyoku
@@ -34,6 +75,7 @@ impl State {
     /// * Kakan
     /// * Daiminkan
     /// * Ankan
+    /// * Nukidora
     ///
     /// and the `actor` must be the target actor.
     ///
@@ -43,6 +85,7 @@ impl State {
             Event::StartKyoku { tehais, .. } => {
                 self.tehai.haipai(&tehais[self.actor as usize]);
                 self.fuuros.clear();
+                self.nukidora = 0;
             }
 
             Event::Tsumo { actor, pai } if actor == self.actor => self.tehai.tsumo(pai),
@@ -136,7 +179,12 @@ impl State {
 
                         _ => None,
                     })
-                    .context(anyhow!("invalid state: previous Pon not found for Kakan"))?;
+                    .with_context(|| {
+                        format!(
+                            "invalid state: previous Pon not found for Kakan of {} in {}",
+                            pai, self.tehai,
+                        )
+                    })?;
 
                 let fuuro = Fuuro::Kakan {
                     pai,
@@ -154,6 +202,11 @@ impl State {
                 self.fuuros.push(fuuro);
             }
 
+            Event::Nukidora { actor, pai } if actor == self.actor => {
+                self.tehai.tedashi(pai);
+                self.nukidora += 1;
+            }
+
             _ => (),
         };
 
@@ -161,32 +214,11 @@ impl State {
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize)]
-#[serde(tag = "type")]
-#[serde(rename_all = "snake_case")]
-pub enum Fuuro {
-    Chi {
-        target: u8,
-        pai: Pai,
-        consumed: Consumed2,
-    },
-    Pon {
-        target: u8,
-        pai: Pai,
-        consumed: Consumed2,
-    },
-    Daiminkan {
-        target: u8,
-        pai: Pai,
-        consumed: Consumed3,
-    },
-    Kakan {
-        pai: Pai,
-        previous_pon_target: u8,
-        previous_pon_pai: Pai,
-        consumed: Consumed2,
-    },
-    Ankan {
-        consumed: Consumed4,
-    },
+/// Moves `pais` from the tehai to where everyone can see them.
+#[inline]
+fn reveal_all(counts: &mut TileCounts, visible: &mut TileCounts, pais: &[Pai]) {
+    for &pai in pais {
+        counts.remove(pai);
+        visible.add(pai);
+    }
 }
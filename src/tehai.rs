@@ -1,4 +1,9 @@
+use std::fmt;
+use std::str::FromStr;
+
+use convlog::notation::{self, Pais};
 use convlog::Pai;
+use serde::de::{Deserialize, Deserializer};
 use serde::ser::{Serialize, SerializeSeq, Serializer};
 
 #[derive(Debug, Clone, Default)]
@@ -26,6 +31,23 @@ impl From<Tehai> for Vec<Pai> {
     }
 }
 
+/// Parses from the compact notation, e.g. "123m406p789s1122z".
+impl FromStr for Tehai {
+    type Err = notation::ParseError;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from(s.parse::<Pais>()?.0))
+    }
+}
+
+impl fmt::Display for Tehai {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", notation::display(&self.inner))
+    }
+}
+
 impl Serialize for Tehai {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -39,6 +61,25 @@ impl Serialize for Tehai {
     }
 }
 
+/// Keeps the order as it is, where the last drawn pai may be at the end.
+impl<'de> Deserialize<'de> for Tehai {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        let inner = <Vec<String>>::deserialize(deserializer)?
+            .iter()
+            .map(|p| p.parse().map_err(Error::custom))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            inner,
+            is_sorted: false,
+        })
+    }
+}
+
 impl Tehai {
     /// Resets current tehai.
     #[inline]
@@ -69,7 +110,7 @@ impl Tehai {
 
         if let Ok(idx) = self
             .inner
-            .binary_search_by_key(&to_ord(pai), |&p| to_ord(p))
+            .binary_search_by_key(&pai.sort_key(), |&p| p.sort_key())
         {
             self.inner.remove(idx);
         }
@@ -85,17 +126,17 @@ impl Tehai {
         for &pai in pais {
             if let Ok(idx) = self
                 .inner
-                .binary_search_by_key(&to_ord(pai), |&p| to_ord(p))
+                .binary_search_by_key(&pai.sort_key(), |&p| p.sort_key())
             {
                 self.inner.remove(idx);
             }
         }
     }
 
-    /// Sort the pai. Aka pai will be before normal pai of the same sequence.
+    /// Sort the pai. Aka pai will be after normal pai of the same number.
     #[inline]
     fn sort(&mut self) {
-        self.inner.sort_unstable_by_key(|&pai| to_ord(pai));
+        self.inner.sort_unstable_by_key(|&pai| pai.sort_key());
         self.is_sorted = true;
     }
 
@@ -104,22 +145,3 @@ impl Tehai {
         &self.inner
     }
 }
-
-#[inline]
-fn to_ord(pai: Pai) -> u8 {
-    match pai {
-        Pai::AkaMan5 => 16,
-        Pai::AkaPin5 => 26,
-        Pai::AkaSou5 => 36,
-
-        _ => {
-            let id = pai.as_u8();
-
-            if 16 <= id && id < 20 || 26 <= id && id < 30 || 36 <= id && id < 40 {
-                id + 1
-            } else {
-                id
-            }
-        }
-    }
-}
@@ -0,0 +1,78 @@
+use std::process::Command;
+
+const BIN: &str = env!("CARGO_BIN_EXE_akochan-reviewer");
+const TESTDATA_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/convlog/tests/testdata");
+
+fn run(args: &[&str]) -> (bool, String) {
+    let output = Command::new(BIN)
+        .args(args)
+        .current_dir(TESTDATA_DIR)
+        .output()
+        .expect("failed to run akochan-reviewer");
+
+    (
+        output.status.success(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+#[test]
+fn test_in_format_with_tenhou_id() {
+    let (ok, stderr) = run(&[
+        "--in-format",
+        "mjlog",
+        "--tenhou-id",
+        "2019050417gm-0029-0000-4f2a8622",
+        "--actor",
+        "0",
+    ]);
+    assert!(!ok);
+    assert!(stderr.contains("--in-format"));
+}
+
+#[test]
+fn test_sanma_with_akochan() {
+    let (ok, stderr) = run(&["-i", "sanma.json", "--actor", "0", "--no-open"]);
+    assert!(!ok);
+    assert!(stderr.contains("akochan does not support sanma"));
+}
+
+#[test]
+fn test_akochan_flags_with_pipe() {
+    for flags in &[
+        &["--pt", "90,45,0,-135"][..],
+        &["--use-ranking-exp"],
+        &["-c", "tactics.json"],
+    ] {
+        let mut args = vec![
+            "-i",
+            "ranked_game.json",
+            "--actor",
+            "0",
+            "--no-open",
+            "--engine",
+            "pipe",
+            "--engine-cmd",
+            "cat",
+        ];
+        args.extend_from_slice(flags);
+        let (ok, stderr) = run(&args);
+        assert!(!ok);
+        assert!(stderr.contains("cannot be used with --engine pipe"));
+    }
+}
+
+#[test]
+fn test_engine_id_with_akochan() {
+    let (ok, stderr) = run(&[
+        "-i",
+        "ranked_game.json",
+        "--actor",
+        "0",
+        "--no-open",
+        "--engine-id",
+        "v1",
+    ]);
+    assert!(!ok);
+    assert!(stderr.contains("--engine-id is for --engine pipe only"));
+}
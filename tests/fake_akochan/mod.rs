@@ -0,0 +1,210 @@
+use akochan_reviewer::engine::ReviewEngine;
+use akochan_reviewer::review::DetailedAction;
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use convlog::mjai::Event;
+use convlog::{score, shanten, Board, TileCounts};
+use serde_json::{self as json, json, Value};
+
+/// A stand-in for `akochan pipe_detailed` that decides by simple rules
+/// instead of searching, so that a review is deterministic and needs no
+/// akochan:
+///
+/// * on its own tsumo, it agaris if there is a yaku, otherwise discards by
+///   the lowest shanten and then the most ukeire, giving the top 3;
+/// * it always passes on others' dahai and kakan.
+///
+/// Its `pt_exp_after` also counts the answers given in the game so far, so
+/// that its answers depend on the earlier kyokus as akochan's may.
+///
+/// It works on the lines of the protocol, so the JSON on both sides is
+/// exercised as well. Like `PipeEngine`, it cannot be started again before
+/// the game is finished or aborted.
+#[derive(Clone)]
+pub struct FakeAkochan {
+    target_actor: u8,
+    board: Board,
+    /// Lines answered but not read yet.
+    lines: VecDeque<String>,
+    started: bool,
+    /// Answers given in the game so far.
+    answered: u32,
+    /// How many more games to fail in the middle, shared with the clones.
+    failures: Arc<AtomicUsize>,
+}
+
+impl FakeAkochan {
+    pub fn new() -> Self {
+        Self {
+            target_actor: 0,
+            board: Board::new(),
+            lines: VecDeque::new(),
+            started: false,
+            answered: 0,
+            failures: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Fails the first `games` games started by it or its clones at the
+    /// first candidates, like akochan crashing.
+    pub fn failing(games: usize) -> Self {
+        let fake = Self::new();
+        fake.failures.store(games, Ordering::SeqCst);
+        fake
+    }
+
+    /// Takes a line of an mjai event and returns the line to answer, if the
+    /// target actor may act.
+    pub fn respond(&mut self, line: &str) -> Result<Option<String>> {
+        let event: Event = json::from_str(line).context("invalid event")?;
+        self.board
+            .update(&event)
+            .context("failed to update board")?;
+
+        let mut actions = match event {
+            Event::Tsumo { actor, .. } if actor == self.target_actor => self.on_tsumo(),
+            Event::Dahai { actor, .. } | Event::Kakan { actor, .. }
+                if actor != self.target_actor =>
+            {
+                vec![detailed(json!({"type": "none"}), 0.)]
+            }
+            _ => return Ok(None),
+        };
+
+        let history = f64::from(self.answered) * 0.001;
+        for action in &mut actions {
+            let pt_exp_after = &mut action["review"]["pt_exp_after"];
+            *pt_exp_after = json!(pt_exp_after.as_f64().unwrap_or_default() + history);
+        }
+        self.answered += 1;
+
+        Ok(Some(json::to_string(&actions)?))
+    }
+
+    fn on_tsumo(&self) -> Vec<Value> {
+        let actor = self.target_actor;
+        let player = &self.board.players[actor as usize];
+        let tsumo_pai = *player.tehai.last().expect("empty tehai");
+
+        let hora = json!({
+            "type": "hora",
+            "actor": actor,
+            "target": actor,
+            "pai": tsumo_pai,
+        });
+        let can_hora = json::from_value(hora.clone())
+            .ok()
+            .and_then(|event: Event| self.board.hora_context(&event))
+            .and_then(|ctx| score::calculate(&ctx))
+            .is_some();
+
+        if player.reach_accepted {
+            let tsumogiri = json!({
+                "type": "dahai",
+                "actor": actor,
+                "pai": tsumo_pai,
+                "tsumogiri": true,
+            });
+            return vec![detailed(if can_hora { hora } else { tsumogiri }, 0.)];
+        }
+
+        let mut actions = vec![];
+        if can_hora {
+            actions.push(detailed(hora, 100.));
+        }
+
+        let counts = TileCounts::from(player.tehai.as_slice());
+        let visible = self.board.visible_pais();
+        let candidates = shanten::discard_candidates(&counts, player.fuuros.len(), &visible);
+        for candidate in candidates.iter().take(3) {
+            // prefer the drawn one for tsumogiri, as it is the last.
+            let idx = player
+                .tehai
+                .iter()
+                .rposition(|p| p.deaka() == candidate.pai)
+                .expect("candidate not in tehai");
+            let dahai = json!({
+                "type": "dahai",
+                "actor": actor,
+                "pai": player.tehai[idx],
+                "tsumogiri": idx == player.tehai.len() - 1,
+            });
+            let ukeire = &candidate.ukeire;
+            let pt = f64::from(ukeire.total) - f64::from(ukeire.shanten) * 100.;
+            actions.push(detailed(dahai, pt));
+        }
+
+        actions
+    }
+}
+
+impl ReviewEngine for FakeAkochan {
+    fn name(&self) -> &str {
+        "fake-akochan"
+    }
+
+    fn start(&mut self, target_actor: u8) -> Result<()> {
+        if self.started {
+            return Err(anyhow!("fake-akochan is already started"));
+        }
+        self.target_actor = target_actor;
+        self.board = Board::new();
+        self.lines.clear();
+        self.answered = 0;
+        self.started = true;
+        Ok(())
+    }
+
+    fn feed(&mut self, event: &Event) -> Result<()> {
+        let line = json::to_string(event)?;
+        if let Some(answer) = self.respond(&line)? {
+            self.lines.push_back(answer);
+        }
+        Ok(())
+    }
+
+    fn candidates(&mut self) -> Result<Vec<DetailedAction>> {
+        let failed = self
+            .failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        if failed {
+            return Err(anyhow!("fake-akochan crashed"));
+        }
+
+        let line = self
+            .lines
+            .pop_front()
+            .context("failed to read from fake-akochan: unexpected EOF")?;
+        json::from_str(&line).context("failed to parse JSON output of fake-akochan")
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if !self.started {
+            return Err(anyhow!("fake-akochan is not started"));
+        }
+        self.started = false;
+        if !self.lines.is_empty() {
+            return Err(anyhow!("{} answers are never read", self.lines.len()));
+        }
+        Ok(())
+    }
+
+    fn abort(&mut self) {
+        self.started = false;
+    }
+}
+
+fn detailed(action: Value, pt_exp: f64) -> Value {
+    json!({
+        "moves": [action],
+        "review": {
+            "pt_exp_after": pt_exp,
+            "pt_exp_total": pt_exp,
+        },
+    })
+}
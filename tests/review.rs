@@ -0,0 +1,527 @@
+mod fake_akochan;
+#[path = "../convlog/tests/testdata/mod.rs"]
+mod testdata;
+
+use akochan_reviewer::batch::{batch_review, BatchOptions};
+use akochan_reviewer::cache::{self, ReviewCache};
+use akochan_reviewer::engine::ReviewEngine;
+use akochan_reviewer::metadata::{Metadata, Player};
+use akochan_reviewer::render::{GameSummary, IndexView, View};
+use akochan_reviewer::review::{review, review_parallel, KyokuReview, Review};
+use fake_akochan::FakeAkochan;
+
+use std::fs;
+use std::time::Duration;
+
+use convlog::mjai::Event;
+use convlog::*;
+use once_cell::sync::Lazy;
+use serde_json as json;
+use testdata::TESTDATA;
+
+/// Reviews of every testdata for every actor, which are shared as they take
+/// a while.
+static REVIEWS: Lazy<Vec<(tenhou::RawLog, u8, Review)>> = Lazy::new(|| {
+    TESTDATA
+        .iter()
+        .flat_map(|data| review_testdata(data, false))
+        .collect()
+});
+
+/// Reviews a testdata for every actor.
+fn review_testdata(data: &str, full: bool) -> Vec<(tenhou::RawLog, u8, Review)> {
+    let mut engine = FakeAkochan::new();
+    let raw_log: tenhou::RawLog = json::from_str(data).expect("failed to parse tenhou log");
+    let log = tenhou::Log::from(raw_log.clone());
+    let events = tenhou_to_mjai(&log).expect("failed to transform tenhou log");
+
+    (0..log.num_players())
+        .map(|actor| {
+            let review_result = review(&mut engine, &events, actor, full, false)
+                .unwrap_or_else(|e| panic!("failed to review actor {}: {:?}", actor, e));
+            assert_eq!(review_result.kyokus.len(), log.kyokus.len());
+            (raw_log.clone(), actor, review_result)
+        })
+        .collect()
+}
+
+#[test]
+fn test_review() {
+    let mut total_entries = 0;
+
+    for (_, actor, review_result) in REVIEWS.iter() {
+        let actor = *actor;
+        assert!(review_result.total_entries <= review_result.total_reviewed);
+
+        let entries: Vec<_> = review_result
+            .kyokus
+            .iter()
+            .flat_map(|k| &k.entries)
+            .collect();
+        assert_eq!(entries.len(), review_result.total_entries);
+        total_entries += entries.len();
+
+        for kyoku in &review_result.kyokus {
+            assert!(!kyoku.end_status.is_empty());
+            assert!(kyoku
+                .end_status
+                .iter()
+                .all(|e| matches!(e, Event::Hora { .. } | Event::Ryukyoku { .. })));
+        }
+
+        for entry in entries {
+            assert_ne!(entry.expected, entry.actual);
+            assert!(!entry.details.is_empty());
+            assert_eq!(entry.details[0].moves, entry.expected);
+            // the fake only agaris or discards on its own tsumo, and passing
+            // alone is never reviewed.
+            match entry.expected[0] {
+                Event::Dahai { actor: a, .. } | Event::Hora { actor: a, .. } => {
+                    assert_eq!(a, actor)
+                }
+                ref ev => panic!("unexpected move {:?}", ev),
+            }
+        }
+    }
+
+    assert!(total_entries > 0);
+}
+
+#[test]
+fn test_review_full() {
+    for (_, _, review_result) in review_testdata(TESTDATA[0], true) {
+        assert_eq!(review_result.total_entries, review_result.total_reviewed);
+    }
+}
+
+#[test]
+fn test_review_deterministic() {
+    let a: Vec<_> = review_testdata(TESTDATA[0], false)
+        .into_iter()
+        .map(|(_, _, r)| json::to_string(&r.kyokus).unwrap())
+        .collect();
+    let b: Vec<_> = REVIEWS
+        .iter()
+        .take(a.len())
+        .map(|(_, _, r)| json::to_string(&r.kyokus).unwrap())
+        .collect();
+    assert_eq!(a, b);
+}
+
+#[test]
+fn test_review_parallel() {
+    for (i, (raw_log, actor, expected)) in REVIEWS.iter().enumerate() {
+        let log = tenhou::Log::from(raw_log.clone());
+        let events = tenhou_to_mjai(&log).expect("failed to transform tenhou log");
+        let expected_kyokus = json::to_string(&expected.kyokus).unwrap();
+
+        // also more jobs than kyokus, for one of them.
+        let jobs_list: &[usize] = if i == 0 { &[3, 100] } else { &[3] };
+        for &jobs in jobs_list {
+            let mut engines: Vec<_> = (0..jobs).map(|_| FakeAkochan::new()).collect();
+            let review_result = review_parallel(&mut engines, &events, *actor, false, false)
+                .expect("failed to review in parallel");
+
+            assert_eq!(review_result.total_reviewed, expected.total_reviewed);
+            assert_eq!(review_result.total_entries, expected.total_entries);
+            assert_eq!(
+                json::to_string(&review_result.kyokus).unwrap(),
+                expected_kyokus,
+            );
+        }
+    }
+}
+
+#[test]
+fn test_review_after_failure() {
+    let (raw_log, actor, expected) = &REVIEWS[0];
+    let log = tenhou::Log::from(raw_log.clone());
+    let events = tenhou_to_mjai(&log).expect("failed to transform tenhou log");
+    let mut engine = FakeAkochan::new();
+
+    // cut right after the first tsumo of the actor, which fails the review
+    // with the engine in the middle of the game.
+    let first_tsumo = events
+        .iter()
+        .position(|ev| matches!(*ev, Event::Tsumo { actor: a, .. } if a == *actor))
+        .unwrap();
+    let cut = &events[..first_tsumo + 2];
+    assert!(review(&mut engine, cut, *actor, false, false).is_err());
+
+    let review_result = review(&mut engine, &events, *actor, false, false)
+        .expect("failed to review after a failed one");
+    assert_eq!(
+        json::to_string(&review_result.kyokus).unwrap(),
+        json::to_string(&expected.kyokus).unwrap(),
+    );
+}
+
+#[test]
+fn test_review_cache() {
+    let dir = tempfile::tempdir().expect("failed to create temp dir");
+    let cache = ReviewCache::new(dir.path(), "fake-akochan");
+    let mut engines = vec![FakeAkochan::new(), FakeAkochan::new()];
+
+    for (raw_log, actor, expected) in REVIEWS.iter().take(4) {
+        let log = tenhou::Log::from(raw_log.clone());
+        let mut events = tenhou_to_mjai(&log).expect("failed to transform tenhou log");
+        let expected_kyokus = json::to_string(&expected.kyokus).unwrap();
+
+        // the first one misses and the second one hits with no engine to
+        // review with, with the same result.
+        for engines in [&mut engines[..], &mut []] {
+            let review_result = cache
+                .review(engines, &events, *actor, false, false)
+                .expect("failed to review with cache");
+            assert_eq!(review_result.total_reviewed, expected.total_reviewed);
+            assert_eq!(review_result.total_entries, expected.total_entries);
+            assert_eq!(
+                json::to_string(&review_result.kyokus).unwrap(),
+                expected_kyokus,
+            );
+        }
+
+        // a changed kyoku is reviewed again, while the others are served from
+        // the cache.
+        let kyoku_ends: Vec<_> = events
+            .iter()
+            .enumerate()
+            .filter(|(_, ev)| matches!(ev, Event::EndKyoku))
+            .map(|(i, _)| i)
+            .collect();
+        let num_kyokus = kyoku_ends.len();
+        let last_kyoku = num_kyokus - 1;
+        set_deltas(&mut events[kyoku_ends[last_kyoku] - 1], [1, 2, 3, 4]);
+        let kept = cache::prune(dir.path(), Some(Duration::from_secs(60 * 60)))
+            .unwrap()
+            .kept;
+        let review_result = cache
+            .review(&mut engines, &events, *actor, false, false)
+            .expect("failed to review with cache");
+        assert_eq!(review_result.kyokus.len(), expected.kyokus.len());
+        assert_eq!(
+            json::to_string(&review_result.kyokus[..last_kyoku]).unwrap(),
+            json::to_string(&expected.kyokus[..last_kyoku]).unwrap(),
+        );
+        let stats = cache::prune(dir.path(), Some(Duration::from_secs(60 * 60))).unwrap();
+        assert_eq!(stats.kept, kept + 1);
+
+        // so are the ones after it, as their reviews may depend on it.
+        set_deltas(&mut events[kyoku_ends[0] - 1], [5, 6, 7, 8]);
+        let kept = stats.kept;
+        let review_result = cache
+            .review(&mut engines, &events, *actor, false, false)
+            .expect("failed to review with cache");
+        assert_eq!(
+            json::to_string(&review_result.kyokus[1..]).unwrap(),
+            json::to_string(&expected.kyokus[1..]).unwrap(),
+        );
+        let stats = cache::prune(dir.path(), Some(Duration::from_secs(60 * 60))).unwrap();
+        assert_eq!(stats.kept, kept + num_kyokus);
+    }
+
+    let stats = cache::prune(dir.path(), Some(Duration::from_secs(60 * 60))).unwrap();
+    assert_eq!(stats.removed, 0);
+    assert!(stats.kept > 0);
+    let kept = stats.kept;
+
+    let stats = cache::prune(dir.path(), None).unwrap();
+    assert_eq!(stats.removed, kept);
+    assert!(stats.removed_bytes > 0);
+    assert_eq!(stats.kept, 0);
+}
+
+fn set_deltas(event: &mut Event, new_deltas: [i32; 4]) {
+    match event {
+        Event::Hora { deltas, .. } | Event::Ryukyoku { deltas, .. } => {
+            *deltas = Some(new_deltas);
+        }
+        ev => panic!("unexpected end of kyoku {:?}", ev),
+    }
+}
+
+#[test]
+fn test_prune_cache_leaves_others() {
+    let dir = tempfile::tempdir().expect("failed to create temp dir");
+    let key = "ab".repeat(32);
+    let sub_dir = dir.path().join("ab");
+    let entries = [
+        sub_dir.join(format!("{}.json", key)),
+        sub_dir.join(format!("{}.json.tmp", key)),
+    ];
+    let others = [
+        dir.path().join("notes.txt"),
+        dir.path().join("photos").join(format!("{}.json", key)),
+        sub_dir.join("notes.json"),
+        sub_dir.join(format!("{}.JSON", key)),
+        sub_dir.join("nested").join(format!("{}.json", key)),
+    ];
+    for path in entries.iter().chain(&others) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "{}").unwrap();
+    }
+
+    let stats = cache::prune(dir.path(), None).expect("failed to prune cache");
+    assert_eq!(stats.removed, entries.len());
+    assert_eq!(stats.kept, 0);
+    assert!(entries.iter().all(|p| !p.exists()));
+    assert!(others.iter().all(|p| p.exists()));
+}
+
+#[test]
+fn test_review_cache_key() {
+    let dir = tempfile::tempdir().expect("failed to create temp dir");
+    let (raw_log, actor, _) = &REVIEWS[0];
+    let log = tenhou::Log::from(raw_log.clone());
+    let events = tenhou_to_mjai(&log).expect("failed to transform tenhou log");
+    let mut engines = vec![FakeAkochan::new()];
+
+    let mut review_with = |engine_id: &str, actor: u8, full: bool| {
+        ReviewCache::new(dir.path(), engine_id)
+            .review(&mut engines, &events, actor, full, false)
+            .expect("failed to review with cache");
+        cache::prune(dir.path(), Some(Duration::from_secs(60 * 60)))
+            .unwrap()
+            .kept
+    };
+
+    let n = review_with("a", *actor, false);
+    assert_eq!(n, log.kyokus.len());
+    assert_eq!(review_with("a", *actor, false), n);
+    assert_eq!(review_with("b", *actor, false), n * 2);
+    assert_eq!(review_with("a", (*actor + 1) % 4, false), n * 3);
+    assert_eq!(review_with("a", *actor, true), n * 4);
+}
+
+#[test]
+fn test_engine_id() {
+    let dir = tempfile::tempdir().expect("failed to create temp dir");
+    let exe = dir.path().join("system.exe");
+    std::fs::write(&exe, "a").unwrap();
+
+    let a = json::json!({"a": 1, "b": [1, {"c": 2, "d": 3}]});
+    let b = json::json!({"b": [1, {"d": 3, "c": 2}], "a": 1});
+    let id = cache::engine_id("akochan", &[&exe], Some(&a)).unwrap();
+    assert_eq!(id, cache::engine_id("akochan", &[&exe], Some(&b)).unwrap());
+    assert_ne!(id, cache::engine_id("akochan", &[&exe], None).unwrap());
+
+    std::fs::write(&exe, "b").unwrap();
+    assert_ne!(id, cache::engine_id("akochan", &[&exe], Some(&a)).unwrap());
+    assert!(cache::engine_id("akochan", &[&dir.path().join("missing")], None).is_err());
+}
+
+#[test]
+fn test_kyoku_review_json() {
+    for (_, _, review_result) in REVIEWS.iter() {
+        let kyokus_json = json::to_string(&review_result.kyokus).unwrap();
+        let kyokus: Vec<KyokuReview> = json::from_str(&kyokus_json).expect("failed to deserialize");
+        assert_eq!(json::to_string(&kyokus).unwrap(), kyokus_json);
+    }
+}
+
+#[test]
+fn test_render() {
+    let engine = FakeAkochan::new();
+
+    for (raw_log, actor, review_result) in REVIEWS.iter() {
+        let actor = *actor;
+        let log = tenhou::Log::from(raw_log.clone());
+        let meta = Metadata {
+            engine: engine.name(),
+            pt: Some(&[90, 45, 0, -135]),
+            game_length: &log.rule.to_string(),
+            tenhou_id: None,
+            players: log
+                .names
+                .iter()
+                .zip(&log.players)
+                .take(log.num_players() as usize)
+                .map(|(name, player)| Player {
+                    name,
+                    dan: player.dan.as_deref(),
+                    rate: player.rate,
+                })
+                .collect(),
+            lobby: &log.lobby,
+            parse_time: Duration::from_millis(1),
+            convert_time: Duration::from_millis(1),
+            review_time: Duration::from_millis(1),
+            total_reviewed: review_result.total_reviewed,
+            total_entries: review_result.total_entries,
+            version: "test",
+        };
+
+        let view = View::new(
+            &review_result.kyokus,
+            actor,
+            log.num_players(),
+            &meta,
+            Some(raw_log.split_by_kyoku()),
+        );
+
+        let mut html = vec![];
+        view.render(&mut html).expect("failed to render");
+        let html = String::from_utf8(html).expect("invalid UTF-8");
+        assert!(html.contains("<!DOCTYPE html>"));
+        assert!(html.contains(&log.names[actor as usize]));
+        if review_result.total_entries > 0 {
+            assert!(html.contains("fake-akochan の最善手"));
+        }
+
+        let view_json = json::to_value(&view).expect("failed to serialize view");
+        assert_eq!(view_json["target_actor"], actor);
+        assert_eq!(
+            view_json["kyokus"].as_array().unwrap().len(),
+            review_result.kyokus.len(),
+        );
+    }
+
+    // without the log viewer and pt.
+    let (raw_log, actor, review_result) = &REVIEWS[0];
+    let log = tenhou::Log::from(raw_log.clone());
+    let meta = Metadata {
+        engine: engine.name(),
+        pt: None,
+        game_length: "東風",
+        tenhou_id: Some("2019050417gm-0029-0000-4f2a8622"),
+        players: vec![],
+        lobby: &log.lobby,
+        parse_time: Duration::default(),
+        convert_time: Duration::default(),
+        review_time: Duration::default(),
+        total_reviewed: review_result.total_reviewed,
+        total_entries: review_result.total_entries,
+        version: "test",
+    };
+    let view = View::new(
+        &review_result.kyokus,
+        *actor,
+        log.num_players(),
+        &meta,
+        None::<Vec<tenhou::RawPartialLog>>,
+    );
+    let mut html = vec![];
+    view.render(&mut html).expect("failed to render");
+    let html = String::from_utf8(html).expect("invalid UTF-8");
+    assert!(html.contains("2019050417gm-0029-0000-4f2a8622"));
+    assert!(!html.contains("<dt>pt</dt>"));
+}
+
+#[test]
+fn test_index() {
+    let mut games = vec![];
+    for (i, (raw_log, actor, review_result)) in REVIEWS.iter().enumerate() {
+        if let Some(agreement_rate) = review_result.agreement_rate() {
+            assert!((0. ..=1.).contains(&agreement_rate));
+        } else {
+            assert_eq!(review_result.total_reviewed, 0);
+        }
+        assert!(review_result.ev_loss() >= 0.);
+
+        let log = tenhou::Log::from(raw_log.clone());
+        let tenhou_id = format!("game-{}", i);
+        let report = format!("{}&tw={}.html", tenhou_id, actor);
+        games.push(GameSummary::new(
+            Some(&tenhou_id),
+            &log.names[*actor as usize],
+            &report,
+            review_result,
+        ));
+    }
+    // anonymized ones are labeled by the position instead.
+    let (_, _, review_result) = &REVIEWS[0];
+    games.push(GameSummary::new(
+        None,
+        "Aさん",
+        "game-secret.html",
+        review_result,
+    ));
+    let anonymized_label = format!(">#{}</a>", games.len());
+    let error = anyhow::anyhow!("failed to download");
+    games.push(GameSummary::failed(Some("game-broken"), &error));
+
+    // the actual discard is often among the top 3 of the fake.
+    assert!(games.iter().map(|g| g.ev_loss).sum::<f64>() > 0.);
+
+    let view = IndexView::new(&games, "test");
+    let mut html = vec![];
+    view.render(&mut html).expect("failed to render");
+    let html = String::from_utf8(html).expect("invalid UTF-8");
+
+    assert!(html.contains("game-0%26tw%3D0.html"));
+    assert!(html.contains("failed to download"));
+    assert!(html.contains("game-secret.html"));
+    assert!(html.contains(&anonymized_label));
+    assert!(html.contains(&format!("{} 戦", REVIEWS.len() + 1)));
+}
+
+#[test]
+fn test_entry_ev_loss() {
+    let entry = REVIEWS
+        .iter()
+        .flat_map(|(_, _, r)| r.kyokus.iter().flat_map(|k| &k.entries))
+        .find(|e| match e.actual[..] {
+            [Event::Dahai { pai, .. }] => e.details[1..]
+                .iter()
+                .any(|d| matches!(d.moves[..], [Event::Dahai { pai: p, .. }] if p == pai)),
+            _ => false,
+        })
+        .expect("no entry discarding a candidate other than the best");
+    let ev_loss = entry.ev_loss().expect("the actual move is not found");
+    assert!(ev_loss >= 0.);
+
+    // tsumogiri does not matter.
+    let mut entry = entry.clone();
+    if let Event::Dahai { tsumogiri, .. } = &mut entry.actual[0] {
+        *tsumogiri = !*tsumogiri;
+    }
+    assert_eq!(entry.ev_loss(), Some(ev_loss));
+
+    // neither a move that is no candidate nor an innocent one is taken as any
+    // of them.
+    entry.actual = vec![Event::Ryukyoku {
+        deltas: None,
+        reason: None,
+        tenpais: None,
+    }];
+    assert_eq!(entry.ev_loss(), None);
+    entry.actual = vec![Event::None];
+    assert_eq!(entry.ev_loss(), None);
+}
+
+#[test]
+fn test_batch_review_after_failure() {
+    let dir = tempfile::tempdir().expect("failed to create temp dir");
+    let ids_file = dir.path().join("ids.txt");
+    let failed_id = "2019050417gm-0029-0000-00000001";
+    let reviewed_id = "2019050417gm-0029-0000-00000002";
+    fs::write(&ids_file, format!("{} 0\n{} 0\n", failed_id, reviewed_id)).unwrap();
+
+    // the engine crashes in the first game only.
+    let opts = BatchOptions {
+        engine: FakeAkochan::failing(1),
+        jobs: 2,
+        cache: None,
+        pt: None,
+        actor: None,
+        player_name: None,
+        kyokus: None,
+        anonymizer: None,
+        sanma: true,
+        full: false,
+        verbose: false,
+        version: "test",
+    };
+    let fetch = |_: &str| Ok(json::from_str(TESTDATA[0])?);
+    let index_path =
+        batch_review(dir.path(), &ids_file, fetch, &opts).expect("failed to batch review");
+
+    let index = fs::read_to_string(index_path).expect("failed to read index page");
+    assert!(index.contains("fake-akochan crashed"));
+    assert!(!dir.path().join(format!("{}&tw=0.html", failed_id)).exists());
+    assert!(dir
+        .path()
+        .join(format!("{}&tw=0.html", reviewed_id))
+        .exists());
+    assert!(index.contains("1 戦"));
+}
@@ -0,0 +1,46 @@
+use akochan_reviewer::state::State;
+
+use convlog::mjai::Event;
+use convlog::notation::Pais;
+use convlog::TileCounts;
+use serde_json as json;
+
+fn state(tehai: &str) -> State {
+    let mut state = State::new(0);
+    state.tehai = tehai.parse().unwrap();
+    state
+}
+
+fn events(lines: &[&str]) -> Vec<Event> {
+    lines.iter().map(|l| json::from_str(l).unwrap()).collect()
+}
+
+fn left(state: &State, moves: &[Event]) -> Vec<(String, u8)> {
+    let visible = TileCounts::from(&"1p".parse::<Pais>().unwrap()[..]);
+    state
+        .ukeire_after(moves, &visible)
+        .expect("not waiting")
+        .pais
+        .iter()
+        .map(|p| (p.pai.to_string(), p.left))
+        .collect()
+}
+
+#[test]
+fn test_ukeire_after_discard_from_pair() {
+    // the discarded one of the pair is gone for the tanki.
+    let moves = events(&[r#"{"type":"dahai","actor":0,"pai":"P","tsumogiri":false}"#]);
+    let left = left(&state("123m456p789s234s55z"), &moves);
+    assert_eq!(left, [("P".to_owned(), 2)]);
+}
+
+#[test]
+fn test_ukeire_after_chi() {
+    // 2m of the chi is gone for the nobetan.
+    let moves = events(&[
+        r#"{"type":"chi","actor":0,"target":3,"pai":"1m","consumed":["2m","3m"]}"#,
+        r#"{"type":"dahai","actor":0,"pai":"E","tsumogiri":false}"#,
+    ]);
+    let left = left(&state("223345m456p789s1z"), &moves);
+    assert_eq!(left, [("2m".to_owned(), 2), ("5m".to_owned(), 3)]);
+}